@@ -0,0 +1,223 @@
+// Bevy hands system parameters over by value, and tile sizes are far smaller than where a cast to
+// `f32` could lose a value.
+#![allow(clippy::needless_pass_by_value, clippy::cast_precision_loss)]
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{
+    index_to_world_position, smooth_path, world_position_to_index, ClearEvent, GameState,
+    LoadMapEvent, Map, Position, SpawnAgentEvent, TILE_SIZE,
+};
+
+/// Tiles per second an agent walks unless told otherwise.
+pub const DEFAULT_AGENT_SPEED: f32 = 4.0;
+
+/// Radius of an agent's collider, in tiles.
+pub const AGENT_RADIUS: f32 = 0.3;
+
+/// How quickly the steering force closes the gap between the velocity an agent has and the one it
+/// wants, per second.
+pub const STEERING_GAIN: f32 = 8.0;
+
+/// The largest steering force, in tiles per second squared (agents have a mass of 1).
+pub const MAX_STEERING_FORCE: f32 = 40.0;
+
+/// How close, in tiles, an agent has to get to a waypoint before it heads for the next one.
+pub const WAYPOINT_RADIUS: f32 = 0.4;
+
+/// How close, in tiles, an agent has to get to its goal to have arrived. It slows down within a
+/// tile of it.
+pub const ARRIVAL_RADIUS: f32 = 0.1;
+
+/// === Components ===
+/// A dynamic body steered along a path to its goal, one waypoint at a time.
+#[derive(Component, Debug)]
+pub struct Agent {
+    /// Tiles per second.
+    pub speed: f32,
+    pub goal: Position,
+    /// The waypoints to walk through, in the same form as `GameState::path`.
+    pub path: Vec<Position>,
+    /// Index into `path` of the waypoint it is walking to.
+    pub next: usize,
+    pub arrived: bool,
+}
+
+impl Agent {
+    #[must_use]
+    pub fn new(speed: f32, goal: Position, path: Vec<Position>) -> Agent {
+        Agent {
+            speed,
+            goal,
+            path,
+            next: 0,
+            arrived: false,
+        }
+    }
+
+    /// Whether the rest of the path, from the tile the agent is on, still leads to the goal
+    /// without crossing a blocked tile.
+    #[must_use]
+    pub fn path_is_clear(&self, map: &Map, from: Position) -> bool {
+        if self.path.last() != Some(&self.goal) {
+            return false;
+        }
+        let rest = self.path.get(self.next..).unwrap_or_default();
+        std::iter::once(&from)
+            .chain(rest)
+            .collect::<Vec<_>>()
+            .windows(2)
+            .all(|pair| map.line_of_sight(pair[0], pair[1]))
+    }
+}
+
+/// === Helper Functions ===
+/// A path from `from` to `goal` with the algorithm and smoothing picked in the UI.
+#[must_use]
+pub fn request_path(
+    map: &Map,
+    game_state: &GameState,
+    from: Position,
+    goal: Position,
+) -> Option<Vec<Position>> {
+    let path = game_state.pathfinder().find_path(map, from, goal).path?;
+    Some(smooth_path(map, &path, game_state.smoothing).waypoints)
+}
+
+/// === Systems ===
+// Walks from the start along the path on screen, or a new one if nothing is solved yet
+pub fn spawn_agent_system(
+    mut spawn_agent_event_reader: EventReader<SpawnAgentEvent>,
+    game_state: Res<GameState>,
+    map: Res<Map>,
+    mut commands: Commands,
+) {
+    for _ in spawn_agent_event_reader.iter() {
+        let path = if game_state.path.is_empty() {
+            request_path(&map, &game_state, game_state.start, game_state.goal)
+        } else {
+            Some(game_state.path.clone())
+        };
+        let Some(path) = path else {
+            println!("No Path Found!");
+            continue;
+        };
+
+        let position = index_to_world_position(game_state.start.0, game_state.start.1);
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(0.9, 0.2, 0.6),
+                    custom_size: Some(Vec2::splat(TILE_SIZE as f32 * 0.6)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(position.x, position.y, 4.0),
+                ..default()
+            })
+            .insert(Name::new("Agent"))
+            .insert(Agent::new(DEFAULT_AGENT_SPEED, game_state.goal, path))
+            .insert(RigidBody::Dynamic)
+            .insert(Collider::ball(AGENT_RADIUS * TILE_SIZE as f32))
+            .insert(ColliderMassProperties::Mass(1.0))
+            .insert(LockedAxes::ROTATION_LOCKED)
+            .insert(Velocity::default())
+            .insert(ExternalForce::default());
+    }
+}
+
+// Seek the next waypoint, and arrive at the last one: the force turns the velocity towards the one
+// wanted, and Rapier moves the body and keeps it out of obstacles and other agents
+pub fn agent_steering_system(
+    mut agent_query: Query<(&mut Agent, &Transform, &mut Velocity, &mut ExternalForce)>,
+) {
+    let tile = TILE_SIZE as f32;
+    for (mut agent, transform, mut velocity, mut force) in &mut agent_query {
+        force.force = Vec2::ZERO;
+        if agent.arrived {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        let mut offset = Vec2::ZERO;
+        while let Some(&waypoint) = agent.path.get(agent.next) {
+            offset = index_to_world_position(waypoint.0, waypoint.1) - position;
+            let last = agent.next + 1 == agent.path.len();
+            if last || offset.length() > WAYPOINT_RADIUS * tile {
+                break;
+            }
+            agent.next += 1;
+        }
+        if agent.next >= agent.path.len() {
+            // no way on, stand still until it gets a new path
+            velocity.linvel = Vec2::ZERO;
+            continue;
+        }
+
+        let last = agent.next + 1 == agent.path.len();
+        if last && offset.length() < ARRIVAL_RADIUS * tile {
+            velocity.linvel = Vec2::ZERO;
+            if agent.path.last() == Some(&agent.goal) {
+                agent.arrived = true;
+                println!("Agent arrived at {:?}", agent.goal);
+            }
+            continue;
+        }
+        let mut speed = agent.speed * tile;
+        if last {
+            speed *= (offset.length() / tile).min(1.0);
+        }
+        let desired = offset.normalize_or_zero() * speed;
+        force.force = ((desired - velocity.linvel) * STEERING_GAIN)
+            .clamp_length_max(MAX_STEERING_FORCE * tile);
+    }
+}
+
+// Once the map changes, agents whose way on is blocked ask for a new path from where they are
+pub fn agent_replan_system(
+    mut agent_query: Query<(&mut Agent, &Transform)>,
+    game_state: Res<GameState>,
+    map: Res<Map>,
+) {
+    if !map.is_changed() {
+        return;
+    }
+    for (mut agent, transform) in &mut agent_query {
+        if agent.arrived {
+            continue;
+        }
+        let (x, y) = world_position_to_index(transform.translation.truncate());
+        if map.outside(x, y) {
+            // squeezed past the map border, there is no tile to plan from
+            continue;
+        }
+        let here = Position(x, y);
+        if agent.path_is_clear(&map, here) {
+            continue;
+        }
+        let goal = agent.goal;
+        if let Some(path) = request_path(&map, &game_state, here, goal) {
+            agent.path = path;
+        } else {
+            // stand still until the map opens up again
+            println!("Agent at {here:?} has no path to {goal:?}");
+            agent.path = vec![here];
+        }
+        agent.next = 0;
+    }
+}
+
+pub fn despawn_agents_system(
+    mut clear_event_reader: EventReader<ClearEvent>,
+    mut load_map_event_reader: EventReader<LoadMapEvent>,
+    agent_query: Query<Entity, With<Agent>>,
+    mut commands: Commands,
+) {
+    let cleared = clear_event_reader.iter().last().is_some();
+    let loaded = load_map_event_reader.iter().last().is_some();
+    if !cleared && !loaded {
+        return;
+    }
+    for entity in agent_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
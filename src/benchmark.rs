@@ -0,0 +1,191 @@
+use std::time::Duration;
+
+use crate::{solve, AraStar, Map, Pathfinder, PathfindingAlgorithm, Position};
+
+/// One algorithm on one start/goal pair.
+#[derive(Clone, Debug)]
+pub struct BenchmarkRun {
+    pub algorithm: PathfindingAlgorithm,
+    /// Index into the pairs that were run.
+    pub pair: usize,
+    pub start: Position,
+    pub goal: Position,
+    pub elapsed: Duration,
+    pub expanded: usize,
+    pub generated: usize,
+    /// Cost of the path found, in `STRAIGHT_COST` units.
+    pub cost: Option<i32>,
+    /// Cost of the shortest path, as found by Dijkstra.
+    pub optimal_cost: Option<i32>,
+}
+
+impl BenchmarkRun {
+    /// How much more the path costs than the shortest one, `0.0` when it is optimal.
+    #[must_use]
+    pub fn gap(&self) -> Option<f64> {
+        match (self.cost, self.optimal_cost) {
+            (Some(cost), Some(optimal)) if optimal > 0 => {
+                Some(f64::from(cost - optimal) / f64::from(optimal))
+            }
+            (Some(_), Some(_)) => Some(0.0),
+            _ => None,
+        }
+    }
+}
+
+/// The runs of one algorithm, added up.
+#[derive(Clone, Debug)]
+pub struct BenchmarkSummary {
+    pub algorithm: PathfindingAlgorithm,
+    pub runs: usize,
+    pub solved: usize,
+    /// Runs whose path costs as much as the shortest one.
+    pub optimal: usize,
+    pub total_elapsed: Duration,
+    pub mean_expanded: f64,
+    pub mean_gap: f64,
+    pub max_gap: f64,
+}
+
+/// Picks `count` start/goal pairs of walkable tiles that are connected, so every pair has an
+/// optimal cost to compare against. The same `seed` always gives the same pairs.
+#[must_use]
+pub fn random_pairs(map: &Map, count: usize, seed: u64) -> Vec<(Position, Position)> {
+    let mut state = seed;
+    let mut random_tile = || {
+        let value = split_mix(&mut state);
+        let x = (value % map.width as u64) as i32;
+        let y = ((value >> 32) % map.height as u64) as i32;
+        Position(x, y)
+    };
+    let mut pairs = vec![];
+    // Gives up on maps with too few connected tiles instead of looping forever
+    let mut attempts = 0;
+    while pairs.len() < count && attempts < count * 100 {
+        attempts += 1;
+        let start = random_tile();
+        let goal = random_tile();
+        if start == goal
+            || map.is_blocked(start.x(), start.y())
+            || map.is_blocked(goal.x(), goal.y())
+        {
+            continue;
+        }
+        if solve(map, PathfindingAlgorithm::AStar, start, goal)
+            .path
+            .is_some()
+        {
+            pairs.push((start, goal));
+        }
+    }
+    pairs
+}
+
+/// Solves every pair with every algorithm in `algorithms`, and with Dijkstra for the optimal
+/// costs. HPA* is skipped on a map without a hierarchy, which it would build for every pair, and
+/// ARA* runs without a time budget, see `repeatable_pathfinder`.
+#[must_use]
+pub fn run_benchmark(
+    map: &Map,
+    pairs: &[(Position, Position)],
+    algorithms: &[PathfindingAlgorithm],
+) -> Vec<BenchmarkRun> {
+    let optimal_costs: Vec<Option<i32>> = pairs
+        .iter()
+        .map(|&(start, goal)| solve(map, PathfindingAlgorithm::Dijkstra, start, goal).cost)
+        .collect();
+
+    let mut runs = vec![];
+    for &algorithm in skip_unprepared(map, algorithms) {
+        for (index, &(start, goal)) in pairs.iter().enumerate() {
+            let result = repeatable_pathfinder(algorithm).find_path(map, start, goal);
+            runs.push(BenchmarkRun {
+                algorithm,
+                pair: index,
+                start,
+                goal,
+                elapsed: result.elapsed,
+                expanded: result.expanded_count(),
+                generated: result.generated_count(),
+                cost: result.cost,
+                optimal_cost: optimal_costs[index],
+            });
+        }
+    }
+    runs
+}
+
+/// The `Pathfinder` of `algorithm`, with ARA* lowering its weight all the way to `1.0` instead of
+/// stopping after `ARA_TIME_BUDGET`. Otherwise its paths would depend on how fast the machine is
+/// and vary from run to run.
+#[must_use]
+pub fn repeatable_pathfinder(algorithm: PathfindingAlgorithm) -> Box<dyn Pathfinder> {
+    match algorithm {
+        PathfindingAlgorithm::AraStar => Box::new(AraStar {
+            time_budget: Duration::MAX,
+            ..AraStar::default()
+        }),
+        _ => algorithm.pathfinder(),
+    }
+}
+
+/// `algorithms` without HPA* when `map` has no hierarchy.
+pub(crate) fn skip_unprepared<'a>(
+    map: &'a Map,
+    algorithms: &'a [PathfindingAlgorithm],
+) -> impl Iterator<Item = &'a PathfindingAlgorithm> {
+    algorithms
+        .iter()
+        .filter(move |algorithm| **algorithm != PathfindingAlgorithm::Hpa || map.has_hierarchy())
+}
+
+/// Adds up the runs of each algorithm, in the order the algorithms first appear.
+#[must_use]
+pub fn summarize(runs: &[BenchmarkRun]) -> Vec<BenchmarkSummary> {
+    let mut algorithms: Vec<PathfindingAlgorithm> = vec![];
+    for run in runs {
+        if !algorithms.contains(&run.algorithm) {
+            algorithms.push(run.algorithm);
+        }
+    }
+
+    algorithms
+        .into_iter()
+        .map(|algorithm| {
+            let runs: Vec<&BenchmarkRun> = runs
+                .iter()
+                .filter(|run| run.algorithm == algorithm)
+                .collect();
+            let gaps: Vec<f64> = runs.iter().filter_map(|run| run.gap()).collect();
+            BenchmarkSummary {
+                algorithm,
+                runs: runs.len(),
+                solved: runs.iter().filter(|run| run.cost.is_some()).count(),
+                optimal: gaps.iter().filter(|gap| **gap == 0.0).count(),
+                total_elapsed: runs.iter().map(|run| run.elapsed).sum(),
+                mean_expanded: mean(runs.iter().map(|run| run.expanded as f64)),
+                mean_gap: mean(gaps.iter().copied()),
+                max_gap: gaps.iter().copied().fold(0.0, f64::max),
+            }
+        })
+        .collect()
+}
+
+/// `SplitMix64`, small and good enough to spread pairs over a map. Kept here so the pairs for a
+/// seed never change with a random number crate's version.
+pub(crate) fn split_mix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    if count == 0 {
+        0.0
+    } else {
+        sum / f64::from(count)
+    }
+}
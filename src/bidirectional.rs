@@ -0,0 +1,388 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+};
+
+use crate::{
+    ExpandedNode, Map, Pathfinder, Position, SearchResult, SearchSnapshot, SearchStatus,
+    SearchSteps,
+};
+
+pub struct BidirectionalBfs;
+
+impl Pathfinder for BidirectionalBfs {
+    fn name(&self) -> &'static str {
+        "Bi-BFS"
+    }
+
+    fn uses_costs(&self) -> bool {
+        false
+    }
+
+    fn steps(&self, map: &Map, start: Position, goal: Position) -> Box<dyn SearchSteps> {
+        Box::new(BidirectionalSearch::new(map, start, goal, false, false))
+    }
+}
+
+pub struct BidirectionalAStar;
+
+impl Pathfinder for BidirectionalAStar {
+    fn name(&self) -> &'static str {
+        "Bi-AStar"
+    }
+
+    fn steps(&self, map: &Map, start: Position, goal: Position) -> Box<dyn SearchSteps> {
+        Box::new(BidirectionalSearch::new(map, start, goal, true, true))
+    }
+}
+
+/// One half of a bidirectional search: a best-first search towards `target`.
+struct Frontier {
+    target: Position,
+    open: BinaryHeap<Reverse<(i32, i32, usize, Position)>>,
+    g: Vec<Option<i32>>,
+    parents: Vec<Option<Position>>,
+    closed: Vec<bool>,
+}
+
+impl Frontier {
+    fn new(target: Position, tiles: usize) -> Frontier {
+        Frontier {
+            target,
+            open: BinaryHeap::new(),
+            g: vec![None; tiles],
+            parents: vec![None; tiles],
+            closed: vec![false; tiles],
+        }
+    }
+
+    /// Drops entries of nodes that were closed since they were pushed, so the top is the next
+    /// node to expand.
+    fn skip_closed(&mut self, width: i32) {
+        while let Some(Reverse((_, _, _, node))) = self.open.peek() {
+            if self.closed[index(width, *node)] {
+                self.open.pop();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn top(&self) -> Option<(i32, i32)> {
+        self.open.peek().map(|Reverse((f, h, _, _))| (*f, *f - *h))
+    }
+
+    fn open_nodes(&self, width: i32) -> Vec<Position> {
+        let mut seen = HashSet::new();
+        self.open
+            .iter()
+            .map(|Reverse((_, _, _, node))| *node)
+            .filter(|node| !self.closed[index(width, *node)] && seen.insert(*node))
+            .collect()
+    }
+
+    /// `node` back to where the half started.
+    fn trace(&self, width: i32, node: Position) -> Vec<Position> {
+        let mut nodes = vec![node];
+        let mut node = node;
+        while let Some(parent) = self.parents[index(width, node)] {
+            nodes.push(parent);
+            node = parent;
+        }
+        nodes
+    }
+}
+
+fn index(width: i32, node: Position) -> usize {
+    (node.y() * width + node.x()) as usize
+}
+
+/// Two searches, one from the start and one from the goal, that stop once no path through the
+/// nodes they have left can beat the best one where they met.
+///
+/// Every step expands one node of the half with fewer open nodes. With a heuristic each half
+/// orders its nodes by `g + h` towards the other end, and the search stops once the smallest `f`
+/// of either half reaches the best meeting cost, as each is a bound on any path not found yet.
+/// Without one it stops once the smallest `g` of both halves add up to it.
+pub struct BidirectionalSearch {
+    uses_heuristic: bool,
+    uses_costs: bool,
+    width: i32,
+    forward: Frontier,
+    backward: Frontier,
+    opened: usize,
+    /// Cost of the best path found so far and the node the two halves met at.
+    best: Option<(i32, Position)>,
+    expanded: Vec<ExpandedNode>,
+    generated: Vec<Position>,
+    current: Option<ExpandedNode>,
+    path: Option<Vec<Position>>,
+    status: SearchStatus,
+}
+
+impl BidirectionalSearch {
+    #[must_use]
+    pub fn new(
+        map: &Map,
+        start: Position,
+        goal: Position,
+        uses_heuristic: bool,
+        uses_costs: bool,
+    ) -> BidirectionalSearch {
+        let tiles = (map.width * map.height) as usize;
+        let mut search = BidirectionalSearch {
+            uses_heuristic,
+            uses_costs,
+            width: map.width,
+            forward: Frontier::new(goal, tiles),
+            backward: Frontier::new(start, tiles),
+            opened: 0,
+            best: None,
+            expanded: Vec::new(),
+            generated: Vec::new(),
+            current: None,
+            path: None,
+            status: SearchStatus::Searching,
+        };
+        if map.is_blocked(start.x(), start.y()) || map.is_blocked(goal.x(), goal.y()) {
+            search.status = SearchStatus::NotFound;
+        } else {
+            search.open_node(map, false, start, 0);
+            search.open_node(map, true, goal, 0);
+        }
+        search
+    }
+
+    fn frontier(&mut self, backward: bool) -> &mut Frontier {
+        if backward {
+            &mut self.backward
+        } else {
+            &mut self.forward
+        }
+    }
+
+    fn heuristic(&self, map: &Map, node: Position, target: Position) -> i32 {
+        if self.uses_heuristic {
+            map.heuristic(&node, &target)
+        } else {
+            0
+        }
+    }
+
+    fn open_node(&mut self, map: &Map, backward: bool, node: Position, g: i32) {
+        let target = self.frontier(backward).target;
+        let h = self.heuristic(map, node, target);
+        let (width, opened) = (self.width, self.opened);
+        let frontier = self.frontier(backward);
+        frontier.g[index(width, node)] = Some(g);
+        frontier.open.push(Reverse((g + h, h, opened, node)));
+        self.opened += 1;
+
+        // a path through `node` once both halves reached it
+        let other = if backward {
+            &self.forward
+        } else {
+            &self.backward
+        };
+        if let Some(other_g) = other.g[index(width, node)] {
+            if self.best.is_none_or(|(cost, _)| g + other_g < cost) {
+                self.best = Some((g + other_g, node));
+            }
+        }
+    }
+
+    /// Cost of moving between neighbours `from` and `to`, in the direction the half searches.
+    fn edge_cost(&self, map: &Map, backward: bool, from: Position, to: Position) -> i32 {
+        if !self.uses_costs {
+            1
+        } else if backward {
+            map.step_cost(&to, &from)
+        } else {
+            map.step_cost(&from, &to)
+        }
+    }
+
+    /// Whether the best meeting point can no longer be beaten, or a half ran out of nodes.
+    fn finished(&mut self) -> Option<SearchStatus> {
+        self.forward.skip_closed(self.width);
+        self.backward.skip_closed(self.width);
+        let (Some(forward), Some(backward)) = (self.forward.top(), self.backward.top()) else {
+            return Some(if self.best.is_some() {
+                SearchStatus::Found
+            } else {
+                SearchStatus::NotFound
+            });
+        };
+        let (cost, _) = self.best?;
+        let bound = if self.uses_heuristic {
+            forward.0.max(backward.0)
+        } else {
+            forward.1 + backward.1
+        };
+        if bound >= cost {
+            Some(SearchStatus::Found)
+        } else {
+            None
+        }
+    }
+
+    fn build_path(&self) -> Option<Vec<Position>> {
+        let (_, meeting) = self.best?;
+        let mut path = self.forward.trace(self.width, meeting);
+        path.reverse();
+        path.extend(self.backward.trace(self.width, meeting).into_iter().skip(1));
+        Some(path)
+    }
+}
+
+impl SearchSteps for BidirectionalSearch {
+    fn step(&mut self, map: &Map) -> SearchStatus {
+        if self.status.is_done() {
+            return self.status;
+        }
+        self.current = None;
+
+        // both halves have open nodes here, `finished` stopped the search otherwise
+        let backward = self.backward.open.len() < self.forward.open.len();
+        let width = self.width;
+        let Some(Reverse((_, _, _, node))) = self.frontier(backward).open.pop() else {
+            self.status = SearchStatus::NotFound;
+            return self.status;
+        };
+        let frontier = self.frontier(backward);
+        let node_index = index(width, node);
+        frontier.closed[node_index] = true;
+        let g = frontier.g[node_index].unwrap_or(0);
+        let parent = frontier.parents[node_index];
+        let target = frontier.target;
+        let expanded = ExpandedNode {
+            position: node,
+            g,
+            h: self.heuristic(map, node, target),
+            parent,
+            backward,
+        };
+        self.expanded.push(expanded);
+        self.current = Some(expanded);
+
+        // moves are reversible, only their cost depends on the direction
+        for successor in map.get_successors(&node) {
+            let neighbour = successor.position;
+            self.generated.push(neighbour);
+            let neighbour_index = index(width, neighbour);
+            if self.frontier(backward).closed[neighbour_index] {
+                continue;
+            }
+            let ng = g + self.edge_cost(map, backward, node, neighbour);
+            if self.frontier(backward).g[neighbour_index].is_none_or(|g| ng < g) {
+                self.frontier(backward).parents[neighbour_index] = Some(node);
+                self.open_node(map, backward, neighbour, ng);
+            }
+        }
+
+        if let Some(status) = self.finished() {
+            self.status = status;
+            if status == SearchStatus::Found {
+                self.path = self.build_path();
+            }
+        }
+        self.status
+    }
+
+    fn status(&self) -> SearchStatus {
+        self.status
+    }
+
+    fn current(&self) -> Option<ExpandedNode> {
+        self.current
+    }
+
+    fn open(&self) -> Vec<Position> {
+        self.forward.open_nodes(self.width)
+    }
+
+    fn backward_open(&self) -> Vec<Position> {
+        self.backward.open_nodes(self.width)
+    }
+
+    fn snapshot(&self) -> SearchSnapshot {
+        SearchSnapshot {
+            current: self.current.map(|node| node.position),
+            open: self.open(),
+            backward_open: self.backward_open(),
+            closed: self.expanded.iter().map(|node| node.position).collect(),
+            expanded: self.expanded.clone(),
+            path: self.path.clone(),
+        }
+    }
+
+    fn into_result(self: Box<Self>, map: &Map) -> SearchResult {
+        let cost = self.path.as_ref().map(|path| map.path_cost(path));
+        SearchResult {
+            path: self.path,
+            cost,
+            expanded: self.expanded.iter().map(|node| node.position).collect(),
+            expanded_nodes: self.expanded,
+            generated: self.generated,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BidirectionalSearch;
+    use crate::{
+        random_pairs, solve, test_support::random_map, CostModel, Map, PathfindingAlgorithm,
+        Position, SearchSteps,
+    };
+
+    #[test]
+    fn matches_dijkstra_on_cost_maps() {
+        for seed in 0..12 {
+            let mut map = random_map(20, 16, seed);
+            map.set_cost_model(CostModel::ALL[seed as usize % CostModel::ALL.len()]);
+            for (start, goal) in random_pairs(&map, 10, seed) {
+                let dijkstra = solve(&map, PathfindingAlgorithm::Dijkstra, start, goal);
+                let bidirectional =
+                    solve(&map, PathfindingAlgorithm::BidirectionalAStar, start, goal);
+                assert_eq!(bidirectional.cost, dijkstra.cost, "{start:?} to {goal:?}");
+
+                let bfs = solve(&map, PathfindingAlgorithm::BFS, start, goal);
+                let bidirectional =
+                    solve(&map, PathfindingAlgorithm::BidirectionalBfs, start, goal);
+                assert_eq!(
+                    bidirectional.path.map(|path| path.len()),
+                    bfs.path.map(|path| path.len()),
+                    "{start:?} to {goal:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_searching_past_the_first_meeting() {
+        // the halves first meet on the expensive middle row, the cheap way round is found later
+        let mut map = Map::new(7, 5, true);
+        for x in 1..6 {
+            map.set_cost(x, 2, Some(3));
+        }
+        let (start, goal) = (Position(0, 2), Position(6, 2));
+        let optimal = solve(&map, PathfindingAlgorithm::Dijkstra, start, goal)
+            .cost
+            .unwrap();
+
+        let mut search = BidirectionalSearch::new(&map, start, goal, true, true);
+        let mut first_meeting = None;
+        while !search.step(&map).is_done() {
+            first_meeting = first_meeting.or(search.best.map(|(cost, _)| cost));
+        }
+        assert!(first_meeting.unwrap() > optimal);
+        assert_eq!(Box::new(search).into_result(&map).cost, Some(optimal));
+    }
+}
+
+// References
+// 1. Bidirectional search, Ira Pohl, "Bi-directional Search", Machine Intelligence 6 (1971)
+// 2. Goldberg and Harrelson, "Computing the Shortest Path: A* Search Meets Graph Theory" (2005),
+// for the stopping condition of bidirectional Dijkstra
@@ -0,0 +1,253 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//! Runs every algorithm on many start/goal pairs and reports runtime, nodes expanded, path cost
+//! and the gap to the optimal cost.
+//!
+//! `cargo run --release --no-default-features --bin benchmark -- [options]`
+//!
+//! ```text
+//! --map FILE          a map saved by the app, or a Moving AI `.map` file
+//! --scen FILE         take the pairs from a Moving AI `.scen` file
+//! --size W H          size of the generated noise map when there is no --map (128 128)
+//! --pairs N           number of random pairs (100)
+//! --seed N            seed for the random pairs (0)
+//! --algorithms A,B    algorithms to run, by name (all)
+//! --format FORMAT     `table` for a summary, `csv` or `json` for every run (table)
+//! ```
+
+use std::{path::Path, process};
+
+use bevy_pathfinding::{
+    random_pairs, run_benchmark, summarize, BenchmarkRun, Map, MapFile, MovingAiMap,
+    PathfindingAlgorithm, Scenario, DEFAULT_CLUSTER_SIZE, STRAIGHT_COST,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Table,
+    Csv,
+    Json,
+}
+
+struct Options {
+    map: Option<String>,
+    scen: Option<String>,
+    width: i32,
+    height: i32,
+    pairs: usize,
+    seed: u64,
+    algorithms: Vec<PathfindingAlgorithm>,
+    format: Format,
+}
+
+fn main() {
+    let options = parse_options(std::env::args().skip(1).collect()).unwrap_or_else(|error| {
+        eprintln!("{error}");
+        process::exit(2);
+    });
+
+    let map = load_map(&options).unwrap_or_else(|error| {
+        eprintln!("{error}");
+        process::exit(1);
+    });
+    let pairs = match &options.scen {
+        Some(path) => match Scenario::load(path) {
+            Ok(scenarios) => scenarios
+                .iter()
+                .map(|scenario| (scenario.start, scenario.goal))
+                .collect(),
+            Err(error) => {
+                eprintln!("Could not load {path}: {error}");
+                process::exit(1);
+            }
+        },
+        None => random_pairs(&map, options.pairs, options.seed),
+    };
+    if pairs
+        .iter()
+        .any(|(start, goal)| !map.inside(start.x(), start.y()) || !map.inside(goal.x(), goal.y()))
+    {
+        eprintln!("Some pairs do not fit the {}x{} map", map.width, map.height);
+        process::exit(1);
+    }
+
+    let runs = run_benchmark(&map, &pairs, &options.algorithms);
+    match options.format {
+        Format::Table => print_table(&map, pairs.len(), &runs),
+        Format::Csv => print_csv(&runs),
+        Format::Json => print_json(&runs),
+    }
+}
+
+fn parse_options(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options {
+        map: None,
+        scen: None,
+        width: 128,
+        height: 128,
+        pairs: 100,
+        seed: 0,
+        algorithms: PathfindingAlgorithm::ALL.to_vec(),
+        format: Format::Table,
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--map" => options.map = Some(value()?),
+            "--scen" => options.scen = Some(value()?),
+            "--size" => {
+                options.width = parse_value(&value()?)?;
+                options.height = parse_value(&value()?)?;
+            }
+            "--pairs" => options.pairs = parse_value(&value()?)?,
+            "--seed" => options.seed = parse_value(&value()?)?,
+            "--algorithms" => {
+                options.algorithms = value()?
+                    .split(',')
+                    .map(parse_algorithm)
+                    .collect::<Result<_, _>>()?;
+            }
+            "--format" => {
+                options.format = match value()?.as_str() {
+                    "table" => Format::Table,
+                    "csv" => Format::Csv,
+                    "json" => Format::Json,
+                    other => return Err(format!("Unknown format {other}")),
+                }
+            }
+            _ => return Err(format!("Unknown option {arg}")),
+        }
+    }
+    if options.width <= 0 || options.height <= 0 {
+        return Err("The size must be positive".to_string());
+    }
+    Ok(options)
+}
+
+fn parse_value<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Expected a number, got {value}"))
+}
+
+fn parse_algorithm(name: &str) -> Result<PathfindingAlgorithm, String> {
+    PathfindingAlgorithm::ALL
+        .into_iter()
+        .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("Unknown algorithm {name}"))
+}
+
+fn load_map(options: &Options) -> Result<Map, String> {
+    let mut map = if let Some(path) = &options.map {
+        let is_moving_ai = Path::new(path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("map"));
+        if is_moving_ai {
+            MovingAiMap::load(path)
+        } else {
+            MapFile::load(path).map(|map_file| map_file.map)
+        }
+        .map_err(|error| format!("Could not load {path}: {error}"))?
+    } else {
+        Map::from_noise(options.width, options.height, true)
+    };
+
+    // `MovingAiMap::load` already builds them
+    if map.jump_table.is_none() {
+        map.build_jump_table();
+    }
+    if !map.has_hierarchy() {
+        map.build_hierarchy(DEFAULT_CLUSTER_SIZE);
+    }
+    Ok(map)
+}
+
+fn print_table(map: &Map, pairs: usize, runs: &[BenchmarkRun]) {
+    println!(
+        "{}x{} map, {} pairs, {} costs",
+        map.width,
+        map.height,
+        pairs,
+        map.cost_model.name()
+    );
+    println!(
+        "{:<12} {:>7} {:>8} {:>12} {:>12} {:>10} {:>10}",
+        "algorithm", "solved", "optimal", "total time", "expanded", "mean gap", "max gap"
+    );
+    for summary in summarize(runs) {
+        println!(
+            "{:<12} {:>7} {:>8} {:>12.2?} {:>12.1} {:>9.2}% {:>9.2}%",
+            summary.algorithm.name(),
+            summary.solved,
+            summary.optimal,
+            summary.total_elapsed,
+            summary.mean_expanded,
+            summary.mean_gap * 100.0,
+            summary.max_gap * 100.0
+        );
+    }
+}
+
+fn print_csv(runs: &[BenchmarkRun]) {
+    println!(
+        "algorithm,pair,start_x,start_y,goal_x,goal_y,micros,expanded,generated,cost,optimal_cost,gap"
+    );
+    for run in runs {
+        println!(
+            "{},{},{},{},{},{},{},{},{},{},{},{}",
+            run.algorithm.name(),
+            run.pair,
+            run.start.x(),
+            run.start.y(),
+            run.goal.x(),
+            run.goal.y(),
+            run.elapsed.as_micros(),
+            run.expanded,
+            run.generated,
+            optional(run.cost.map(tiles)),
+            optional(run.optimal_cost.map(tiles)),
+            optional(run.gap())
+        );
+    }
+}
+
+fn print_json(runs: &[BenchmarkRun]) {
+    println!("[");
+    for (index, run) in runs.iter().enumerate() {
+        let separator = if index + 1 < runs.len() { "," } else { "" };
+        println!(
+            "  {{\"algorithm\": \"{}\", \"pair\": {}, \"start\": [{}, {}], \"goal\": [{}, {}], \
+             \"micros\": {}, \"expanded\": {}, \"generated\": {}, \"cost\": {}, \
+             \"optimal_cost\": {}, \"gap\": {}}}{}",
+            run.algorithm.name(),
+            run.pair,
+            run.start.x(),
+            run.start.y(),
+            run.goal.x(),
+            run.goal.y(),
+            run.elapsed.as_micros(),
+            run.expanded,
+            run.generated,
+            json_optional(run.cost.map(tiles)),
+            json_optional(run.optimal_cost.map(tiles)),
+            json_optional(run.gap()),
+            separator
+        );
+    }
+    println!("]");
+}
+
+/// Costs are reported in tiles rather than `STRAIGHT_COST` units.
+fn tiles(cost: i32) -> f64 {
+    f64::from(cost) / f64::from(STRAIGHT_COST)
+}
+
+fn optional(value: Option<f64>) -> String {
+    value.map_or_else(String::new, |value| value.to_string())
+}
+
+fn json_optional(value: Option<f64>) -> String {
+    value.map_or_else(|| "null".to_string(), |value| value.to_string())
+}
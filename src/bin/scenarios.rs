@@ -0,0 +1,103 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//! Solves every scenario of a Moving AI `.scen` file and checks the path lengths against the
+//! published optimal lengths.
+//!
+//! `cargo run --release --no-default-features --bin scenarios -- <map> <scen> [algorithm...]`
+//!
+//! Algorithms are given by name (`AStar`, `Jps+`, ...), all of them are run by default.
+
+use std::{process, time::Duration};
+
+use bevy_pathfinding::{run_scenarios, MovingAiMap, PathfindingAlgorithm, Scenario};
+
+/// Mismatches listed per algorithm before the rest are only counted.
+const MAX_LISTED_MISMATCHES: usize = 5;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 2 {
+        eprintln!("usage: scenarios <map> <scen> [algorithm...]");
+        process::exit(2);
+    }
+
+    let map = MovingAiMap::load(&args[0]).unwrap_or_else(|error| {
+        eprintln!("Could not load {}: {}", args[0], error);
+        process::exit(1);
+    });
+    let scenarios = Scenario::load(&args[1]).unwrap_or_else(|error| {
+        eprintln!("Could not load {}: {}", args[1], error);
+        process::exit(1);
+    });
+    if let Some(scenario) = scenarios.iter().find(|scenario| {
+        !map.inside(scenario.start.x(), scenario.start.y())
+            || !map.inside(scenario.goal.x(), scenario.goal.y())
+    }) {
+        eprintln!(
+            "{} does not fit the map, it was made for {}",
+            args[1], scenario.map
+        );
+        process::exit(1);
+    }
+
+    let algorithms: Vec<PathfindingAlgorithm> = if args.len() > 2 {
+        args[2..]
+            .iter()
+            .map(|name| {
+                PathfindingAlgorithm::ALL
+                    .into_iter()
+                    .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+                    .unwrap_or_else(|| {
+                        eprintln!("Unknown algorithm {name}");
+                        process::exit(2);
+                    })
+            })
+            .collect()
+    } else {
+        PathfindingAlgorithm::ALL.to_vec()
+    };
+
+    let results = run_scenarios(&map, &scenarios, &algorithms);
+    for algorithm in algorithms {
+        let results: Vec<_> = results
+            .iter()
+            .filter(|result| result.algorithm == algorithm)
+            .collect();
+        let solved = results
+            .iter()
+            .filter(|result| result.length.is_some())
+            .count();
+        let optimal = results.iter().filter(|result| result.optimal).count();
+        let elapsed: Duration = results.iter().map(|result| result.result.elapsed).sum();
+        println!(
+            "{:<12} solved {}/{}, optimal {}/{}, {:.1?}",
+            algorithm.name(),
+            solved,
+            results.len(),
+            optimal,
+            results.len(),
+            elapsed
+        );
+
+        let mismatches: Vec<_> = results.iter().filter(|result| !result.optimal).collect();
+        for result in mismatches.iter().take(MAX_LISTED_MISMATCHES) {
+            let scenario = &scenarios[result.scenario];
+            println!(
+                "    bucket {} {:?} -> {:?}: length {}, optimal {:.4}",
+                scenario.bucket,
+                scenario.start,
+                scenario.goal,
+                result
+                    .length
+                    .map_or_else(|| "none".to_string(), |length| format!("{length:.4}")),
+                scenario.optimal_length
+            );
+        }
+        if mismatches.len() > MAX_LISTED_MISMATCHES {
+            println!(
+                "    ... and {} more",
+                mismatches.len() - MAX_LISTED_MISMATCHES
+            );
+        }
+    }
+}
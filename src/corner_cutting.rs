@@ -0,0 +1,130 @@
+/// When a diagonal step may pass the corner of a blocked tile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CornerCutting {
+    /// A diagonal step only needs its target to be open, even between two walls that touch at
+    /// a corner.
+    Always,
+    /// At least one of the two tiles beside a diagonal step must be open.
+    OneSideOpen,
+    /// Both tiles beside a diagonal step must be open, so no corner is ever cut.
+    BothSidesOpen,
+    /// There are no diagonal steps at all, movement is 4-way.
+    Never,
+}
+
+impl CornerCutting {
+    pub const ALL: [CornerCutting; 4] = [
+        CornerCutting::Always,
+        CornerCutting::OneSideOpen,
+        CornerCutting::BothSidesOpen,
+        CornerCutting::Never,
+    ];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            CornerCutting::Always => "Corners: Always",
+            CornerCutting::OneSideOpen => "Corners: One Open",
+            CornerCutting::BothSidesOpen => "Corners: Both Open",
+            CornerCutting::Never => "Corners: Never",
+        }
+    }
+
+    #[must_use]
+    pub fn next(self) -> CornerCutting {
+        let index = Self::ALL
+            .iter()
+            .position(|policy| *policy == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    #[must_use]
+    pub fn allows_diagonals(self) -> bool {
+        self != CornerCutting::Never
+    }
+
+    /// Whether a diagonal step is allowed, given which of the two tiles beside it are open.
+    #[must_use]
+    pub fn allows(self, one_side_open: bool, other_side_open: bool) -> bool {
+        match self {
+            CornerCutting::Always => true,
+            CornerCutting::OneSideOpen => one_side_open || other_side_open,
+            CornerCutting::BothSidesOpen => one_side_open && other_side_open,
+            CornerCutting::Never => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CornerCutting;
+    use crate::{
+        random_pairs, solve, test_support::random_map, Map, PathfindingAlgorithm, Position,
+    };
+
+    #[test]
+    fn allows_by_open_sides() {
+        let allowed = |policy: CornerCutting| {
+            [(true, true), (true, false), (false, true), (false, false)]
+                .map(|(one_side, other_side)| policy.allows(one_side, other_side))
+        };
+        assert_eq!(allowed(CornerCutting::Always), [true, true, true, true]);
+        assert_eq!(
+            allowed(CornerCutting::OneSideOpen),
+            [true, true, true, false]
+        );
+        assert_eq!(
+            allowed(CornerCutting::BothSidesOpen),
+            [true, false, false, false]
+        );
+        assert_eq!(allowed(CornerCutting::Never), [false, false, false, false]);
+    }
+
+    #[test]
+    fn moves_and_sight_lines_follow_the_policy() {
+        // from (0, 0) to (1, 1) with no, one and both of (1, 0) and (0, 1) blocked
+        let expected = [
+            (CornerCutting::Always, [true, true, true]),
+            (CornerCutting::OneSideOpen, [true, true, false]),
+            (CornerCutting::BothSidesOpen, [true, false, false]),
+            (CornerCutting::Never, [false, false, false]),
+        ];
+        for (policy, allowed) in expected {
+            let mut map = Map::new(2, 2, true);
+            map.set_corner_cutting(policy);
+            for (blocked, allowed) in allowed.into_iter().enumerate() {
+                map.set_blocked(1, 0, blocked >= 1);
+                map.set_blocked(0, 1, blocked >= 2);
+                assert_eq!(map.can_move(0, 0, 1, 1), allowed, "{}", policy.name());
+                // a sight line along the diagonal passes the same corner
+                let sight = map.line_of_sight(&Position(0, 0), &Position(1, 1));
+                assert_eq!(sight, allowed || blocked == 0, "{}", policy.name());
+            }
+        }
+    }
+
+    #[test]
+    fn jps_matches_dijkstra_under_every_policy() {
+        for seed in 0..8 {
+            let mut map = random_map(24, 20, seed);
+            // half the maps without costs, where JPS prunes the most
+            if seed % 2 == 0 {
+                map.costs.iter_mut().for_each(|cost| *cost = Some(1));
+            }
+            for policy in CornerCutting::ALL {
+                map.set_corner_cutting(policy);
+                for (start, goal) in random_pairs(&map, 8, seed) {
+                    let dijkstra = solve(&map, PathfindingAlgorithm::Dijkstra, start, goal);
+                    let jps = solve(&map, PathfindingAlgorithm::Jps, start, goal);
+                    assert_eq!(
+                        jps.cost,
+                        dijkstra.cost,
+                        "{} from {start:?} to {goal:?}",
+                        policy.name()
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use crate::Position;
+
+/// Cost of an orthogonal step onto a tile of cost 1.
+///
+/// Step costs are fixed point so they stay integers, which is what `pathfinding` needs.
+pub const STRAIGHT_COST: i32 = 100;
+
+/// `STRAIGHT_COST * sqrt(2)` rounded down. The octile heuristic uses the same constant, so it
+/// never overestimates.
+pub const DIAGONAL_COST: i32 = 141;
+
+/// How moving from one tile to the next is priced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CostModel {
+    /// 4-way movement, there are no diagonal steps.
+    Manhattan,
+    /// 8-way movement, a diagonal step costs the same as an orthogonal one.
+    Chebyshev,
+    /// 8-way movement, a diagonal step costs sqrt(2) times an orthogonal one.
+    Octile,
+}
+
+impl CostModel {
+    pub const ALL: [CostModel; 3] = [
+        CostModel::Manhattan,
+        CostModel::Chebyshev,
+        CostModel::Octile,
+    ];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            CostModel::Manhattan => "Manhattan",
+            CostModel::Chebyshev => "Chebyshev",
+            CostModel::Octile => "Octile",
+        }
+    }
+
+    #[must_use]
+    pub fn next(self) -> CostModel {
+        let index = Self::ALL
+            .iter()
+            .position(|model| *model == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    #[must_use]
+    pub fn allows_diagonals(self) -> bool {
+        self != CostModel::Manhattan
+    }
+
+    /// Cost of a single step in direction `(dx, dy)` onto a tile that costs `tile_cost`.
+    #[must_use]
+    pub fn step_cost(self, dx: i32, dy: i32, tile_cost: i32) -> i32 {
+        let step = if dx != 0 && dy != 0 && self == CostModel::Octile {
+            DIAGONAL_COST
+        } else {
+            STRAIGHT_COST
+        };
+        step * tile_cost
+    }
+
+    /// Estimate of the cost from `from` to `to` that matches the model and never overestimates,
+    /// since every tile costs at least 1.
+    #[must_use]
+    pub fn heuristic(self, from: &Position, to: &Position) -> i32 {
+        let dx = (from.x() - to.x()).abs();
+        let dy = (from.y() - to.y()).abs();
+        match self {
+            CostModel::Manhattan => STRAIGHT_COST * (dx + dy),
+            CostModel::Chebyshev => STRAIGHT_COST * dx.max(dy),
+            CostModel::Octile => {
+                STRAIGHT_COST * (dx.max(dy) - dx.min(dy)) + DIAGONAL_COST * dx.min(dy)
+            }
+        }
+    }
+}
+
+/// Straight-line distance between the centres of `from` and `to` in `STRAIGHT_COST` units,
+/// rounded down like `DIAGONAL_COST`. Any-angle paths are priced with it whatever the cost model.
+#[must_use]
+pub fn euclidean_cost(from: &Position, to: &Position) -> i32 {
+    let dx = f64::from(to.x() - from.x());
+    let dy = f64::from(to.y() - from.y());
+    (f64::from(STRAIGHT_COST) * dx.hypot(dy)) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CostModel, DIAGONAL_COST, STRAIGHT_COST};
+    use crate::{
+        random_pairs, solve, test_support::random_map, Map, PathfindingAlgorithm, Position,
+    };
+
+    #[test]
+    fn prices_steps_by_model() {
+        for model in CostModel::ALL {
+            assert_eq!(model.step_cost(1, 0, 3), 3 * STRAIGHT_COST);
+            assert_eq!(model.step_cost(0, -1, 1), STRAIGHT_COST);
+        }
+        assert_eq!(CostModel::Octile.step_cost(1, 1, 2), 2 * DIAGONAL_COST);
+        assert_eq!(CostModel::Chebyshev.step_cost(-1, 1, 2), 2 * STRAIGHT_COST);
+
+        let mut map = Map::new(3, 3, true);
+        for model in CostModel::ALL {
+            map.set_cost_model(model);
+            let diagonal = map
+                .get_successors(&Position(0, 0))
+                .into_iter()
+                .find(|successor| successor.position == Position(1, 1));
+            assert_eq!(diagonal.is_some(), model.allows_diagonals());
+        }
+    }
+
+    #[test]
+    fn heuristics_are_exact_on_open_maps() {
+        let mut map = Map::new(12, 9, true);
+        let (start, goal) = (Position(1, 2), Position(10, 6));
+        for model in CostModel::ALL {
+            map.set_cost_model(model);
+            let cost = solve(&map, PathfindingAlgorithm::Dijkstra, start, goal).cost;
+            assert_eq!(
+                Some(model.heuristic(&start, &goal)),
+                cost,
+                "{}",
+                model.name()
+            );
+        }
+        assert_eq!(
+            CostModel::Manhattan.heuristic(&start, &goal),
+            13 * STRAIGHT_COST
+        );
+        assert_eq!(
+            CostModel::Chebyshev.heuristic(&start, &goal),
+            9 * STRAIGHT_COST
+        );
+        assert_eq!(
+            CostModel::Octile.heuristic(&start, &goal),
+            5 * STRAIGHT_COST + 4 * DIAGONAL_COST
+        );
+    }
+
+    #[test]
+    fn heuristics_never_overestimate() {
+        for seed in 0..9 {
+            let mut map = random_map(20, 16, seed);
+            let model = CostModel::ALL[seed as usize % CostModel::ALL.len()];
+            map.set_cost_model(model);
+            for (start, goal) in random_pairs(&map, 10, seed) {
+                let cost = solve(&map, PathfindingAlgorithm::Dijkstra, start, goal)
+                    .cost
+                    .unwrap();
+                assert!(model.heuristic(&start, &goal) <= cost, "{}", model.name());
+                let a_star = solve(&map, PathfindingAlgorithm::AStar, start, goal);
+                assert_eq!(a_star.cost, Some(cost), "{}", model.name());
+            }
+        }
+    }
+}
+
+// References
+// 1. Heuristics, Amit Patel
+// http://theory.stanford.edu/~amitp/GameProgramming/Heuristics.html
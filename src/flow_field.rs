@@ -0,0 +1,196 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::{Map, Position};
+
+/// Directions towards one goal for every tile of a map, so any number of agents heading there
+/// can look up their next step instead of searching for a path each.
+///
+/// Built in two passes: the integration field is the cost from every tile to the goal, found by
+/// a Dijkstra search outwards from the goal that prices steps like `Map::step_cost` (so tile costs
+/// count), and the direction field points every tile at the neighbour its cost comes through.
+#[derive(Clone, Debug)]
+pub struct FlowField {
+    pub width: i32,
+    pub height: i32,
+    pub goal: Position,
+    /// Cost from each tile to the goal, `None` for blocked tiles and tiles that cannot reach it.
+    pub integration: Vec<Option<i32>>,
+    /// The step `(dx, dy)` to take from each tile, `None` at the goal and wherever `integration`
+    /// is `None`.
+    pub directions: Vec<Option<(i32, i32)>>,
+}
+
+impl FlowField {
+    #[must_use]
+    pub fn new(map: &Map, goal: Position) -> FlowField {
+        let tiles = (map.width * map.height) as usize;
+        let mut field = FlowField {
+            width: map.width,
+            height: map.height,
+            goal,
+            integration: vec![None; tiles],
+            directions: vec![None; tiles],
+        };
+        if map.is_blocked(goal.x(), goal.y()) {
+            return field;
+        }
+        field.integrate(map);
+        field.point(map);
+        field
+    }
+
+    fn index(&self, position: Position) -> Option<usize> {
+        if 0 <= position.x()
+            && position.x() < self.width
+            && 0 <= position.y()
+            && position.y() < self.height
+        {
+            Some((position.y() * self.width + position.x()) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Dijkstra from the goal. Moves run from the tile towards the goal, so reaching `neighbour`
+    /// from `node` costs the step from `neighbour` onto `node`.
+    fn integrate(&mut self, map: &Map) {
+        let mut open = BinaryHeap::new();
+        if let Some(index) = self.index(self.goal) {
+            self.integration[index] = Some(0);
+        }
+        open.push(Reverse((0, self.goal)));
+        while let Some(Reverse((cost, node))) = open.pop() {
+            if self.cost(node).is_some_and(|best| best < cost) {
+                continue;
+            }
+            for successor in map.get_successors(&node) {
+                let neighbour = successor.position;
+                let neighbour_cost = cost + map.step_cost(&neighbour, &node);
+                if let Some(index) = self.index(neighbour) {
+                    if self.integration[index].is_none_or(|best| neighbour_cost < best) {
+                        self.integration[index] = Some(neighbour_cost);
+                        open.push(Reverse((neighbour_cost, neighbour)));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Points every reachable tile at the neighbour it is cheapest to go on from.
+    fn point(&mut self, map: &Map) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let node = Position(x, y);
+                if node == self.goal || self.cost(node).is_none() {
+                    continue;
+                }
+                let best = map
+                    .get_successors(&node)
+                    .into_iter()
+                    .filter_map(|successor| {
+                        let cost = self.cost(successor.position)? + successor.cost;
+                        Some((cost, successor.position))
+                    })
+                    .min();
+                if let (Some((_, next)), Some(index)) = (best, self.index(node)) {
+                    self.directions[index] = Some((next.x() - x, next.y() - y));
+                }
+            }
+        }
+    }
+
+    /// Cost from `position` to the goal, `None` when it cannot get there.
+    #[must_use]
+    pub fn cost(&self, position: Position) -> Option<i32> {
+        self.integration[self.index(position)?]
+    }
+
+    /// The step to take from `position` towards the goal.
+    #[must_use]
+    pub fn direction(&self, position: Position) -> Option<(i32, i32)> {
+        self.directions[self.index(position)?]
+    }
+
+    /// The tile to move to from `position`.
+    #[must_use]
+    pub fn next(&self, position: Position) -> Option<Position> {
+        let (dx, dy) = self.direction(position)?;
+        Some(Position(position.x() + dx, position.y() + dy))
+    }
+
+    /// The path the field leads along from `start`, the same cost as the cheapest one.
+    #[must_use]
+    pub fn path_from(&self, start: Position) -> Option<Vec<Position>> {
+        self.cost(start)?;
+        let mut path = vec![start];
+        let mut node = start;
+        while node != self.goal {
+            node = self.next(node)?;
+            path.push(node);
+        }
+        Some(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlowField;
+    use crate::{
+        random_pairs, solve, test_support::random_map, CostModel, Map, PathfindingAlgorithm,
+        Position,
+    };
+
+    #[test]
+    fn leads_every_tile_along_a_cheapest_path() {
+        for seed in 0..6 {
+            let mut map = random_map(16, 12, seed);
+            map.set_cost_model(CostModel::ALL[seed as usize % CostModel::ALL.len()]);
+            let (_, goal) = random_pairs(&map, 1, seed)[0];
+            let field = FlowField::new(&map, goal);
+            for y in 0..map.height {
+                for x in 0..map.width {
+                    let start = Position(x, y);
+                    let dijkstra = solve(&map, PathfindingAlgorithm::Dijkstra, start, goal).cost;
+                    assert_eq!(field.cost(start), dijkstra, "{start:?} to {goal:?}");
+                    let path = field.path_from(start);
+                    assert_eq!(path.is_some(), dijkstra.is_some(), "{start:?} to {goal:?}");
+                    if let Some(path) = path {
+                        assert_eq!(path.last(), Some(&goal));
+                        assert_eq!(
+                            Some(map.path_cost(&path)),
+                            dijkstra,
+                            "{start:?} to {goal:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn blocked_and_unreachable_tiles_have_no_path() {
+        // the right column is walled off from the goal
+        let mut map = Map::new(5, 3, true);
+        for y in 0..3 {
+            map.set_blocked(3, y, true);
+        }
+        let field = FlowField::new(&map, Position(0, 0));
+        assert_eq!(field.path_from(Position(3, 1)), None);
+        assert_eq!(field.path_from(Position(4, 1)), None);
+        assert_eq!(field.cost(Position(4, 1)), None);
+        assert_eq!(field.direction(Position(4, 1)), None);
+        assert_eq!(field.path_from(Position(-1, 0)), None);
+        assert_eq!(field.path_from(Position(0, 0)), Some(vec![Position(0, 0)]));
+        assert_eq!(field.direction(Position(0, 0)), None);
+
+        // a goal on a blocked tile leads nowhere
+        let field = FlowField::new(&map, Position(3, 0));
+        assert!(field.integration.iter().all(Option::is_none));
+    }
+}
+
+// References
+// 1. Crowd Pathfinding and Steering Using Flow Field Tiles, Elijah Emerson, Game AI Pro (2013)
+// http://www.gameaipro.com/GameAIPro/GameAIPro_Chapter23_Crowd_Pathfinding_and_Steering_Using_Flow_Field_Tiles.pdf
+// 2. Flow Field Pathfinding, Leif Erkenbrach
+// https://leifnode.com/2013/12/flow-field-pathfinding/
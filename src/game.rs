@@ -1,20 +1,22 @@
+use std::path::PathBuf;
+
 use bevy::prelude::*;
 use bevy_ecs_tilemap::tiles::{TilePos, TileStorage};
-use pathfinding::prelude::{astar, bfs, dijkstra};
 
-use crate::{jps::Jps, CostsTile, CostsTileMap};
+use crate::{CostsTile, CostsTileMap};
 
 use super::{
-    world_position_to_index, Map, MapUpdatedEvent, Mouse, Position, UserInterfaceInteractionEvent,
+    smooth_path, world_position_to_index, ExpandedNode, FlowField, IncrementalPlanner, Map,
+    MapFile, Mouse, PathSmoothing, Pathfinder, PathfindingAlgorithm, Position, SearchResult,
+    SearchTimeline, UserInterfaceInteractionEvent, DEFAULT_CLUSTER_SIZE, DEFAULT_WEIGHT,
+    STRAIGHT_COST,
 };
 
-#[derive(Clone, Copy, Debug)]
-pub enum PathfindingAlgorithm {
-    AStar,
-    BFS,
-    Dijkstra,
-    Jps,
-}
+pub const MAP_WIDTH: i32 = 64;
+pub const MAP_HEIGHT: i32 = 64;
+
+/// Where Save and Load go when no map file is given on the command line.
+pub const DEFAULT_MAP_PATH: &str = "map.txt";
 
 #[derive(Debug)]
 pub enum PlacementMode {
@@ -24,22 +26,245 @@ pub enum PlacementMode {
     Goal,
     IncreaseCost,
     DecreaseCost,
+    /// Start, then goal, of an agent for multi-agent planning.
+    AgentPair,
+}
+
+/// What the heat map colours expanded tiles by, if anything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeatMapMode {
+    Off,
+    /// When the tile was expanded, first to last.
+    ExpansionOrder,
+    /// The cost from the start when it was expanded.
+    CostToCome,
+}
+
+impl HeatMapMode {
+    pub const ALL: [HeatMapMode; 3] = [
+        HeatMapMode::Off,
+        HeatMapMode::ExpansionOrder,
+        HeatMapMode::CostToCome,
+    ];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            HeatMapMode::Off => "Off",
+            HeatMapMode::ExpansionOrder => "Order",
+            HeatMapMode::CostToCome => "g",
+        }
+    }
+
+    #[must_use]
+    pub fn next(self) -> HeatMapMode {
+        let index = Self::ALL.iter().position(|mode| *mode == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
 }
 
 // === Resources ===
 #[derive(Debug)]
 pub struct GameState {
     pub pathfinding_algorithm: PathfindingAlgorithm,
+    /// How much Weighted A* and the first round of ARA* inflate the heuristic, one of `WEIGHTS`.
+    pub weight: f64,
     pub placement_mode: PlacementMode,
     pub start: Position,
     pub goal: Position,
+    /// The path after `smoothing`, see `set_path`.
     pub path: Vec<Position>,
+    /// The path as the search returned it.
+    pub unsmoothed_path: Vec<Position>,
+    /// Points of the smoothed curve in tile coordinates, empty unless `smoothing` is
+    /// `PathSmoothing::Curve`.
+    pub path_curve: Vec<(f32, f32)>,
+    pub smoothing: PathSmoothing,
     pub tested: Vec<Position>,
     pub searched: Vec<Position>,
-    pub step: usize,
+    /// Open nodes of the half of a bidirectional search that runs from the goal.
+    pub backward_tested: Vec<Position>,
+    /// The node the stepped search expanded last.
+    pub current: Option<Position>,
+    /// `g`, `h` and parent of every node in `searched`, for the node stats overlay.
+    pub expanded_nodes: Vec<ExpandedNode>,
+    pub show_node_stats: bool,
+    /// Whether the arrows of the `FlowField` towards the goal are drawn over the map.
+    pub show_flow_field: bool,
+    pub heat_map: HeatMapMode,
+    /// What every algorithm found for the same start and goal, drawn over the map by Compare.
+    pub comparison: Vec<ComparedPath>,
+}
+
+impl GameState {
+    /// The picked algorithm, with the picked weight.
+    #[must_use]
+    pub fn pathfinder(&self) -> Box<dyn Pathfinder> {
+        self.pathfinding_algorithm.weighted_pathfinder(self.weight)
+    }
+
+    /// Forgets the path and everything drawn for the last search.
+    pub fn clear_search(&mut self) {
+        self.path = Vec::new();
+        self.unsmoothed_path = Vec::new();
+        self.path_curve = Vec::new();
+        self.tested = Vec::new();
+        self.searched = Vec::new();
+        self.backward_tested = Vec::new();
+        self.current = None;
+        self.expanded_nodes = Vec::new();
+        self.comparison = Vec::new();
+    }
+
+    /// Keeps `path` as the search returned it and runs it through the smoothing pipeline.
+    pub fn set_path(&mut self, map: &Map, path: Vec<Position>) {
+        let smoothed = smooth_path(map, &path, self.smoothing);
+        self.unsmoothed_path = path;
+        self.path = smoothed.waypoints;
+        self.path_curve = smoothed.curve;
+    }
+
+    /// Whether the path is drawn as lines between waypoints rather than as tiles, because the
+    /// algorithm is any-angle or the path was smoothed.
+    #[must_use]
+    pub fn draws_path_as_lines(&self) -> bool {
+        self.pathfinding_algorithm.any_angle() || self.smoothing != PathSmoothing::Off
+    }
+}
+
+/// One algorithm's answer in comparison mode.
+#[derive(Debug)]
+pub struct ComparedPath {
+    pub algorithm: PathfindingAlgorithm,
+    pub result: SearchResult,
+}
+
+/// Colours telling the algorithms apart in comparison mode, by index into
+/// `PathfindingAlgorithm::ALL`.
+pub const COMPARISON_COLORS: [Color; PathfindingAlgorithm::ALL.len()] = [
+    Color::rgb(0.90, 0.30, 0.30),
+    Color::rgb(0.95, 0.60, 0.20),
+    Color::rgb(0.90, 0.85, 0.25),
+    Color::rgb(0.35, 0.75, 0.35),
+    Color::rgb(0.25, 0.75, 0.85),
+    Color::rgb(0.30, 0.45, 0.90),
+    Color::rgb(0.65, 0.40, 0.85),
+    Color::rgb(0.90, 0.45, 0.70),
+    Color::rgb(0.55, 0.40, 0.25),
+    Color::rgb(0.20, 0.55, 0.50),
+    Color::rgb(0.60, 0.80, 0.25),
+    Color::rgb(0.80, 0.35, 0.15),
+    Color::rgb(0.75, 0.70, 0.95),
+    Color::rgb(0.60, 0.15, 0.30),
+];
+
+#[must_use]
+pub fn comparison_color(algorithm: PathfindingAlgorithm) -> Color {
+    let index = PathfindingAlgorithm::ALL
+        .iter()
+        .position(|other| *other == algorithm)
+        .unwrap_or(0);
+    COMPARISON_COLORS[index % COMPARISON_COLORS.len()]
+}
+
+/// Heuristic weights the Weight button cycles through.
+pub const WEIGHTS: [f64; 5] = [1.25, 1.5, 2.0, 3.0, 5.0];
+
+/// Expansions per second the Play button cycles through.
+pub const PLAYBACK_SPEEDS: [f32; 5] = [2.0, 10.0, 50.0, 250.0, 1000.0];
+
+/// The timeline of the search the Step, Back, Rewind and Play buttons and the timeline bar move
+/// through, one expansion at a time.
+pub struct SearchPlayback {
+    pub timeline: Option<SearchTimeline>,
+    pub playing: bool,
+    /// Index into `PLAYBACK_SPEEDS`.
+    pub speed: usize,
+    /// Fraction of an expansion carried over to the next frame.
+    pub pending: f32,
+}
+
+impl Default for SearchPlayback {
+    fn default() -> Self {
+        SearchPlayback {
+            timeline: None,
+            playing: false,
+            speed: 1,
+            pending: 0.0,
+        }
+    }
+}
+
+impl SearchPlayback {
+    #[must_use]
+    pub fn expansions_per_second(&self) -> f32 {
+        PLAYBACK_SPEEDS[self.speed]
+    }
+
+    /// Drops the timeline, it no longer matches the map, start, goal or algorithm.
+    pub fn stop(&mut self) {
+        self.timeline = None;
+        self.playing = false;
+        self.pending = 0.0;
+    }
+}
+
+/// The search state of the last Solve with an incremental algorithm, kept so the path can be
+/// repaired when tiles, the start or the goal change instead of being searched again.
+#[derive(Default)]
+pub struct Replanner {
+    pub planner: Option<IncrementalPlanner>,
+    /// Nodes the search expanded when it was first solved, to compare repairs with.
+    pub first_expanded: usize,
+}
+
+/// Where the map comes from: the file named by the first command line argument, which is also
+/// where Save and Load go. Without one the app starts on a generated map and saves to
+/// `DEFAULT_MAP_PATH`. Read before the app starts so the startup systems can use it.
+#[derive(Debug)]
+pub struct MapSource {
+    pub path: PathBuf,
+    /// Taken by `setup_map`, `None` when there was no file to load.
+    pub map: Option<Map>,
+    pub start: Position,
+    pub goal: Position,
+}
+
+impl MapSource {
+    #[must_use]
+    pub fn from_args() -> MapSource {
+        let argument = std::env::args().nth(1);
+        let mut source = MapSource {
+            path: argument
+                .clone()
+                .unwrap_or_else(|| DEFAULT_MAP_PATH.to_string())
+                .into(),
+            map: None,
+            start: Position(2, 4),
+            goal: Position(6, 4),
+        };
+        if argument.is_some() {
+            match MapFile::load(&source.path) {
+                Ok(map_file) => {
+                    source.map = Some(map_file.map);
+                    source.start = map_file.start;
+                    source.goal = map_file.goal;
+                }
+                Err(error) => println!("Could not load {}: {}", source.path.display(), error),
+            }
+        }
+        source
+    }
 }
 
 // === Events ===
+pub struct MapUpdatedEvent {}
+
+/// A tile was blocked, opened or given another cost.
+pub struct TileChangedEvent {
+    pub position: Position,
+}
+
 pub struct SolveEvent {}
 
 pub struct StepEvent {}
@@ -58,33 +283,131 @@ pub struct PathfindingAlgorithmSelectionChangedEvent {
 
 pub struct PathfindingAlgorithmChangedEvent {}
 
+pub struct CycleCostModelEvent {}
+
+pub struct CostModelChangedEvent {}
+
+pub struct ToggleDiagonalsEvent {}
+
+pub struct DiagonalsChangedEvent {}
+
+pub struct CycleCornerCuttingEvent {}
+
+pub struct CornerCuttingChangedEvent {}
+
+pub struct CompareEvent {}
+
+pub struct SpawnAgentEvent {}
+
+pub struct PlanAgentsEvent {}
+
+pub struct CycleMultiAgentPlannerEvent {}
+
+pub struct MultiAgentPlannerChangedEvent {}
+
+/// The start/goal pairs or the plan for them changed, so their markers are drawn again.
+pub struct MultiAgentChangedEvent {}
+
+pub struct ToggleNodeStatsEvent {}
+
+pub struct NodeStatsChangedEvent {}
+
+pub struct CycleHeatMapEvent {}
+
+pub struct HeatMapChangedEvent {}
+
+pub struct ToggleFlowFieldEvent {}
+
+pub struct FlowFieldChangedEvent {}
+
+pub struct CycleWeightEvent {}
+
+pub struct WeightChangedEvent {}
+
+pub struct CycleSmoothingEvent {}
+
+pub struct SmoothingChangedEvent {}
+
+pub struct SaveMapEvent {}
+
+pub struct LoadMapEvent {}
+
+pub struct TogglePlayEvent {}
+
+pub struct CyclePlaybackSpeedEvent {}
+
+pub struct StepBackEvent {}
+
+pub struct RewindEvent {}
+
+/// Moves the timeline to a point of the whole search, `0.0` is the start and `1.0` the end.
+pub struct SeekEvent {
+    pub fraction: f32,
+}
+
 // === Systems ===
+pub fn setup_map(mut commands: Commands, mut map_source: ResMut<MapSource>) {
+    println!("Setup Map...");
+    let mut map = map_source
+        .map
+        .take()
+        .unwrap_or_else(|| Map::from_noise(MAP_WIDTH, MAP_HEIGHT, true));
+    map.build_jump_table();
+    map.build_hierarchy(DEFAULT_CLUSTER_SIZE);
+    commands.insert_resource(FlowField::new(&map, map_source.goal));
+    commands.insert_resource(map);
+}
+
 pub fn setup_game(
     mut commands: Commands,
     mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
     mut pathfinding_algorithm_changed_event: EventWriter<PathfindingAlgorithmChangedEvent>,
+    mut cost_model_changed_event: EventWriter<CostModelChangedEvent>,
+    mut diagonals_changed_event: EventWriter<DiagonalsChangedEvent>,
+    mut corner_cutting_changed_event: EventWriter<CornerCuttingChangedEvent>,
+    mut node_stats_changed_event: EventWriter<NodeStatsChangedEvent>,
+    mut weight_changed_event: EventWriter<WeightChangedEvent>,
+    map_source: Res<MapSource>,
 ) {
     println!("Setup Game...");
     commands.insert_resource(GameState {
         pathfinding_algorithm: PathfindingAlgorithm::BFS,
+        weight: DEFAULT_WEIGHT,
         placement_mode: PlacementMode::Obstacle,
-        start: Position(2, 4),
-        goal: Position(6, 4),
+        start: map_source.start,
+        goal: map_source.goal,
         path: Vec::new(),
+        unsmoothed_path: Vec::new(),
+        path_curve: Vec::new(),
+        smoothing: PathSmoothing::Off,
         tested: Vec::new(),
         searched: Vec::new(),
-        step: 0,
+        backward_tested: Vec::new(),
+        current: None,
+        expanded_nodes: Vec::new(),
+        show_node_stats: false,
+        show_flow_field: false,
+        heat_map: HeatMapMode::Off,
+        comparison: Vec::new(),
     });
     map_updated_event_writer.send(MapUpdatedEvent {});
     pathfinding_algorithm_changed_event.send(PathfindingAlgorithmChangedEvent {});
+    cost_model_changed_event.send(CostModelChangedEvent {});
+    diagonals_changed_event.send(DiagonalsChangedEvent {});
+    corner_cutting_changed_event.send(CornerCuttingChangedEvent {});
+    node_stats_changed_event.send(NodeStatsChangedEvent {});
+    weight_changed_event.send(WeightChangedEvent {});
 }
 
 pub fn placement_system(
     mut user_interface_interaction_event_reader: EventReader<UserInterfaceInteractionEvent>,
     mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
+    mut tile_changed_event_writer: EventWriter<TileChangedEvent>,
     tile_storage_query: Query<&TileStorage, With<CostsTileMap>>,
     mouse: Res<Mouse>,
     mut game_state: ResMut<GameState>,
+    mut playback: ResMut<SearchPlayback>,
+    replanner: Res<Replanner>,
     mut map: ResMut<Map>,
     mut commands: Commands,
 ) {
@@ -92,6 +415,10 @@ pub fn placement_system(
     for _ in user_interface_interaction_event_reader.iter() {
         return;
     }
+    // `multi_agent_placement_system` places agent pairs, the single-agent search stays as it is
+    if matches!(game_state.placement_mode, PlacementMode::AgentPair) {
+        return;
+    }
     if mouse.holding_lmb {
         let (x, y) = world_position_to_index(mouse.world_position);
         let clicked_position = Position(x, y);
@@ -109,8 +436,12 @@ pub fn placement_system(
         // println!("clicked index x: {}, y: {}", x, y);
         match game_state.placement_mode {
             PlacementMode::Path => {
-                let index = map.xy_idx(x, y);
-                map.blocked[index] = false;
+                if map.is_blocked(x, y) {
+                    tile_changed_event_writer.send(TileChangedEvent {
+                        position: clicked_position,
+                    });
+                }
+                map.set_blocked(x, y, false);
 
                 if let Ok(tile_storage) = tile_storage_query.get_single() {
                     if let Some(entity) = tile_storage.get(&TilePos {
@@ -122,8 +453,12 @@ pub fn placement_system(
                 }
             }
             PlacementMode::Obstacle => {
-                let index = map.xy_idx(x, y);
-                map.blocked[index] = true;
+                if !map.is_blocked(x, y) {
+                    tile_changed_event_writer.send(TileChangedEvent {
+                        position: clicked_position,
+                    });
+                }
+                map.set_blocked(x, y, true);
 
                 if let Ok(tile_storage) = tile_storage_query.get_single() {
                     if let Some(entity) = tile_storage.get(&TilePos {
@@ -144,18 +479,23 @@ pub fn placement_system(
                 // Do Nothing
             }
         }
-        game_state.path = Vec::new();
-        game_state.tested = Vec::new();
-        game_state.searched = Vec::new();
+        // with an incremental search `replan_system` repairs the path instead
+        if replanner.planner.is_none() {
+            game_state.clear_search();
+        }
+        playback.stop();
         map_updated_event_writer.send(MapUpdatedEvent {});
     }
 }
 pub fn cost_system(
     mut user_interface_interaction_event_reader: EventReader<UserInterfaceInteractionEvent>,
     mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
+    mut tile_changed_event_writer: EventWriter<TileChangedEvent>,
     mouse: Res<Mouse>,
     mouse_input: Res<Input<MouseButton>>,
     mut game_state: ResMut<GameState>,
+    mut playback: ResMut<SearchPlayback>,
+    replanner: Res<Replanner>,
     mut map: ResMut<Map>,
 ) {
     // This is a hack to prevent placement when buttons are clicked.
@@ -178,14 +518,20 @@ pub fn cost_system(
             PlacementMode::IncreaseCost => {
                 let index = map.xy_idx(x, y);
                 if let Some(current_cost) = map.costs[index] {
-                    map.costs[index] = Some(current_cost + 1);
+                    map.set_cost(x, y, Some(current_cost + 1));
+                    tile_changed_event_writer.send(TileChangedEvent {
+                        position: clicked_position,
+                    });
                 }
             }
             PlacementMode::DecreaseCost => {
                 let index = map.xy_idx(x, y);
                 if let Some(current_cost) = map.costs[index] {
                     if current_cost > 1 {
-                        map.costs[index] = Some(current_cost - 1);
+                        map.set_cost(x, y, Some(current_cost - 1));
+                        tile_changed_event_writer.send(TileChangedEvent {
+                            position: clicked_position,
+                        });
                     }
                 }
             }
@@ -193,7 +539,11 @@ pub fn cost_system(
                 // Do Nothing
             }
         }
-        game_state.path = Vec::new();
+        if replanner.planner.is_none() {
+            game_state.set_path(&map, Vec::new());
+        }
+        game_state.comparison = Vec::new();
+        playback.stop();
         map_updated_event_writer.send(MapUpdatedEvent {});
     }
 }
@@ -202,17 +552,177 @@ pub fn step_system(
     mut step_event_reader: EventReader<StepEvent>,
     mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
     mut game_state: ResMut<GameState>,
+    mut playback: ResMut<SearchPlayback>,
+    map: Res<Map>,
 ) {
     for _ in step_event_reader.iter() {
-        if !game_state.path.is_empty() {
-            if game_state.step < game_state.path.len() - 1 {
-                game_state.step = game_state.step + 1;
-            } else {
-                game_state.step = 2; // Why 2? Me Dumb?
-            }
+        playback.playing = false;
+        update_timeline(&mut playback, &mut game_state, &map, |timeline, map| {
+            timeline.step_forward(map);
+        });
+        map_updated_event_writer.send(MapUpdatedEvent {});
+    }
+}
+
+pub fn step_back_system(
+    mut step_back_event_reader: EventReader<StepBackEvent>,
+    mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
+    mut game_state: ResMut<GameState>,
+    mut playback: ResMut<SearchPlayback>,
+    map: Res<Map>,
+) {
+    for _ in step_back_event_reader.iter() {
+        playback.playing = false;
+        update_timeline(&mut playback, &mut game_state, &map, |timeline, _| {
+            timeline.step_back();
+        });
+        map_updated_event_writer.send(MapUpdatedEvent {});
+    }
+}
+
+pub fn rewind_system(
+    mut rewind_event_reader: EventReader<RewindEvent>,
+    mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
+    mut game_state: ResMut<GameState>,
+    mut playback: ResMut<SearchPlayback>,
+    map: Res<Map>,
+) {
+    for _ in rewind_event_reader.iter() {
+        playback.playing = false;
+        update_timeline(&mut playback, &mut game_state, &map, |timeline, map| {
+            timeline.seek(map, 0);
+        });
+        map_updated_event_writer.send(MapUpdatedEvent {});
+    }
+}
+
+pub fn seek_system(
+    mut seek_event_reader: EventReader<SeekEvent>,
+    mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
+    mut game_state: ResMut<GameState>,
+    mut playback: ResMut<SearchPlayback>,
+    map: Res<Map>,
+) {
+    // dragging sends one event per frame, only the latest matters
+    if let Some(event) = seek_event_reader.iter().last() {
+        let fraction = event.fraction.clamp(0.0, 1.0);
+        playback.playing = false;
+        update_timeline(&mut playback, &mut game_state, &map, |timeline, map| {
+            // the bar spans the whole search, so record all of it first
+            timeline.complete(map);
+            let position = (fraction * timeline.len() as f32).round() as usize;
+            timeline.seek(map, position);
+        });
+        map_updated_event_writer.send(MapUpdatedEvent {});
+    }
+}
+
+pub fn play_system(
+    mut toggle_play_event_reader: EventReader<TogglePlayEvent>,
+    mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
+    mut game_state: ResMut<GameState>,
+    mut playback: ResMut<SearchPlayback>,
+    map: Res<Map>,
+    time: Res<Time>,
+) {
+    for _ in toggle_play_event_reader.iter() {
+        playback.playing = !playback.playing;
+        playback.pending = 0.0;
+        // playing a search that was shown to the end plays it again
+        if playback.playing
+            && playback
+                .timeline
+                .as_ref()
+                .map_or(false, SearchTimeline::at_end)
+        {
+            update_timeline(&mut playback, &mut game_state, &map, |timeline, map| {
+                timeline.seek(map, 0);
+            });
             map_updated_event_writer.send(MapUpdatedEvent {});
         }
     }
+    if !playback.playing {
+        return;
+    }
+
+    playback.pending += playback.expansions_per_second() * time.delta_seconds();
+    let steps = playback.pending.floor();
+    if steps >= 1.0 {
+        playback.pending -= steps;
+        let at_end = update_timeline(&mut playback, &mut game_state, &map, |timeline, map| {
+            timeline.seek(map, timeline.position() + steps as usize);
+        });
+        if at_end {
+            playback.playing = false;
+        }
+        map_updated_event_writer.send(MapUpdatedEvent {});
+    }
+}
+
+pub fn cycle_playback_speed_system(
+    mut cycle_playback_speed_event_reader: EventReader<CyclePlaybackSpeedEvent>,
+    mut playback: ResMut<SearchPlayback>,
+) {
+    for _ in cycle_playback_speed_event_reader.iter() {
+        playback.speed = (playback.speed + 1) % PLAYBACK_SPEEDS.len();
+    }
+}
+
+/// Moves the playback timeline with `move_to`, starting a new one when there is none, and copies
+/// what the search looked like at that point into `game_state`. Prints the result once the search
+/// is over. Returns whether the timeline shows the end of the search.
+fn update_timeline(
+    playback: &mut SearchPlayback,
+    game_state: &mut GameState,
+    map: &Map,
+    move_to: impl FnOnce(&mut SearchTimeline, &Map),
+) -> bool {
+    let timeline = playback.timeline.get_or_insert_with(|| {
+        SearchTimeline::new(
+            game_state
+                .pathfinder()
+                .steps(map, game_state.start, game_state.goal),
+        )
+    });
+    let was_complete = timeline.is_complete();
+    move_to(timeline, map);
+    if !was_complete {
+        if let Some(result) = timeline.result() {
+            print_result(result);
+        }
+    }
+
+    let snapshot = timeline.snapshot();
+    game_state.tested = snapshot.open;
+    game_state.backward_tested = snapshot.backward_open;
+    game_state.searched = snapshot.closed;
+    game_state.expanded_nodes = snapshot.expanded;
+    game_state.current = snapshot.current;
+    game_state.set_path(map, snapshot.path.unwrap_or_default());
+    game_state.comparison = Vec::new();
+    timeline.at_end()
+}
+
+fn print_result(result: &SearchResult) {
+    if let Some(path) = &result.path {
+        println!("Path: {:?}", path);
+        // step costs are fixed point, print them in tiles
+        println!(
+            "Cost: {:?}",
+            result.cost.map(|cost| cost as f32 / STRAIGHT_COST as f32)
+        );
+        if let Some(bound) = result.bound {
+            println!("At most {:.2} times the optimal cost", bound);
+        }
+    } else {
+        println!("No Path Found!");
+    }
+    println!(
+        "Expanded: {}, Generated: {}, Time: {:?}",
+        result.expanded.len(),
+        result.generated.len(),
+        result.elapsed
+    );
 }
 
 // See Reference 1
@@ -220,139 +730,144 @@ pub fn solve_system(
     mut solve_event_reader: EventReader<SolveEvent>,
     mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
     mut game_state: ResMut<GameState>,
+    mut playback: ResMut<SearchPlayback>,
+    mut replanner: ResMut<Replanner>,
     map: Res<Map>,
 ) {
     for _ in solve_event_reader.iter() {
         println!("Attempting to solve...");
-        let start = game_state.start;
-        let goal = game_state.goal;
-        match game_state.pathfinding_algorithm {
-            PathfindingAlgorithm::AStar => {
-                let mut searched = Vec::new();
-                let result = astar(
-                    &start,
-                    |position| {
-                        let successors = map
-                            .get_successors(position)
-                            .iter()
-                            .map(|successor| (successor.position, successor.cost))
-                            .collect::<Vec<_>>();
-
-                        for suc in &successors {
-                            searched.push(suc.0);
-                        }
-
-                        successors
-                    },
-                    |position| position.distance(&goal),
-                    |position| *position == goal,
-                );
-                if let Some(result) = result {
-                    println!("Path: {:?}", result.0);
-                    println!("Cost: {:?}", result.1);
-                    game_state.path = result.0;
-                    game_state.step = game_state.path.len();
-                } else {
-                    println!("No Path Found!");
-                    game_state.path = Vec::new();
-                    game_state.step = 0;
-                }
-
-                game_state.tested = searched;
+        playback.stop();
+        let (start, goal) = (game_state.start, game_state.goal);
+        let result = match game_state
+            .pathfinding_algorithm
+            .incremental(&map, start, goal)
+        {
+            Some(mut planner) => {
+                let result = planner.plan(&map);
+                replanner.first_expanded = result.expanded_count();
+                replanner.planner = Some(planner);
+                result
             }
-            PathfindingAlgorithm::BFS => {
-                let mut searched = Vec::new();
-                let result = bfs(
-                    &start,
-                    |position| {
-                        let successors = map
-                            .get_successors(position)
-                            .iter()
-                            .map(|successor| successor.position)
-                            .collect::<Vec<_>>();
-
-                        for pos in &successors {
-                            searched.push(pos.clone())
-                        }
+            None => {
+                replanner.planner = None;
+                game_state.pathfinder().find_path(&map, start, goal)
+            }
+        };
+        print_result(&result);
 
-                        successors
-                    },
-                    |position| *position == goal,
-                );
-                if let Some(result) = result {
-                    println!("Path: {:?}", result);
-                    game_state.path = result;
-                    game_state.step = game_state.path.len();
-                } else {
-                    println!("No Path Found!");
-                    game_state.path = Vec::new();
-                    game_state.step = 0;
-                }
+        game_state.set_path(&map, result.path.unwrap_or_default());
+        game_state.tested = result.generated;
+        game_state.searched = result.expanded;
+        game_state.backward_tested = Vec::new();
+        game_state.expanded_nodes = result.expanded_nodes;
+        game_state.current = None;
+        game_state.comparison = Vec::new();
+        map_updated_event_writer.send(MapUpdatedEvent {});
+    }
+}
 
-                game_state.tested = searched;
-            }
-            PathfindingAlgorithm::Dijkstra => {
-                let mut searched = Vec::new();
-                let result = dijkstra(
-                    &start,
-                    |position| {
-                        let successors = map
-                            .get_successors(position)
-                            .iter()
-                            .map(|successor| (successor.position, successor.cost))
-                            .collect::<Vec<_>>();
-
-                        for suc in &successors {
-                            searched.push(suc.0);
-                        }
+/// Repairs the path of the incremental search after tiles, the start or the goal changed, and
+/// shows only the nodes the repair expanded.
+pub fn replan_system(
+    mut tile_changed_event_reader: EventReader<TileChangedEvent>,
+    mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
+    mut game_state: ResMut<GameState>,
+    mut replanner: ResMut<Replanner>,
+    map: Res<Map>,
+) {
+    let tiles: Vec<Position> = tile_changed_event_reader
+        .iter()
+        .map(|event| event.position)
+        .collect();
+    let (start, goal) = (game_state.start, game_state.goal);
+    let planner = match replanner.planner.as_mut() {
+        Some(planner) => planner,
+        None => return,
+    };
+    if tiles.is_empty() && planner.start() == start && planner.goal() == goal {
+        return;
+    }
 
-                        successors
-                    },
-                    |position| *position == goal,
-                );
-                if let Some(result) = result {
-                    println!("Path: {:?}", result.0);
-                    println!("Cost: {:?}", result.1);
-                    game_state.path = result.0;
-                    game_state.step = game_state.path.len();
-                } else {
-                    println!("No Path Found!");
-                    game_state.path = Vec::new();
-                    game_state.step = 0;
-                }
+    planner.update_tiles(&map, &tiles);
+    // D* Lite keeps its search when the start moves, anything else has to start over
+    let kept =
+        planner.goal() == goal && (planner.start() == start || planner.move_start(&map, start));
+    let result = if kept {
+        let result = planner.plan(&map);
+        println!(
+            "Repaired the path, re-expanded {} nodes (the first search expanded {})",
+            result.expanded_count(),
+            replanner.first_expanded
+        );
+        result
+    } else {
+        let mut planner = match game_state
+            .pathfinding_algorithm
+            .incremental(&map, start, goal)
+        {
+            Some(planner) => planner,
+            None => return,
+        };
+        let result = planner.plan(&map);
+        replanner.first_expanded = result.expanded_count();
+        replanner.planner = Some(planner);
+        println!("Searched again, expanded {} nodes", result.expanded_count());
+        result
+    };
+    if result.path.is_none() {
+        println!("No Path Found!");
+    }
 
-                game_state.tested = searched;
-            }
-            PathfindingAlgorithm::Jps => {
-                let result = Jps::find_path(&map, start, goal);
-                if let Some(result) = result.0 {
-                    println!("Path: {:?}", result);
-                    game_state.path = result;
-                    game_state.step = game_state.path.len();
-                } else {
-                    println!("No Path Found!");
-                    game_state.path = Vec::new();
-                    game_state.step = 0;
-                }
+    game_state.set_path(&map, result.path.unwrap_or_default());
+    game_state.tested = result.generated;
+    game_state.searched = result.expanded;
+    game_state.backward_tested = Vec::new();
+    game_state.expanded_nodes = result.expanded_nodes;
+    game_state.current = None;
+    game_state.comparison = Vec::new();
+    map_updated_event_writer.send(MapUpdatedEvent {});
+}
 
-                game_state.tested = result.1;
-                game_state.searched = result.2;
-            }
+/// Solves the same start and goal with every algorithm, so their paths can be drawn on top of
+/// each other and their numbers put side by side.
+pub fn compare_system(
+    mut compare_event_reader: EventReader<CompareEvent>,
+    mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
+    mut game_state: ResMut<GameState>,
+    mut playback: ResMut<SearchPlayback>,
+    mut replanner: ResMut<Replanner>,
+    map: Res<Map>,
+) {
+    for _ in compare_event_reader.iter() {
+        playback.stop();
+        game_state.clear_search();
+        replanner.planner = None;
+        for algorithm in PathfindingAlgorithm::ALL {
+            let result = algorithm.weighted_pathfinder(game_state.weight).find_path(
+                &map,
+                game_state.start,
+                game_state.goal,
+            );
+            println!("{}:", algorithm.name());
+            print_result(&result);
+            game_state
+                .comparison
+                .push(ComparedPath { algorithm, result });
         }
         map_updated_event_writer.send(MapUpdatedEvent {});
     }
 }
-
-// Reset the Path Solve
 pub fn reset_system(
     mut reset_event_reader: EventReader<ResetEvent>,
     mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
     mut game_state: ResMut<GameState>,
+    mut playback: ResMut<SearchPlayback>,
+    mut replanner: ResMut<Replanner>,
 ) {
     for _ in reset_event_reader.iter() {
-        game_state.path = Vec::new();
-        game_state.tested = Vec::new();
-        game_state.searched = Vec::new();
+        game_state.clear_search();
+        playback.stop();
+        replanner.planner = None;
         map_updated_event_writer.send(MapUpdatedEvent {});
     }
 }
@@ -362,16 +877,20 @@ pub fn clear_system(
     mut clear_event_reader: EventReader<ClearEvent>,
     mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
     mut game_state: ResMut<GameState>,
+    mut playback: ResMut<SearchPlayback>,
+    mut replanner: ResMut<Replanner>,
     mut map: ResMut<Map>,
 ) {
     for _ in clear_event_reader.iter() {
-        game_state.path = Vec::new();
+        game_state.clear_search();
         game_state.start = Position(2, 4);
         game_state.goal = Position(6, 4);
-        game_state.tested.clear();
-        game_state.searched.clear();
+        playback.stop();
+        replanner.planner = None;
         map.costs = vec![Some(1); (map.width * map.height) as usize];
         map.blocked = vec![false; (map.width * map.height) as usize];
+        map.build_jump_table();
+        map.build_hierarchy(DEFAULT_CLUSTER_SIZE);
         map_updated_event_writer.send(MapUpdatedEvent {});
     }
 }
@@ -387,31 +906,234 @@ pub fn change_pathfinding_algorithm_system(
     for pathfinding_algorithm_selection_changed_event in
         pathfinding_algorithm_selection_changed_event_reader.iter()
     {
-        match pathfinding_algorithm_selection_changed_event.pathfinding_algorithm {
-            PathfindingAlgorithm::AStar => {
-                game_state.pathfinding_algorithm = PathfindingAlgorithm::AStar;
-            }
-            PathfindingAlgorithm::BFS => {
-                game_state.pathfinding_algorithm = PathfindingAlgorithm::BFS;
-            }
-            PathfindingAlgorithm::Dijkstra => {
-                game_state.pathfinding_algorithm = PathfindingAlgorithm::Dijkstra;
+        game_state.pathfinding_algorithm =
+            pathfinding_algorithm_selection_changed_event.pathfinding_algorithm;
+        pathfinding_algorithm_changed_event_writer.send(PathfindingAlgorithmChangedEvent {});
+        reset_event_writer.send(ResetEvent {});
+    }
+}
+
+pub fn change_cost_model_system(
+    mut cycle_cost_model_event_reader: EventReader<CycleCostModelEvent>,
+    mut cost_model_changed_event_writer: EventWriter<CostModelChangedEvent>,
+    mut reset_event_writer: EventWriter<ResetEvent>,
+    mut map: ResMut<Map>,
+) {
+    for _ in cycle_cost_model_event_reader.iter() {
+        let cost_model = map.cost_model.next();
+        map.set_cost_model(cost_model);
+        cost_model_changed_event_writer.send(CostModelChangedEvent {});
+        reset_event_writer.send(ResetEvent {});
+    }
+}
+
+pub fn cycle_weight_system(
+    mut cycle_weight_event_reader: EventReader<CycleWeightEvent>,
+    mut weight_changed_event_writer: EventWriter<WeightChangedEvent>,
+    mut reset_event_writer: EventWriter<ResetEvent>,
+    mut game_state: ResMut<GameState>,
+) {
+    for _ in cycle_weight_event_reader.iter() {
+        // the next weight up, back to the smallest after the largest
+        let weight = game_state.weight;
+        game_state.weight = WEIGHTS
+            .iter()
+            .copied()
+            .find(|next| *next > weight)
+            .unwrap_or(WEIGHTS[0]);
+        weight_changed_event_writer.send(WeightChangedEvent {});
+        reset_event_writer.send(ResetEvent {});
+    }
+}
+
+pub fn toggle_diagonals_system(
+    mut toggle_diagonals_event_reader: EventReader<ToggleDiagonalsEvent>,
+    mut diagonals_changed_event_writer: EventWriter<DiagonalsChangedEvent>,
+    mut reset_event_writer: EventWriter<ResetEvent>,
+    mut map: ResMut<Map>,
+) {
+    for _ in toggle_diagonals_event_reader.iter() {
+        let allow_diagonals = !map.allow_diagonals;
+        map.set_allow_diagonals(allow_diagonals);
+        diagonals_changed_event_writer.send(DiagonalsChangedEvent {});
+        reset_event_writer.send(ResetEvent {});
+    }
+}
+
+pub fn change_corner_cutting_system(
+    mut cycle_corner_cutting_event_reader: EventReader<CycleCornerCuttingEvent>,
+    mut corner_cutting_changed_event_writer: EventWriter<CornerCuttingChangedEvent>,
+    mut reset_event_writer: EventWriter<ResetEvent>,
+    mut map: ResMut<Map>,
+) {
+    for _ in cycle_corner_cutting_event_reader.iter() {
+        let corner_cutting = map.corner_cutting.next();
+        map.set_corner_cutting(corner_cutting);
+        corner_cutting_changed_event_writer.send(CornerCuttingChangedEvent {});
+        reset_event_writer.send(ResetEvent {});
+    }
+}
+
+pub fn toggle_node_stats_system(
+    mut toggle_node_stats_event_reader: EventReader<ToggleNodeStatsEvent>,
+    mut node_stats_changed_event_writer: EventWriter<NodeStatsChangedEvent>,
+    mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
+    mut game_state: ResMut<GameState>,
+) {
+    for _ in toggle_node_stats_event_reader.iter() {
+        game_state.show_node_stats = !game_state.show_node_stats;
+        node_stats_changed_event_writer.send(NodeStatsChangedEvent {});
+        map_updated_event_writer.send(MapUpdatedEvent {});
+    }
+}
+
+/// The field is only kept up to date while it is shown, so it is built again when it is switched
+/// on.
+pub fn toggle_flow_field_system(
+    mut toggle_flow_field_event_reader: EventReader<ToggleFlowFieldEvent>,
+    mut flow_field_changed_event_writer: EventWriter<FlowFieldChangedEvent>,
+    mut game_state: ResMut<GameState>,
+    mut flow_field: ResMut<FlowField>,
+    map: Res<Map>,
+) {
+    for _ in toggle_flow_field_event_reader.iter() {
+        game_state.show_flow_field = !game_state.show_flow_field;
+        if game_state.show_flow_field {
+            *flow_field = FlowField::new(&map, game_state.goal);
+        }
+        flow_field_changed_event_writer.send(FlowFieldChangedEvent {});
+    }
+}
+
+/// Builds the flow field again whenever the map or the goal changed while it is shown, so it
+/// always heads for the current goal.
+pub fn update_flow_field_system(
+    mut flow_field: ResMut<FlowField>,
+    game_state: Res<GameState>,
+    map: Res<Map>,
+) {
+    if !game_state.show_flow_field {
+        return;
+    }
+    if map.is_changed() || flow_field.goal != game_state.goal {
+        *flow_field = FlowField::new(&map, game_state.goal);
+    }
+}
+
+pub fn cycle_heat_map_system(
+    mut cycle_heat_map_event_reader: EventReader<CycleHeatMapEvent>,
+    mut heat_map_changed_event_writer: EventWriter<HeatMapChangedEvent>,
+    mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
+    mut game_state: ResMut<GameState>,
+) {
+    for _ in cycle_heat_map_event_reader.iter() {
+        game_state.heat_map = game_state.heat_map.next();
+        heat_map_changed_event_writer.send(HeatMapChangedEvent {});
+        map_updated_event_writer.send(MapUpdatedEvent {});
+    }
+}
+
+/// Smooths the current path again from the one the search returned.
+pub fn cycle_smoothing_system(
+    mut cycle_smoothing_event_reader: EventReader<CycleSmoothingEvent>,
+    mut smoothing_changed_event_writer: EventWriter<SmoothingChangedEvent>,
+    mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
+    mut game_state: ResMut<GameState>,
+    map: Res<Map>,
+) {
+    for _ in cycle_smoothing_event_reader.iter() {
+        game_state.smoothing = game_state.smoothing.next();
+        let path = std::mem::take(&mut game_state.unsmoothed_path);
+        game_state.set_path(&map, path);
+        smoothing_changed_event_writer.send(SmoothingChangedEvent {});
+        map_updated_event_writer.send(MapUpdatedEvent {});
+    }
+}
+
+pub fn save_map_system(
+    mut save_map_event_reader: EventReader<SaveMapEvent>,
+    game_state: Res<GameState>,
+    map: Res<Map>,
+    map_source: Res<MapSource>,
+) {
+    for _ in save_map_event_reader.iter() {
+        match MapFile::save(&map_source.path, &map, game_state.start, game_state.goal) {
+            Ok(()) => println!("Saved map to {}", map_source.path.display()),
+            Err(error) => println!("Could not save {}: {}", map_source.path.display(), error),
+        }
+    }
+}
+
+// The tilemaps are spawned once, so only maps of the current size can be loaded here
+pub fn load_map_system(
+    mut load_map_event_reader: EventReader<LoadMapEvent>,
+    mut map_updated_event_writer: EventWriter<MapUpdatedEvent>,
+    tile_storage_query: Query<&TileStorage, With<CostsTileMap>>,
+    mut game_state: ResMut<GameState>,
+    mut playback: ResMut<SearchPlayback>,
+    mut replanner: ResMut<Replanner>,
+    mut map: ResMut<Map>,
+    map_source: Res<MapSource>,
+    mut commands: Commands,
+) {
+    for _ in load_map_event_reader.iter() {
+        let map_file = match MapFile::load(&map_source.path) {
+            Ok(map_file) => map_file,
+            Err(error) => {
+                println!("Could not load {}: {}", map_source.path.display(), error);
+                continue;
             }
+        };
+        if map_file.map.width != map.width || map_file.map.height != map.height {
+            println!(
+                "Could not load {}: the map is {}x{}, expected {}x{}",
+                map_source.path.display(),
+                map_file.map.width,
+                map_file.map.height,
+                map.width,
+                map.height
+            );
+            continue;
+        }
 
-            PathfindingAlgorithm::Jps => {
-                game_state.pathfinding_algorithm = PathfindingAlgorithm::Jps
+        let mut loaded = map_file.map;
+        loaded.allow_diagonals = map.allow_diagonals;
+        loaded.cost_model = map.cost_model;
+        loaded.corner_cutting = map.corner_cutting;
+        loaded.build_jump_table();
+        loaded.build_hierarchy(DEFAULT_CLUSTER_SIZE);
+        *map = loaded;
+
+        if let Ok(tile_storage) = tile_storage_query.get_single() {
+            for y in 0..map.height {
+                for x in 0..map.width {
+                    if let Some(entity) = tile_storage.get(&TilePos {
+                        x: x as u32,
+                        y: y as u32,
+                    }) {
+                        if map.is_path(x, y) {
+                            commands.entity(entity).insert(CostsTile {});
+                        } else {
+                            commands.entity(entity).remove::<CostsTile>();
+                        }
+                    }
+                }
             }
         }
-        pathfinding_algorithm_changed_event_writer.send(PathfindingAlgorithmChangedEvent {});
-        reset_event_writer.send(ResetEvent {});
+
+        game_state.start = map_file.start;
+        game_state.goal = map_file.goal;
+        game_state.clear_search();
+        playback.stop();
+        replanner.planner = None;
+        println!("Loaded map from {}", map_source.path.display());
+        map_updated_event_writer.send(MapUpdatedEvent {});
     }
 }
 
 // References
 // 1. Pathfinding Docs
-// https://docs.rs/pathfinding/latest/pathfinding/directed/astar/fn.astar.html
-// https://docs.rs/pathfinding/latest/pathfinding/directed/bfs/index.html
-// https://docs.rs/pathfinding/latest/pathfinding/directed/dijkstra/index.html
+// See `solver.rs`.
 // 2. Pathfinding in Rust: A tutorial with examples
 // https://blog.logrocket.com/pathfinding-rust-tutorial-examples/
 // https://github.com/gregstoll/rust-pathfinding
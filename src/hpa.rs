@@ -0,0 +1,510 @@
+use std::collections::{HashMap, HashSet};
+
+use pathfinding::prelude::{dijkstra, dijkstra_all};
+
+use crate::{BestFirstSearch, Expander, Map, Pathfinder, Position, SearchSteps};
+
+/// Cluster size used when a `Map` has no hierarchy of its own.
+pub const DEFAULT_CLUSTER_SIZE: i32 = 8;
+
+/// Entrances shorter than this get a single transition in the middle, longer ones one at each end.
+const MAX_ENTRANCE_WIDTH: usize = 6;
+
+/// The abstract graph HPA* searches.
+///
+/// The map is cut into square clusters. Wherever two neighboring clusters share open tiles along
+/// their border there is an entrance, represented by one or two transitions: pairs of adjacent
+/// tiles, one on each side. Transition tiles are the abstract nodes; they are connected across
+/// the border by a single step and inside a cluster by the cost of the shortest path that stays
+/// in that cluster.
+#[derive(Clone, Debug)]
+pub struct Hierarchy {
+    cluster_size: i32,
+    width: i32,
+    height: i32,
+    clusters_x: i32,
+    clusters_y: i32,
+    /// Transitions per border, keyed by the two cluster indices (lowest first).
+    borders: HashMap<(usize, usize), Vec<(Position, Position)>>,
+    /// Tiles on the other side of each transition.
+    transitions: HashMap<Position, Vec<Position>>,
+    /// Intra-cluster edges per cluster, from each transition tile to the others.
+    edges: Vec<HashMap<Position, Vec<(Position, i32)>>>,
+}
+
+impl Hierarchy {
+    #[must_use]
+    pub fn new(map: &Map, cluster_size: i32) -> Hierarchy {
+        let clusters_x = (map.width + cluster_size - 1) / cluster_size;
+        let clusters_y = (map.height + cluster_size - 1) / cluster_size;
+        let mut hierarchy = Hierarchy {
+            cluster_size,
+            width: map.width,
+            height: map.height,
+            clusters_x,
+            clusters_y,
+            borders: HashMap::new(),
+            transitions: HashMap::new(),
+            edges: vec![HashMap::new(); (clusters_x * clusters_y) as usize],
+        };
+
+        for cluster in 0..hierarchy.edges.len() {
+            for neighbor in hierarchy.neighbor_clusters(cluster) {
+                if cluster < neighbor {
+                    hierarchy.build_border(map, cluster, neighbor);
+                }
+            }
+        }
+        hierarchy.build_transitions();
+        for cluster in 0..hierarchy.edges.len() {
+            hierarchy.build_edges(map, cluster);
+        }
+        hierarchy
+    }
+
+    /// Whether the hierarchy was built for a map of this size.
+    #[must_use]
+    pub fn fits(&self, map: &Map) -> bool {
+        self.width == map.width && self.height == map.height
+    }
+
+    #[must_use]
+    pub fn cluster_size(&self) -> i32 {
+        self.cluster_size
+    }
+
+    #[must_use]
+    pub fn cluster_of(&self, x: i32, y: i32) -> usize {
+        ((y / self.cluster_size) * self.clusters_x + x / self.cluster_size) as usize
+    }
+
+    /// Number of abstract nodes, handy to compare with the size of the map.
+    #[must_use]
+    pub fn node_count(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// Rebuilds the clusters a change of the tile at `(x, y)` can affect: every cluster within one
+    /// tile of it, and the borders between them.
+    pub fn update(&mut self, map: &Map, x: i32, y: i32) {
+        let dirty: HashSet<usize> = (y - 1..=y + 1)
+            .flat_map(|ny| (x - 1..=x + 1).map(move |nx| (nx, ny)))
+            .filter(|(nx, ny)| map.inside(*nx, *ny))
+            .map(|(nx, ny)| self.cluster_of(nx, ny))
+            .collect();
+        for cluster in &dirty {
+            for neighbor in self.neighbor_clusters(*cluster) {
+                if *cluster < neighbor && dirty.contains(&neighbor) {
+                    self.build_border(map, *cluster, neighbor);
+                }
+            }
+        }
+        self.build_transitions();
+        for cluster in dirty {
+            self.build_edges(map, cluster);
+        }
+    }
+
+    /// Inclusive tile bounds of `cluster`, clipped to the map.
+    fn bounds(&self, cluster: usize) -> (i32, i32, i32, i32) {
+        let (cx, cy) = (
+            cluster as i32 % self.clusters_x,
+            cluster as i32 / self.clusters_x,
+        );
+        let (x0, y0) = (cx * self.cluster_size, cy * self.cluster_size);
+        (
+            x0,
+            y0,
+            (x0 + self.cluster_size).min(self.width) - 1,
+            (y0 + self.cluster_size).min(self.height) - 1,
+        )
+    }
+
+    fn contains(&self, cluster: usize, position: Position) -> bool {
+        let (x0, y0, x1, y1) = self.bounds(cluster);
+        (x0..=x1).contains(&position.x()) && (y0..=y1).contains(&position.y())
+    }
+
+    /// The clusters left, right, above and below `cluster`.
+    fn neighbor_clusters(&self, cluster: usize) -> Vec<usize> {
+        let (cx, cy) = (
+            cluster as i32 % self.clusters_x,
+            cluster as i32 / self.clusters_x,
+        );
+        [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .into_iter()
+            .map(|(dx, dy)| (cx + dx, cy + dy))
+            .filter(|(nx, ny)| {
+                0 <= *nx && *nx < self.clusters_x && 0 <= *ny && *ny < self.clusters_y
+            })
+            .map(|(nx, ny)| (ny * self.clusters_x + nx) as usize)
+            .collect()
+    }
+
+    /// Finds the transitions between `a` and the cluster `b` to its right or below it.
+    fn build_border(&mut self, map: &Map, a: usize, b: usize) {
+        let (x0, y0, x1, y1) = self.bounds(a);
+        let same_row = a as i32 / self.clusters_x == b as i32 / self.clusters_x;
+        let pairs: Vec<(Position, Position)> = if same_row {
+            (y0..=y1)
+                .map(|y| (Position(x1, y), Position(x1 + 1, y)))
+                .collect()
+        } else {
+            (x0..=x1)
+                .map(|x| (Position(x, y1), Position(x, y1 + 1)))
+                .collect()
+        };
+
+        let mut transitions = vec![];
+        let mut run: Vec<(Position, Position)> = vec![];
+        for pair in pairs.into_iter().map(Some).chain([None]) {
+            match pair {
+                Some((p, q)) if map.is_path(p.x(), p.y()) && map.is_path(q.x(), q.y()) => {
+                    run.push((p, q));
+                }
+                _ => {
+                    if run.len() >= MAX_ENTRANCE_WIDTH {
+                        transitions.push(run[0]);
+                        transitions.push(run[run.len() - 1]);
+                    } else if !run.is_empty() {
+                        transitions.push(run[run.len() / 2]);
+                    }
+                    run.clear();
+                }
+            }
+        }
+
+        // diagonal steps between two blocked tiles (only when corners may always be cut) are an
+        // entrance of their own; steps past the corner of four clusters are kept on the border
+        // below so they are only found once
+        let diagonals: Vec<(Position, (i32, i32))> = if same_row {
+            (y0..=y1)
+                .flat_map(|y| [(Position(x1, y), (1, 1)), (Position(x1, y), (1, -1))])
+                .filter(|(p, (_, dy))| (y0..=y1).contains(&(p.y() + dy)))
+                .collect()
+        } else {
+            (x0..=x1)
+                .flat_map(|x| [(Position(x, y1), (1, 1)), (Position(x, y1), (-1, 1))])
+                .collect()
+        };
+        for (p, (dx, dy)) in diagonals {
+            let (x, y) = (p.x(), p.y());
+            let squeezed = map.is_blocked(x + dx, y) && map.is_blocked(x, y + dy);
+            if map.is_path(x, y) && map.can_move(x, y, dx, dy) && squeezed {
+                transitions.push((p, Position(x + dx, y + dy)));
+            }
+        }
+        self.borders.insert((a, b), transitions);
+    }
+
+    fn build_transitions(&mut self) {
+        self.transitions.clear();
+        for (p, q) in self.borders.values().flatten() {
+            self.transitions.entry(*p).or_default().push(*q);
+            self.transitions.entry(*q).or_default().push(*p);
+        }
+    }
+
+    fn build_edges(&mut self, map: &Map, cluster: usize) {
+        let nodes: Vec<Position> = self
+            .transitions
+            .keys()
+            .filter(|node| self.contains(cluster, **node))
+            .copied()
+            .collect();
+
+        let mut edges = HashMap::new();
+        for node in &nodes {
+            let reachable = dijkstra_all(node, |position| {
+                self.local_successors(map, cluster, *position)
+            });
+            let targets = nodes
+                .iter()
+                .filter_map(|other| reachable.get(other).map(|(_, cost)| (*other, *cost)))
+                .collect();
+            edges.insert(*node, targets);
+        }
+        self.edges[cluster] = edges;
+    }
+
+    fn local_successors(
+        &self,
+        map: &Map,
+        cluster: usize,
+        position: Position,
+    ) -> Vec<(Position, i32)> {
+        map.get_successors(&position)
+            .into_iter()
+            .filter(|successor| self.contains(cluster, successor.position))
+            .map(|successor| (successor.position, successor.cost))
+            .collect()
+    }
+
+    /// Shortest path from `from` to `to` that does not leave `cluster`.
+    fn local_path(
+        &self,
+        map: &Map,
+        cluster: usize,
+        from: Position,
+        to: Position,
+    ) -> Option<(Vec<Position>, i32)> {
+        dijkstra(
+            &from,
+            |position| self.local_successors(map, cluster, *position),
+            |position| *position == to,
+        )
+    }
+
+    /// Abstract edges leaving `node`: the intra-cluster ones and the steps across borders.
+    fn abstract_successors(&self, map: &Map, node: Position) -> Vec<(Position, i32)> {
+        let cluster = self.cluster_of(node.x(), node.y());
+        let mut successors = self.edges[cluster].get(&node).cloned().unwrap_or_default();
+        if let Some(others) = self.transitions.get(&node) {
+            successors.extend(
+                others
+                    .iter()
+                    .map(|other| (*other, map.step_cost(&node, other))),
+            );
+        }
+        successors
+    }
+}
+
+/// Hierarchical pathfinding (HPA*).
+///
+/// Searches the abstract graph of `Map::hierarchy` with the start and goal temporarily connected
+/// to the transitions of their clusters, then refines every abstract edge into tiles with a search
+/// that stays inside one cluster. Paths are near optimal, not optimal.
+///
+/// Build the hierarchy once with `Map::build_hierarchy`, tile changes keep it up to date. Without
+/// one every query builds a throwaway hierarchy, which costs far more than the search itself.
+pub struct Hpa;
+
+impl Pathfinder for Hpa {
+    fn name(&self) -> &'static str {
+        "HPA*"
+    }
+
+    fn steps(&self, map: &Map, start: Position, goal: Position) -> Box<dyn SearchSteps> {
+        let hierarchy = (!map.has_hierarchy()).then(|| Hierarchy::new(map, DEFAULT_CLUSTER_SIZE));
+        let mut expander = HpaExpander {
+            hierarchy,
+            start,
+            start_edges: Vec::new(),
+            goal_edges: HashMap::new(),
+        };
+        if map.is_path(start.x(), start.y()) && map.is_path(goal.x(), goal.y()) {
+            expander.connect(map, start, goal);
+        }
+        Box::new(BestFirstSearch::new(map, expander, start, goal))
+    }
+}
+
+/// Expands a node of the abstract graph, with the start and goal connected to it.
+pub struct HpaExpander {
+    /// Built for this search when the map has no hierarchy of its own.
+    hierarchy: Option<Hierarchy>,
+    start: Position,
+    /// Transitions of the start's cluster (and the goal, when it shares it) reachable from the
+    /// start, with their cost.
+    start_edges: Vec<(Position, i32)>,
+    /// Transitions of the goal's cluster the goal can be reached from, with their cost.
+    goal_edges: HashMap<Position, i32>,
+}
+
+impl HpaExpander {
+    fn hierarchy<'a>(&'a self, map: &'a Map) -> Option<&'a Hierarchy> {
+        self.hierarchy.as_ref().or(map.hierarchy.as_ref())
+    }
+
+    /// Connects start and goal to the transitions of their clusters.
+    fn connect(&mut self, map: &Map, start: Position, goal: Position) {
+        let Some(hierarchy) = self.hierarchy(map) else {
+            return;
+        };
+        let start_cluster = hierarchy.cluster_of(start.x(), start.y());
+        let goal_cluster = hierarchy.cluster_of(goal.x(), goal.y());
+
+        let reachable = dijkstra_all(&start, |position| {
+            hierarchy.local_successors(map, start_cluster, *position)
+        });
+        let mut start_edges: Vec<(Position, i32)> = hierarchy.edges[start_cluster]
+            .keys()
+            .filter_map(|node| reachable.get(node).map(|(_, cost)| (*node, *cost)))
+            .collect();
+        if start_cluster == goal_cluster {
+            if let Some((_, cost)) = reachable.get(&goal) {
+                start_edges.push((goal, *cost));
+            }
+        }
+
+        let goal_edges: HashMap<Position, i32> = hierarchy.edges[goal_cluster]
+            .keys()
+            .filter_map(|node| {
+                hierarchy
+                    .local_path(map, goal_cluster, *node, goal)
+                    .map(|(_, cost)| (*node, cost))
+            })
+            .collect();
+
+        self.start_edges = start_edges;
+        self.goal_edges = goal_edges;
+    }
+}
+
+impl Expander for HpaExpander {
+    fn successors(
+        &self,
+        map: &Map,
+        node: Position,
+        _parent: Option<Position>,
+        goal: Position,
+        generated: &mut Vec<Position>,
+    ) -> Vec<(Position, i32)> {
+        let Some(hierarchy) = self.hierarchy(map) else {
+            return vec![];
+        };
+        let mut successors = hierarchy.abstract_successors(map, node);
+        if node == self.start {
+            successors.extend(self.start_edges.iter().copied());
+        }
+        if let Some(cost) = self.goal_edges.get(&node) {
+            successors.push((goal, *cost));
+        }
+
+        for successor in &successors {
+            generated.push(successor.0);
+        }
+        successors
+    }
+
+    /// Replaces every abstract edge inside a cluster by the tiles of its local path.
+    fn refine(&self, map: &Map, nodes: Vec<Position>) -> Vec<Position> {
+        let Some(hierarchy) = self.hierarchy(map) else {
+            return nodes;
+        };
+        let mut path = nodes.iter().take(1).copied().collect::<Vec<_>>();
+        for pair in nodes.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            let cluster = hierarchy.cluster_of(from.x(), from.y());
+            if cluster == hierarchy.cluster_of(to.x(), to.y()) {
+                if let Some((tiles, _)) = hierarchy.local_path(map, cluster, from, to) {
+                    path.extend(tiles.into_iter().skip(1));
+                }
+            } else {
+                path.push(to);
+            }
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        random_pairs, solve, split_mix,
+        test_support::{assert_walkable_path, random_map},
+        CornerCutting, Hierarchy, Map, PathfindingAlgorithm, Position,
+    };
+
+    /// A map with the same tiles and corner cutting as `map`, and a hierarchy built from scratch.
+    fn rebuilt(map: &Map, cluster_size: i32) -> Map {
+        let mut fresh = Map::new(map.width, map.height, map.allow_diagonals);
+        fresh.blocked = map.blocked.clone();
+        fresh.costs = map.costs.clone();
+        fresh.corner_cutting = map.corner_cutting;
+        fresh.build_hierarchy(cluster_size);
+        fresh
+    }
+
+    fn assert_valid_path(map: &Map, start: Position, goal: Position) {
+        let hpa = solve(map, PathfindingAlgorithm::Hpa, start, goal);
+        let dijkstra = solve(map, PathfindingAlgorithm::Dijkstra, start, goal);
+        let Some(path) = hpa.path else {
+            return;
+        };
+        assert_walkable_path(map, &path, start, goal);
+        assert!(hpa.cost >= dijkstra.cost, "{start:?} to {goal:?}");
+    }
+
+    #[test]
+    fn crosses_clusters_through_their_entrances() {
+        // two clusters side by side, the wall between them open on a single row
+        let mut map = Map::new(8, 4, true);
+        for y in 0..4 {
+            map.set_blocked(3, y, y != 2);
+        }
+        let hierarchy = Hierarchy::new(&map, 4);
+        assert_eq!(
+            hierarchy.borders[&(0, 1)],
+            vec![(Position(3, 2), Position(4, 2))]
+        );
+        assert_eq!(hierarchy.node_count(), 2);
+
+        map.build_hierarchy(4);
+        let (start, goal) = (Position(0, 0), Position(7, 0));
+        let hpa = solve(&map, PathfindingAlgorithm::Hpa, start, goal);
+        let path = hpa.path.unwrap();
+        assert!(path
+            .windows(2)
+            .any(|step| step == [Position(3, 2), Position(4, 2)]));
+        assert_eq!(
+            hpa.cost,
+            solve(&map, PathfindingAlgorithm::Dijkstra, start, goal).cost
+        );
+
+        // a wide entrance gets a transition at each end
+        let hierarchy = Hierarchy::new(&Map::new(16, 8, true), 8);
+        assert_eq!(
+            hierarchy.borders[&(0, 1)],
+            vec![
+                (Position(7, 0), Position(8, 0)),
+                (Position(7, 7), Position(8, 7))
+            ]
+        );
+    }
+
+    #[test]
+    fn updates_match_a_fresh_hierarchy() {
+        for seed in 0..6 {
+            let mut map = random_map(30, 25, seed);
+            map.corner_cutting = CornerCutting::ALL[seed as usize % CornerCutting::ALL.len()];
+            map.build_hierarchy(7);
+            let mut state = seed;
+            for edit in 0..10 {
+                let value = split_mix(&mut state);
+                let (x, y) = ((value % 30) as i32, ((value >> 32) % 25) as i32);
+                if edit % 3 == 0 {
+                    map.set_cost(x, y, Some(1 + (value >> 48) as i32 % 4));
+                } else {
+                    map.set_blocked(x, y, !map.is_blocked(x, y));
+                }
+                let fresh = rebuilt(&map, 7);
+                for (start, goal) in random_pairs(&fresh, 3, value) {
+                    let updated = solve(&map, PathfindingAlgorithm::Hpa, start, goal);
+                    let rebuilt = solve(&fresh, PathfindingAlgorithm::Hpa, start, goal);
+                    assert_eq!(updated.cost, rebuilt.cost, "seed {seed}, edit {edit}");
+                    assert_valid_path(&map, start, goal);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn finds_every_connected_pair() {
+        for seed in 0..10 {
+            let mut map = random_map(33, 21, seed);
+            map.build_hierarchy(8);
+            for (start, goal) in random_pairs(&map, 10, seed) {
+                assert!(solve(&map, PathfindingAlgorithm::Hpa, start, goal)
+                    .path
+                    .is_some());
+                assert_valid_path(&map, start, goal);
+            }
+        }
+    }
+}
+
+// References
+// 1. Near Optimal Hierarchical Path-Finding, Botea, Müller, Schaeffer
+// https://webdocs.cs.ualberta.ca/~mmueller/ps/hpastar.pdf
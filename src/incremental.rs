@@ -0,0 +1,554 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    ExpandedNode, Map, Pathfinder, Position, SearchResult, SearchSnapshot, SearchStatus,
+    SearchSteps,
+};
+
+const INFINITY: i32 = i32::MAX;
+
+/// An open node with its key and the order it was pushed in.
+type OpenEntry = Reverse<((i32, i32), usize, Position)>;
+
+/// Adds two costs that may be `INFINITY`.
+fn add(cost: i32, step: i32) -> i32 {
+    if cost == INFINITY {
+        INFINITY
+    } else {
+        cost + step
+    }
+}
+
+pub struct LpaStar;
+
+impl Pathfinder for LpaStar {
+    fn name(&self) -> &'static str {
+        "LPA*"
+    }
+
+    fn steps(&self, map: &Map, start: Position, goal: Position) -> Box<dyn SearchSteps> {
+        Box::new(IncrementalPlanner::lpa_star(map, start, goal))
+    }
+
+    fn incremental(
+        &self,
+        map: &Map,
+        start: Position,
+        goal: Position,
+    ) -> Option<IncrementalPlanner> {
+        Some(IncrementalPlanner::lpa_star(map, start, goal))
+    }
+}
+
+pub struct DStarLite;
+
+impl Pathfinder for DStarLite {
+    fn name(&self) -> &'static str {
+        "D* Lite"
+    }
+
+    fn steps(&self, map: &Map, start: Position, goal: Position) -> Box<dyn SearchSteps> {
+        Box::new(IncrementalPlanner::d_star_lite(map, start, goal))
+    }
+
+    fn incremental(
+        &self,
+        map: &Map,
+        start: Position,
+        goal: Position,
+    ) -> Option<IncrementalPlanner> {
+        Some(IncrementalPlanner::d_star_lite(map, start, goal))
+    }
+}
+
+/// A search that keeps what it knows between calls to `plan`, so after tiles change only the part
+/// of the search they affect is done again.
+///
+/// Every node has `g`, its cost as last expanded, and `rhs`, the best cost its neighbours offer
+/// now. Nodes where the two differ are open, and `plan` expands them until the path is known to
+/// be the cheapest again. LPA* searches from the start towards the goal. D* Lite searches from the
+/// goal towards the start, so the start can also move along the path (`move_start`) without
+/// throwing the search away.
+pub struct IncrementalPlanner {
+    /// D* Lite, searching from the goal.
+    backward: bool,
+    start: Position,
+    goal: Position,
+    width: i32,
+    g: Vec<i32>,
+    rhs: Vec<i32>,
+    open: BinaryHeap<OpenEntry>,
+    /// Key of every open node, entries in `open` with another key are stale.
+    keys: Vec<Option<(i32, i32)>>,
+    opened: usize,
+    /// D* Lite's key modifier, how far the start moved in all.
+    key_offset: i32,
+    /// Nodes expanded since `plan` was last called, or since the planner was made.
+    expanded: Vec<ExpandedNode>,
+    generated: Vec<Position>,
+    current: Option<ExpandedNode>,
+    path: Option<Vec<Position>>,
+    status: SearchStatus,
+    elapsed: Duration,
+}
+
+impl IncrementalPlanner {
+    #[must_use]
+    pub fn lpa_star(map: &Map, start: Position, goal: Position) -> IncrementalPlanner {
+        IncrementalPlanner::new(map, start, goal, false)
+    }
+
+    #[must_use]
+    pub fn d_star_lite(map: &Map, start: Position, goal: Position) -> IncrementalPlanner {
+        IncrementalPlanner::new(map, start, goal, true)
+    }
+
+    fn new(map: &Map, start: Position, goal: Position, backward: bool) -> IncrementalPlanner {
+        let tiles = (map.width * map.height) as usize;
+        let mut planner = IncrementalPlanner {
+            backward,
+            start,
+            goal,
+            width: map.width,
+            g: vec![INFINITY; tiles],
+            rhs: vec![INFINITY; tiles],
+            open: BinaryHeap::new(),
+            keys: vec![None; tiles],
+            opened: 0,
+            key_offset: 0,
+            expanded: Vec::new(),
+            generated: Vec::new(),
+            current: None,
+            path: None,
+            status: SearchStatus::Searching,
+            elapsed: Duration::ZERO,
+        };
+        let source = planner.source();
+        if !map.is_blocked(source.x(), source.y()) {
+            let index = planner.index(source);
+            planner.rhs[index] = 0;
+            planner.push(map, source);
+        }
+        let (start, goal) = (planner.start, planner.goal);
+        if map.is_blocked(start.x(), start.y()) || map.is_blocked(goal.x(), goal.y()) {
+            planner.status = SearchStatus::NotFound;
+        }
+        planner
+    }
+
+    #[must_use]
+    pub fn start(&self) -> Position {
+        self.start
+    }
+
+    #[must_use]
+    pub fn goal(&self) -> Position {
+        self.goal
+    }
+
+    /// Whether this is D* Lite, which can move its start.
+    #[must_use]
+    pub fn is_d_star_lite(&self) -> bool {
+        self.backward
+    }
+
+    fn index(&self, node: Position) -> usize {
+        (node.y() * self.width + node.x()) as usize
+    }
+
+    /// Where the search grows from.
+    fn source(&self) -> Position {
+        if self.backward {
+            self.goal
+        } else {
+            self.start
+        }
+    }
+
+    /// Where the search has to reach.
+    fn target(&self) -> Position {
+        if self.backward {
+            self.start
+        } else {
+            self.goal
+        }
+    }
+
+    /// Searching again after a change, unless the start or goal is off the map, where there is
+    /// nothing to index and no path.
+    fn restart(&mut self, map: &Map) {
+        if map.inside(self.start.x(), self.start.y()) && map.inside(self.goal.x(), self.goal.y()) {
+            self.status = SearchStatus::Searching;
+        } else {
+            self.path = None;
+            self.status = SearchStatus::NotFound;
+        }
+    }
+
+    fn heuristic(&self, map: &Map, node: Position) -> i32 {
+        map.heuristic(&node, &self.target())
+    }
+
+    fn key(&self, map: &Map, node: Position) -> (i32, i32) {
+        let index = self.index(node);
+        let cost = self.g[index].min(self.rhs[index]);
+        (add(cost, self.heuristic(map, node) + self.key_offset), cost)
+    }
+
+    fn push(&mut self, map: &Map, node: Position) {
+        let key = self.key(map, node);
+        let index = self.index(node);
+        self.keys[index] = Some(key);
+        self.open.push(Reverse((key, self.opened, node)));
+        self.opened += 1;
+    }
+
+    /// Drops stale entries, so the top of `open` is the open node with the lowest key.
+    fn skip_stale(&mut self) {
+        while let Some(Reverse((key, _, node))) = self.open.peek() {
+            if self.keys[self.index(*node)] == Some(*key) {
+                break;
+            }
+            self.open.pop();
+        }
+    }
+
+    /// Cost of the move between neighbours `near` and `far`, where `near` is the one closer to
+    /// the source. Moves are made from start to goal whichever way the search runs.
+    fn edge_cost(&self, map: &Map, near: Position, far: Position) -> i32 {
+        if self.backward {
+            map.step_cost(&far, &near)
+        } else {
+            map.step_cost(&near, &far)
+        }
+    }
+
+    /// The neighbour `node` is cheapest to reach from, and that cost.
+    fn best_neighbour(&self, map: &Map, node: Position) -> Option<(i32, Position)> {
+        if map.is_blocked(node.x(), node.y()) {
+            return None;
+        }
+        map.get_successors(&node)
+            .into_iter()
+            .map(|successor| successor.position)
+            .map(|neighbour| {
+                let cost = add(
+                    self.g[self.index(neighbour)],
+                    self.edge_cost(map, neighbour, node),
+                );
+                (cost, neighbour)
+            })
+            .filter(|(cost, _)| *cost != INFINITY)
+            .min()
+    }
+
+    /// Works out `rhs` of `node` again and opens or closes it to match.
+    fn update_vertex(&mut self, map: &Map, node: Position) {
+        let index = self.index(node);
+        if node == self.source() {
+            // a source that was blocked and opened again starts the search anew
+            self.rhs[index] = if map.is_blocked(node.x(), node.y()) {
+                INFINITY
+            } else {
+                0
+            };
+        } else {
+            self.rhs[index] = self
+                .best_neighbour(map, node)
+                .map_or(INFINITY, |(cost, _)| cost);
+        }
+        self.keys[index] = None;
+        if self.g[index] != self.rhs[index] {
+            self.push(map, node);
+        }
+    }
+
+    /// Whether the target's cost is final: it is consistent and no open node can lower it.
+    fn finished(&mut self, map: &Map) -> bool {
+        self.skip_stale();
+        let target = self.target();
+        let index = self.index(target);
+        let target_key = self.key(map, target);
+        match self.open.peek() {
+            Some(Reverse((key, _, _))) => *key >= target_key && self.rhs[index] == self.g[index],
+            None => true,
+        }
+    }
+
+    /// Tells the planner that `tiles` were blocked, opened or given another cost, so the nodes
+    /// around them are looked at again by the next `plan`.
+    pub fn update_tiles(&mut self, map: &Map, tiles: &[Position]) {
+        let mut nodes = HashSet::new();
+        for tile in tiles {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let (x, y) = (tile.x() + dx, tile.y() + dy);
+                    if map.inside(x, y) {
+                        nodes.insert(Position(x, y));
+                    }
+                }
+            }
+        }
+        for node in nodes {
+            self.update_vertex(map, node);
+        }
+        self.restart(map);
+    }
+
+    /// D* Lite: moves the start, keeping everything searched so far. Returns `false` for LPA*,
+    /// whose search grows from the start and has to be made again.
+    pub fn move_start(&mut self, map: &Map, start: Position) -> bool {
+        if !self.backward {
+            return false;
+        }
+        self.key_offset += map.heuristic(&self.start, &start);
+        self.start = start;
+        self.restart(map);
+        true
+    }
+
+    /// Expands open nodes until the cheapest path is known again, and returns it with the nodes
+    /// this call expanded.
+    pub fn plan(&mut self, map: &Map) -> SearchResult {
+        self.expanded = Vec::new();
+        self.generated = Vec::new();
+        self.elapsed = Duration::ZERO;
+        while !self.step(map).is_done() {}
+        self.result(map)
+    }
+
+    /// Makes `node` consistent: an overconsistent node takes its new, lower cost, an
+    /// underconsistent one is reset so its cost is worked out again. Either way its neighbours
+    /// are updated to match.
+    fn expand(&mut self, map: &Map, node: Position, key: (i32, i32)) {
+        let index = self.index(node);
+        self.keys[index] = None;
+        let overconsistent = self.g[index] > self.rhs[index];
+        self.g[index] = if overconsistent {
+            self.rhs[index]
+        } else {
+            INFINITY
+        };
+        let expanded = ExpandedNode {
+            position: node,
+            g: key.1,
+            h: self.heuristic(map, node),
+            parent: self.best_neighbour(map, node).map(|(_, parent)| parent),
+            backward: self.backward,
+        };
+        self.expanded.push(expanded);
+        self.current = Some(expanded);
+
+        if !overconsistent {
+            self.update_vertex(map, node);
+        }
+        for successor in map.get_successors(&node) {
+            self.generated.push(successor.position);
+            self.update_vertex(map, successor.position);
+        }
+    }
+
+    fn build_path(&self, map: &Map) -> Option<Vec<Position>> {
+        let target = self.target();
+        if self.g[self.index(target)] == INFINITY {
+            return None;
+        }
+        // from the target down the costs to the source, every node has a cheaper neighbour
+        let mut path = vec![target];
+        let mut node = target;
+        let mut seen = HashSet::new();
+        while node != self.source() {
+            if !seen.insert(node) {
+                return None;
+            }
+            let (_, next) = self.best_neighbour(map, node)?;
+            path.push(next);
+            node = next;
+        }
+        if !self.backward {
+            path.reverse();
+        }
+        Some(path)
+    }
+
+    fn result(&self, map: &Map) -> SearchResult {
+        let cost = self.path.as_ref().map(|path| map.path_cost(path));
+        SearchResult {
+            path: self.path.clone(),
+            cost,
+            expanded: self.expanded.iter().map(|node| node.position).collect(),
+            expanded_nodes: self.expanded.clone(),
+            generated: self.generated.clone(),
+            elapsed: self.elapsed,
+            ..Default::default()
+        }
+    }
+}
+
+impl SearchSteps for IncrementalPlanner {
+    fn step(&mut self, map: &Map) -> SearchStatus {
+        if self.status.is_done() {
+            return self.status;
+        }
+        let now = Instant::now();
+        self.current = None;
+
+        while !self.finished(map) {
+            // `finished` left the open node with the lowest key on top
+            let Some(Reverse((key, _, node))) = self.open.pop() else {
+                break;
+            };
+            if key < self.key(map, node) {
+                // the start moved since it was pushed
+                self.push(map, node);
+                continue;
+            }
+            self.expand(map, node, key);
+            break;
+        }
+        if self.finished(map) {
+            self.path = self.build_path(map);
+            self.status = if self.path.is_some() {
+                SearchStatus::Found
+            } else {
+                SearchStatus::NotFound
+            };
+        }
+        self.elapsed += now.elapsed();
+        self.status
+    }
+
+    fn status(&self) -> SearchStatus {
+        self.status
+    }
+
+    fn current(&self) -> Option<ExpandedNode> {
+        self.current
+    }
+
+    fn open(&self) -> Vec<Position> {
+        if self.backward {
+            return Vec::new();
+        }
+        self.open_nodes()
+    }
+
+    fn backward_open(&self) -> Vec<Position> {
+        if self.backward {
+            self.open_nodes()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn snapshot(&self) -> SearchSnapshot {
+        SearchSnapshot {
+            current: self.current.map(|node| node.position),
+            open: self.open(),
+            backward_open: self.backward_open(),
+            closed: self.expanded.iter().map(|node| node.position).collect(),
+            expanded: self.expanded.clone(),
+            path: self.path.clone(),
+        }
+    }
+
+    fn into_result(self: Box<Self>, map: &Map) -> SearchResult {
+        self.result(map)
+    }
+}
+
+impl IncrementalPlanner {
+    fn open_nodes(&self) -> Vec<Position> {
+        let mut seen = HashSet::new();
+        self.open
+            .iter()
+            .filter(|Reverse((key, _, node))| self.keys[self.index(*node)] == Some(*key))
+            .map(|Reverse((_, _, node))| *node)
+            .filter(|node| seen.insert(*node))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{random_pairs, solve, PathfindingAlgorithm};
+
+    fn dijkstra_cost(map: &Map, start: Position, goal: Position) -> Option<i32> {
+        solve(map, PathfindingAlgorithm::Dijkstra, start, goal).cost
+    }
+
+    #[test]
+    fn endpoints_off_the_map_are_not_found() {
+        let map = Map::new(10, 10, true);
+        for (start, goal) in [
+            (Position(3, -2), Position(5, 5)),
+            (Position(5, 5), Position(10, 3)),
+        ] {
+            for algorithm in [
+                PathfindingAlgorithm::LpaStar,
+                PathfindingAlgorithm::DStarLite,
+            ] {
+                assert_eq!(solve(&map, algorithm, start, goal).path, None);
+            }
+            let mut planner = IncrementalPlanner::d_star_lite(&map, start, goal);
+            planner.update_tiles(&map, &[Position(4, 4)]);
+            assert_eq!(planner.plan(&map).path, None);
+        }
+
+        let mut planner = IncrementalPlanner::d_star_lite(&map, Position(1, 1), Position(5, 5));
+        assert!(planner.plan(&map).path.is_some());
+        assert!(planner.move_start(&map, Position(-1, 1)));
+        assert_eq!(planner.plan(&map).path, None);
+    }
+
+    #[test]
+    fn repairs_match_a_fresh_search() {
+        let costly_map = || {
+            let mut map = Map::from_noise(48, 48, true);
+            for index in 0..map.costs.len() {
+                map.costs[index] = Some(1 + (index * 7 % 4) as i32);
+            }
+            map
+        };
+        for (start, goal) in random_pairs(&costly_map(), 4, 1) {
+            for backward in [false, true] {
+                let mut map = costly_map();
+                let mut planner = IncrementalPlanner::new(&map, start, goal, backward);
+                assert_eq!(planner.plan(&map).cost, dijkstra_cost(&map, start, goal));
+
+                // block tiles along the path, then open them again with another cost
+                let path = planner.plan(&map).path.unwrap_or_default();
+                let tiles: Vec<Position> = path
+                    .iter()
+                    .copied()
+                    .filter(|tile| *tile != start && *tile != goal)
+                    .step_by(5)
+                    .collect();
+                for tile in &tiles {
+                    map.set_blocked(tile.x(), tile.y(), true);
+                }
+                planner.update_tiles(&map, &tiles);
+                assert_eq!(planner.plan(&map).cost, dijkstra_cost(&map, start, goal));
+
+                for tile in &tiles {
+                    map.set_blocked(tile.x(), tile.y(), false);
+                    map.set_cost(tile.x(), tile.y(), Some(3));
+                }
+                planner.update_tiles(&map, &tiles);
+                let repaired = planner.plan(&map);
+                let fresh = IncrementalPlanner::new(&map, start, goal, backward).plan(&map);
+                assert_eq!(repaired.cost, fresh.cost);
+                assert_eq!(repaired.cost, dijkstra_cost(&map, start, goal));
+            }
+        }
+    }
+}
+
+// References
+// 1. Koenig, Likhachev and Furcy, "Lifelong Planning A*", Artificial Intelligence 155 (2004)
+// 2. Koenig and Likhachev, "D* Lite", AAAI (2002)
@@ -1,203 +1,58 @@
-use bevy::utils::HashMap;
-use std::{
-    cmp::Ordering,
-    collections::{BTreeSet, HashSet},
-    f32::consts::SQRT_2,
-    rc::Rc,
-};
-
-use crate::{Map, Position};
-
-struct Heuristic;
-
-impl Heuristic {
-    pub fn octile(dx: f32, dy: f32) -> f32 {
-        let squart2 = SQRT_2 - 1.0;
-        if dx < dy {
-            squart2 * dx + dy
-        } else {
-            squart2 * dy + dx
-        }
-    }
-
-    pub fn manhattan(dx: f32, dy: f32) -> f32 {
-        dx + dy
-    }
-}
-
+use crate::{BestFirstSearch, CornerCutting, Expander, Map, Pathfinder, Position, SearchSteps};
+
+/// Jump Point Search that honours `Map::costs`.
+///
+/// Classic JPS pruning is only valid where every step costs the same, so a tile that touches a
+/// tile of a different cost is treated as a jump point and expanded without pruning. Inside
+/// uniform-cost regions this behaves like plain JPS, and the returned paths match Dijkstra.
+///
+/// With 4-way movement (`Map::diagonals` is false) horizontal moves play the part diagonal moves
+/// play on an 8-way grid: a canonical path does its horizontal steps before its vertical ones, so
+/// a horizontal jump branches up and down at every tile and a vertical jump only turns at forced
+/// neighbors.
 pub struct Jps;
 
-#[derive(Debug)]
-struct PathNode {
-    node: Position,
-    parent: Option<Rc<PathNode>>,
-    // distance to start + estimate to end
-    f: f32,
-    // distance to start (parent's g + distance from parent)
-    g: f32,
-    // estimate to end
-    h: f32,
-}
+/// Expands a node into the jump points reached from it.
+pub struct JpsExpander;
 
-impl PathNode {
-    fn new(node: Position) -> Self {
-        Self {
-            node,
-            parent: None,
-            f: 0.0,
-            g: 0.0,
-            h: 0.0,
-        }
-    }
-
-    fn get_x(&self) -> i32 {
-        self.node.0
-    }
-
-    fn get_y(&self) -> i32 {
-        self.node.1
-    }
-
-    fn set_parent(&mut self, parent: Rc<PathNode>) {
-        self.parent = Some(parent);
+impl Expander for JpsExpander {
+    fn successors(
+        &self,
+        map: &Map,
+        node: Position,
+        parent: Option<Position>,
+        goal: Position,
+        generated: &mut Vec<Position>,
+    ) -> Vec<(Position, i32)> {
+        Jps::find_neighbors(map, node, parent)
+            .into_iter()
+            .filter_map(|neighbor| Jps::jump(map, neighbor, node, goal, generated))
+            .map(|jump_point| (jump_point, Jps::segment_cost(map, node, jump_point)))
+            .collect()
     }
-}
 
-impl PartialEq for PathNode {
-    fn eq(&self, other: &Self) -> bool {
-        self.f == other.f
+    fn refine(&self, _map: &Map, nodes: Vec<Position>) -> Vec<Position> {
+        Jps::fill_segments(&nodes)
     }
 }
 
-impl Eq for PathNode {}
-
-impl PartialOrd for PathNode {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl Pathfinder for Jps {
+    fn name(&self) -> &'static str {
+        "Jps"
     }
-}
 
-impl Ord for PathNode {
-    fn cmp(&self, other: &Self) -> Ordering {
-        let ord = self.f.total_cmp(&other.f);
-        if ord == Ordering::Equal {
-            self.node.cmp(&other.node)
-        } else {
-            ord
-        }
+    fn steps(&self, map: &Map, start: Position, goal: Position) -> Box<dyn SearchSteps> {
+        Box::new(BestFirstSearch::new(map, JpsExpander, start, goal))
     }
 }
 
 impl Jps {
-    pub fn find_path(
-        graph: &Map,
-        start: Position,
-        end: Position,
-    ) -> (Option<Vec<Position>>, Vec<Position>, Vec<Position>) {
-        if graph.is_blocked(end.x(), end.y()) {
-            return (None, vec![], vec![]);
-        }
-
-        let mut open: BTreeSet<Rc<PathNode>> = BTreeSet::new();
-
-        open.insert(Rc::new(PathNode::new(start)));
-
-        let mut closed: HashSet<Position> = HashSet::new();
-        let mut node_path: HashMap<Position, Rc<PathNode>> = HashMap::new();
-        let mut tested = HashSet::new();
-        while let Some(path_node) = open.pop_first() {
-            if path_node.node == end {
-                //TODO backtrace
-                return (
-                    Some(back_trace(path_node)),
-                    Vec::from_iter(tested),
-                    Vec::from_iter(node_path.into_keys()),
-                );
-            }
-            closed.insert(path_node.node.clone());
-
-            Jps::identify_successors(
-                graph,
-                path_node.clone(),
-                end,
-                &mut closed,
-                &mut open,
-                &mut node_path,
-                &mut tested,
-            );
-        }
-
-        (
-            None,
-            Vec::from_iter(tested),
-            Vec::from_iter(node_path.into_keys()),
-        )
-    }
-
-    fn identify_successors(
-        graph: &Map,
-        node: Rc<PathNode>,
-        goal: Position,
-        closed: &mut HashSet<Position>,
-        open: &mut BTreeSet<Rc<PathNode>>,
-        node_path: &mut HashMap<Position, Rc<PathNode>>,
-        tested: &mut HashSet<Position>,
-    ) {
-        let (x, y) = (node.get_x(), node.get_y());
-        let (end_x, end_y) = (goal.x(), goal.y());
-        let neighbors = Jps::find_neighbors(graph, node.clone());
-        for neighbor in neighbors {
-            let jump_point = Jps::jump(graph, neighbor, node.node, goal, tested);
-            if let Some(jump_point) = jump_point {
-                if closed.contains(&jump_point) {
-                    continue;
-                }
-
-                let (jx, jy) = (jump_point.x(), jump_point.y());
-
-                if jx == 14 && jy == 9 {
-                    println!("haha");
-                }
-                let d = {
-                    let dx = (jx - x).abs() as f32;
-                    let dy = (jy - y).abs() as f32;
-                    Heuristic::octile(dx, dy)
-                };
-                let ng = node.g + d; //next 'g' value
-
-                let (none, prev_g) = node_path
-                    .get(&jump_point)
-                    .map_or((true, 0.0), |pn| (false, pn.g));
-                if none || ng < prev_g {
-                    let mut jump_node = PathNode::new(jump_point.clone());
-                    jump_node.set_parent(node.clone());
-                    jump_node.g = ng;
-                    jump_node.h =
-                        Heuristic::manhattan((jx - end_x).abs() as f32, (jy - end_y).abs() as f32);
-                    jump_node.f = jump_node.g + jump_node.h;
-
-                    let jump_node = Rc::new(jump_node);
-                    //update the old one
-                    if let Some(prev_path_node) = node_path.remove(&jump_point) {
-                        node_path.insert(jump_point.clone(), jump_node.clone());
-                        open.remove(&prev_path_node);
-                        open.insert(jump_node);
-                    } else {
-                        //insert the new one
-                        open.insert(jump_node.clone());
-                        node_path.insert(jump_point.clone(), jump_node);
-                    }
-                }
-            }
-        }
-    }
-
     fn jump(
         graph: &Map,
         current: Position,
         parent: Position,
         goal: Position,
-        tested: &mut HashSet<Position>,
+        tested: &mut Vec<Position>,
     ) -> Option<Position> {
         if graph.is_blocked(current.x(), current.y()) {
             return None;
@@ -207,69 +62,131 @@ impl Jps {
             return Some(current);
         }
 
-        tested.insert(current);
+        tested.push(current);
 
         let (x, y) = (current.x(), current.y());
+
+        // the terrain changes around here, let the search decide how to continue
+        if !Jps::can_prune(graph, x, y) {
+            return Some(current);
+        }
+
         let (dx, dy) = (current.x() - parent.x(), current.y() - parent.y());
 
         // check for forced neighbors
-        // along the diagonal
-        if dx != 0 && dy != 0 {
-            if (graph.is_path(x - dx, y + dy) && graph.is_blocked(x - dx, y))
-                || (graph.is_path(x + dx, y - dy) && graph.is_blocked(x, y - dy))
-            {
-                return Some(current);
-            }
+        if Jps::has_forced_neighbor(graph, x, y, dx, dy) {
+            return Some(current);
+        }
 
-            for next in vec![
-                graph.walkable_position(x + dx, y),
-                graph.walkable_position(x, y + dy),
-            ]
-            .into_iter()
-            .flatten()
-            {
+        // along the diagonal (or horizontally on a 4-way grid)
+        for (bx, by) in Jps::branches(graph, dx, dy) {
+            if let Some(next) = graph.walkable_position(x + bx, y + by) {
                 if Jps::jump(graph, next, current, goal, tested).is_some() {
                     return Some(current);
                 }
             }
+        }
+
+        if graph.can_move(x, y, dx, dy) {
+            Jps::jump(graph, Position(x + dx, y + dy), current, goal, tested)
         } else {
-            // check horizontally/vertically
+            None
+        }
+    }
 
-            if dx != 0 {
-                if graph.is_path(x + dx, y + 1) && graph.is_blocked(x, y + 1)
-                    || graph.is_path(x + dx, y - 1) && graph.is_blocked(x, y - 1)
-                {
-                    return Some(current);
-                }
+    /// Whether `(x, y)`, reached by moving in direction `(dx, dy)`, has a forced neighbor.
+    pub(crate) fn has_forced_neighbor(graph: &Map, x: i32, y: i32, dx: i32, dy: i32) -> bool {
+        Jps::forced_directions(graph, x, y, dx, dy)
+            .into_iter()
+            .any(|(fx, fy)| graph.can_move(x, y, fx, fy))
+    }
+
+    /// Directions from `(x, y)` a path arriving in direction `(dx, dy)` may continue in without
+    /// a detour, whatever the tiles around look like.
+    fn natural_directions(graph: &Map, dx: i32, dy: i32) -> Vec<(i32, i32)> {
+        if !graph.diagonals() {
+            if dy == 0 {
+                vec![(dx, 0), (0, 1), (0, -1)]
             } else {
-                if graph.is_path(x + 1, y + dy) && graph.is_blocked(x + 1, y)
-                    || graph.is_path(x - 1, y + dy) && graph.is_blocked(x - 1, y)
-                {
-                    return graph.walkable_position(x, y);
+                vec![(0, dy)]
+            }
+        } else if dx != 0 && dy != 0 {
+            vec![(0, dy), (dx, 0), (dx, dy)]
+        } else {
+            vec![(dx, dy)]
+        }
+    }
+
+    /// Directions from `(x, y)` that only a path arriving in direction `(dx, dy)` can take
+    /// optimally, because a blocked tile cuts off the way around `(x, y)`. Whether the step is
+    /// possible at all is left to `Map::can_move`.
+    fn forced_directions(graph: &Map, x: i32, y: i32, dx: i32, dy: i32) -> Vec<(i32, i32)> {
+        let mut directions = vec![];
+        if !graph.diagonals() {
+            // vertically on a 4-way grid, a horizontal move could not have been made earlier
+            if dy != 0 {
+                for side in [1, -1] {
+                    if graph.is_blocked(x + side, y - dy) {
+                        directions.push((side, 0));
+                    }
+                }
+            }
+        } else if dx != 0 && dy != 0 {
+            // along the diagonal
+            if graph.is_blocked(x - dx, y) {
+                directions.push((-dx, dy));
+            }
+            if graph.is_blocked(x, y - dy) {
+                directions.push((dx, -dy));
+            }
+        } else {
+            // horizontally or vertically, `(px, py)` points to either side
+            for (px, py) in [(dy, dx), (-dy, -dx)] {
+                if graph.corner_cutting == CornerCutting::BothSidesOpen {
+                    // the diagonal that would have skipped (x, y) needs the tile behind the side
+                    if graph.is_path(x + px, y + py) && graph.is_blocked(x - dx + px, y - dy + py) {
+                        directions.push((px, py));
+                        directions.push((dx + px, dy + py));
+                    }
+                } else if graph.is_blocked(x + px, y + py) {
+                    directions.push((dx + px, dy + py));
                 }
             }
         }
+        directions
+    }
 
-        // moving diagonally, must make sure one of the vertical/hhorizontal
-        // neighbors is open to allow the path
-        if graph.is_path(x + dx, y) || graph.is_path(x, y + dy) {
-            if let Some(next) = graph.walkable_position(x + dx, y + dy) {
-                Jps::jump(graph, next, current, goal, tested)
+    /// The directions a jump in direction `(dx, dy)` scans at every tile before taking its next
+    /// step: the two straight parts of a diagonal, or up and down on a 4-way grid.
+    pub(crate) fn branches(graph: &Map, dx: i32, dy: i32) -> Vec<(i32, i32)> {
+        if !graph.diagonals() {
+            if dy == 0 {
+                vec![(0, 1), (0, -1)]
             } else {
-                None
+                vec![]
             }
+        } else if dx != 0 && dy != 0 {
+            vec![(dx, 0), (0, dy)]
         } else {
-            None
+            vec![]
         }
     }
 
-    /// https://zerowidth.com/2013/a-visual-explanation-of-jump-point-search.html
-    fn find_neighbors(graph: &Map, node: Rc<PathNode>) -> Vec<Position> {
-        if let Some(parent) = node.parent.as_ref() {
-            let (x, y) = (node.get_x(), node.get_y());
+    /// <https://zerowidth.com/2013/a-visual-explanation-of-jump-point-search.html>
+    pub(crate) fn find_neighbors(
+        graph: &Map,
+        node: Position,
+        parent: Option<Position>,
+    ) -> Vec<Position> {
+        if !Jps::can_prune(graph, node.x(), node.y()) {
+            return Jps::get_all_neightbors(graph, node);
+        }
+
+        if let Some(parent) = parent {
+            let (x, y) = (node.x(), node.y());
 
             let (dx, dy) = {
-                let (px, py) = (parent.get_x(), parent.get_y());
+                let (px, py) = (parent.x(), parent.y());
 
                 (
                     (x - px) / 1.max((x - px).abs()),
@@ -277,177 +194,161 @@ impl Jps {
                 )
             };
 
-            if dx != 0 && dy != 0 {
-                let mut vec = vec![];
-                let horizonetal = graph.walkable_position(x, y + dy);
-                let vertically = graph.walkable_position(x + dx, y);
-
-                // moving horizonetally and vertically first
-                if let Some(node) = horizonetal.as_ref() {
-                    vec.push(node.clone());
-                };
-                if let Some(node) = vertically.as_ref() {
-                    vec.push(node.clone());
-                };
-
-                if horizonetal.is_some() || vertically.is_some() {
-                    if let Some(node) = graph.walkable_position(x + dx, y + dy) {
-                        vec.push(node)
-                    }
-                }
-
-                if graph.is_blocked(x - dx, y) && graph.is_path(x, y + dy) {
-                    if let Some(node) = graph.walkable_position(x - dx, y + dy) {
-                        vec.push(node);
-                    }
-                }
+            Jps::natural_directions(graph, dx, dy)
+                .into_iter()
+                .chain(Jps::forced_directions(graph, x, y, dx, dy))
+                .filter(|(nx, ny)| graph.can_move(x, y, *nx, *ny))
+                .map(|(nx, ny)| Position(x + nx, y + ny))
+                .collect()
 
-                if graph.is_blocked(x, y - dy) && graph.is_path(x + dx, y) {
-                    if let Some(node) = graph.walkable_position(x + dx, y - dy) {
-                        vec.push(node);
-                    }
-                }
+            // no neighbors
+        } else {
+            Jps::get_all_neightbors(graph, node)
+        }
+    }
 
-                vec
-            } else {
-                // search horizonetally
-                if dx == 0 {
-                    let mut vec = vec![];
-                    if let Some(node) = graph.walkable_position(x, y + dy) {
-                        vec.push(node);
-
-                        //down is force neighbors
-                        if graph.is_blocked(x + 1, y) {
-                            if let Some(node) = graph.walkable_position(x + 1, y + dy) {
-                                vec.push(node);
-                            }
-                        }
-
-                        // up is force neighbors
-                        if graph.is_blocked(x - 1, y) {
-                            if let Some(node) = graph.walkable_position(x - 1, y + dy) {
-                                vec.push(node);
-                            }
-                        }
-                    }
+    /// Whether the neighbors of `(x, y)` may be pruned, which is the case unless it sits on a cost
+    /// boundary.
+    pub(crate) fn can_prune(graph: &Map, x: i32, y: i32) -> bool {
+        !Jps::on_cost_boundary(graph, x, y)
+    }
 
-                    vec
-                }
-                // search vertically
-                else if let Some(node) = graph.walkable_position(x + dx, y) {
-                    let mut vec = vec![];
-                    vec.push(node);
-
-                    // right is force neighbors
-                    if graph.is_blocked(x, y + 1) {
-                        if let Some(node) = graph.walkable_position(x + dx, y + 1) {
-                            vec.push(node);
-                        }
-                    }
+    /// Whether a walkable neighbour of `(x, y)` costs something different than `(x, y)` itself.
+    pub(crate) fn on_cost_boundary(graph: &Map, x: i32, y: i32) -> bool {
+        let cost = graph.cost(x, y);
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+            .any(|(nx, ny)| graph.is_path(nx, ny) && graph.cost(nx, ny) != cost)
+    }
 
-                    //left is force neighbors
-                    if graph.is_blocked(x, y - 1) {
-                        if let Some(node) = graph.walkable_position(x + dx, y - 1) {
-                            vec.push(node);
-                        }
-                    }
+    /// Cost of walking the straight or diagonal line from `from` to `to`.
+    pub(crate) fn segment_cost(graph: &Map, from: Position, to: Position) -> i32 {
+        let (dx, dy) = ((to.x() - from.x()).signum(), (to.y() - from.y()).signum());
+        let (mut x, mut y) = (from.x(), from.y());
+        let mut cost = 0;
+        while (x, y) != (to.x(), to.y()) {
+            cost += graph.step_cost(&Position(x, y), &Position(x + dx, y + dy));
+            x += dx;
+            y += dy;
+        }
+        cost
+    }
 
-                    vec
-                } else {
-                    vec![]
-                }
+    /// Fills in the tiles between consecutive jump points, which always lie on a straight or
+    /// diagonal line.
+    pub(crate) fn fill_segments(jump_points: &[Position]) -> Vec<Position> {
+        let mut path: Vec<Position> = jump_points.iter().take(1).copied().collect();
+        for pair in jump_points.windows(2) {
+            let (dx, dy) = (
+                (pair[1].x() - pair[0].x()).signum(),
+                (pair[1].y() - pair[0].y()).signum(),
+            );
+            let (mut x, mut y) = (pair[0].x(), pair[0].y());
+            while Position(x, y) != pair[1] {
+                x += dx;
+                y += dy;
+                path.push(Position(x, y));
             }
-
-            // no neighbors
-        } else {
-            let vec = Jps::get_all_neightbors(graph, node.node);
-            vec
         }
+        path
     }
 
     fn get_all_neightbors(graph: &Map, node: Position) -> Vec<Position> {
-        let (x, y) = (node.x(), node.y());
+        graph
+            .get_successors(&node)
+            .into_iter()
+            .map(|successor| successor.position)
+            .collect()
+    }
+}
 
-        let n = graph.walkable_position(x, y - 1);
-        let e = graph.walkable_position(x + 1, y);
-        let s = graph.walkable_position(x, y + 1);
-        let w = graph.walkable_position(x - 1, y);
+#[cfg(test)]
+mod tests {
+    use super::Jps;
+    use crate::{
+        random_pairs, solve,
+        test_support::{assert_walkable_path, random_map},
+        CostModel, Map, PathfindingAlgorithm, Position,
+    };
+
+    fn assert_matches_dijkstra(map: &Map, start: Position, goal: Position) {
+        let dijkstra = solve(map, PathfindingAlgorithm::Dijkstra, start, goal);
+        let jps = solve(map, PathfindingAlgorithm::Jps, start, goal);
+        assert_eq!(jps.cost, dijkstra.cost, "{start:?} to {goal:?}");
+        if let Some(path) = jps.path {
+            assert_walkable_path(map, &path, start, goal);
+        }
+    }
 
-        let nw = if n.is_some() || w.is_some() {
-            graph.walkable_position(x - 1, y - 1)
-        } else {
-            None
-        };
-        let ne = if n.is_some() || e.is_some() {
-            graph.walkable_position(x + 1, y - 1)
-        } else {
-            None
-        };
-        let se = if s.is_some() || e.is_some() {
-            graph.walkable_position(x + 1, y + 1)
-        } else {
-            None
-        };
-        let sw = if s.is_some() || w.is_some() {
-            graph.walkable_position(x - 1, y + 1)
-        } else {
+    #[test]
+    fn stops_at_forced_neighbors() {
+        // moving right along the middle row, the wall above (4, 1) cuts off the way to (5, 0)
+        let mut map = Map::new(8, 3, true);
+        map.set_blocked(4, 0, true);
+        let mut tested = vec![];
+        assert_eq!(
+            Jps::jump(
+                &map,
+                Position(1, 1),
+                Position(0, 1),
+                Position(7, 2),
+                &mut tested
+            ),
+            Some(Position(4, 1))
+        );
+        assert!(Jps::has_forced_neighbor(&map, 4, 1, 1, 0));
+        assert!(!Jps::has_forced_neighbor(&map, 3, 1, 1, 0));
+        assert_eq!(
+            Jps::find_neighbors(&map, Position(4, 1), Some(Position(3, 1))),
+            vec![Position(5, 1), Position(5, 0)]
+        );
+
+        // without the wall the jump runs into the map edge
+        map.set_blocked(4, 0, false);
+        assert_eq!(
+            Jps::jump(
+                &map,
+                Position(1, 1),
+                Position(0, 1),
+                Position(0, 0),
+                &mut tested
+            ),
             None
-        };
+        );
+        assert_eq!(
+            Jps::find_neighbors(&map, Position(4, 1), Some(Position(3, 1))),
+            vec![Position(5, 1)]
+        );
+    }
 
-        vec![n, e, s, w, nw, ne, se, sw]
-            .into_iter()
-            .flatten()
-            .collect()
+    #[test]
+    fn matches_dijkstra_on_cost_maps() {
+        for seed in 0..20 {
+            let mut map = random_map(24, 20, seed);
+            map.set_cost_model(CostModel::ALL[seed as usize % CostModel::ALL.len()]);
+            for (start, goal) in random_pairs(&map, 10, seed) {
+                assert_matches_dijkstra(&map, start, goal);
+            }
+        }
     }
-}
 
-// fn to_path_node(vec: Vec<Position>, parent: &Rc<PathNode>, goal: Position) -> Vec<PathNode> {
-//     let (end_x, end_y) = (goal.x(), goal.y());
-//     let mut res = vec![];
-//     for graph_node in vec {
-//         let mut path_node = PathNode::new(graph_node);
-//         let (jx, jy) = (graph_node.x(), graph_node.y());
-//         let d = {
-//             let dx = (jx - parent.get_x()).abs() as f32;
-//             let dy = (jy - parent.get_y()).abs() as f32;
-//             Heuristic::octile(dx, dy)
-//         };
-//         path_node.set_parent(parent.clone());
-//         path_node.g =  parent.g + d;
-//         path_node.h = Heuristic::manhattan((jx - end_x).abs() as f32, (jy - end_y).abs() as f32);
-//         path_node.f = path_node.g + path_node.h;
-
-//         path_node.set_parent(parent.clone());
-
-//         res.push(path_node);
-//     }
-//     res
-// }
-fn back_trace(path_node: Rc<PathNode>) -> Vec<Position> {
-    let mut result = vec![];
-
-    let mut start = Some(path_node);
-    while let Some(node) = start {
-        result.push(node.node);
-        if let Some(parent) = node.parent.as_ref() {
-            let (dx, dy) = (node.get_x() - parent.get_x(), node.get_y() - parent.get_y());
-            let (dx, dy) = (
-                if 0 != dx { dx / dx.abs() } else { 0 },
-                if 0 != dy { dy / dy.abs() } else { 0 },
-            );
-            let (mut x, mut y) = (node.get_x(), node.get_y());
-
-            loop {
-                x -= dx;
-                y -= dy;
-                let pos = Position(x, y);
-                if pos == parent.node {
-                    break;
-                }
-                result.push(pos);
+    #[test]
+    fn turns_where_the_cost_changes() {
+        // a cheap row beside the expensive one the start and goal are on
+        let mut map = Map::new(10, 3, true);
+        for x in 1..9 {
+            map.set_cost(x, 0, Some(9));
+        }
+        assert_matches_dijkstra(&map, Position(0, 0), Position(9, 0));
+
+        // an expensive wall with one cheap tile to go through
+        let mut map = Map::new(9, 9, true);
+        for y in 0..9 {
+            if y != 6 {
+                map.set_cost(4, y, Some(8));
             }
         }
-        start = node.parent.clone();
+        assert_matches_dijkstra(&map, Position(0, 1), Position(8, 1));
+        assert_matches_dijkstra(&map, Position(2, 0), Position(8, 8));
     }
-    result
 }
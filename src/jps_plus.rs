@@ -0,0 +1,354 @@
+use std::{cmp::Reverse, collections::HashSet};
+
+use crate::{BestFirstSearch, Expander, Jps, Map, Pathfinder, Position, SearchSteps};
+
+/// The eight directions a `JumpTable` stores, orthogonal ones first.
+const DIRECTIONS: [(i32, i32); 8] = [
+    (0, -1),
+    (1, 0),
+    (0, 1),
+    (-1, 0),
+    (-1, -1),
+    (1, -1),
+    (1, 1),
+    (-1, 1),
+];
+
+fn direction_index(dx: i32, dy: i32) -> usize {
+    DIRECTIONS
+        .iter()
+        .position(|direction| *direction == (dx, dy))
+        .expect("not a unit direction")
+}
+
+/// Precomputed jumps for JPS+.
+///
+/// For every tile and direction the table holds the number of steps to the next jump point
+/// (positive) or, if there is none, the number of steps that can be taken before hitting a wall
+/// (zero or negative). The jump points are the same ones `Jps::jump` would find without a goal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JumpTable {
+    width: i32,
+    height: i32,
+    distances: Vec<[i32; 8]>,
+}
+
+impl JumpTable {
+    #[must_use]
+    pub fn new(map: &Map) -> JumpTable {
+        let mut table = JumpTable {
+            width: map.width,
+            height: map.height,
+            distances: vec![[0; 8]; (map.width * map.height) as usize],
+        };
+        // the directions a jump branches into first, the jump looks them up
+        for direction in sweep_order(map) {
+            table.sweep(map, direction);
+        }
+        table
+    }
+
+    /// Whether the table was built for a map of this size.
+    #[must_use]
+    pub fn fits(&self, map: &Map) -> bool {
+        self.width == map.width && self.height == map.height
+    }
+
+    /// The entry for leaving `(x, y)` in direction `(dx, dy)`.
+    #[must_use]
+    pub fn distance(&self, x: i32, y: i32, dx: i32, dy: i32) -> i32 {
+        self.distances[self.xy_idx(x, y)][direction_index(dx, dy)]
+    }
+
+    /// Refreshes the entries affected by a change of the tile at `(x, y)`, blocked or cost.
+    ///
+    /// Forced neighbors, cost boundaries and corner cutting only look one tile around, so only
+    /// entries near the change can change by themselves. From there the change is walked back
+    /// along each direction (the rows and columns through the tile for straight directions) for
+    /// as long as entries keep changing. Directions that branch (see `Jps::branches`) are also
+    /// refreshed wherever an entry they branch into changed.
+    pub fn update(&mut self, map: &Map, x: i32, y: i32) {
+        let nearby: Vec<Position> = (y - 2..=y + 2)
+            .flat_map(|ny| (x - 2..=x + 2).map(move |nx| Position(nx, ny)))
+            .filter(|position| map.inside(position.x(), position.y()))
+            .collect();
+
+        let mut branch_changes = HashSet::new();
+        for direction in sweep_order(map) {
+            let (dx, dy) = DIRECTIONS[direction];
+            let branches = !Jps::branches(map, dx, dy).is_empty();
+            let mut seeds = nearby.clone();
+            if branches {
+                seeds.extend(branch_changes.iter().copied());
+            }
+            // (x, y) depends on (x + dx, y + dy), so refresh whatever a changed tile leads into too
+            let mut starts: Vec<Position> = seeds
+                .iter()
+                .flat_map(|seed| [*seed, Position(seed.x() - dx, seed.y() - dy)])
+                .filter(|position| map.inside(position.x(), position.y()))
+                .collect();
+            starts.sort_by_key(|position| Reverse(position.x() * dx + position.y() * dy));
+            starts.dedup();
+
+            for start in starts {
+                let (mut cx, mut cy) = (start.x(), start.y());
+                while map.inside(cx, cy) {
+                    let index = self.xy_idx(cx, cy);
+                    let distance = self.compute(map, cx, cy, direction);
+                    if self.distances[index][direction] == distance {
+                        break;
+                    }
+                    self.distances[index][direction] = distance;
+                    if !branches {
+                        branch_changes.insert(Position(cx, cy));
+                    }
+                    cx -= dx;
+                    cy -= dy;
+                }
+            }
+        }
+    }
+
+    fn xy_idx(&self, x: i32, y: i32) -> usize {
+        (y as usize * self.width as usize) + x as usize
+    }
+
+    fn sweep(&mut self, map: &Map, direction: usize) {
+        let (dx, dy) = DIRECTIONS[direction];
+        // visit tiles so that (x + dx, y + dy) is always done before (x, y)
+        let xs: Vec<i32> = if dx > 0 {
+            (0..map.width).rev().collect()
+        } else {
+            (0..map.width).collect()
+        };
+        let ys: Vec<i32> = if dy > 0 {
+            (0..map.height).rev().collect()
+        } else {
+            (0..map.height).collect()
+        };
+
+        for y in &ys {
+            for x in &xs {
+                let index = self.xy_idx(*x, *y);
+                self.distances[index][direction] = self.compute(map, *x, *y, direction);
+            }
+        }
+    }
+
+    fn compute(&self, map: &Map, x: i32, y: i32, direction: usize) -> i32 {
+        let (dx, dy) = DIRECTIONS[direction];
+        if map.is_blocked(x, y) || !map.can_move(x, y, dx, dy) {
+            return 0;
+        }
+
+        let (nx, ny) = (x + dx, y + dy);
+        if self.is_jump_point(map, nx, ny, dx, dy) {
+            return 1;
+        }
+
+        let next = self.distances[self.xy_idx(nx, ny)][direction];
+        if next > 0 {
+            next + 1
+        } else {
+            next - 1
+        }
+    }
+
+    /// Mirrors `Jps::jump`, minus the goal check which JPS+ does while searching.
+    fn is_jump_point(&self, map: &Map, x: i32, y: i32, dx: i32, dy: i32) -> bool {
+        if !Jps::can_prune(map, x, y) || Jps::has_forced_neighbor(map, x, y, dx, dy) {
+            return true;
+        }
+
+        Jps::branches(map, dx, dy)
+            .into_iter()
+            .any(|(bx, by)| self.distance(x, y, bx, by) > 0)
+    }
+}
+
+/// Directions in the order they have to be swept: the ones a jump branches into before the jump.
+fn sweep_order(map: &Map) -> Vec<usize> {
+    let (branching, plain): (Vec<usize>, Vec<usize>) =
+        (0..DIRECTIONS.len()).partition(|direction| {
+            let (dx, dy) = DIRECTIONS[*direction];
+            !Jps::branches(map, dx, dy).is_empty()
+        });
+    plain.into_iter().chain(branching).collect()
+}
+
+/// Jump Point Search that reads its jumps from a precomputed `JumpTable`.
+///
+/// Uses `Map::jump_table` when it is present, otherwise builds a table for this query only.
+pub struct JpsPlus;
+
+impl Pathfinder for JpsPlus {
+    fn name(&self) -> &'static str {
+        "Jps+"
+    }
+
+    fn steps(&self, map: &Map, start: Position, goal: Position) -> Box<dyn SearchSteps> {
+        let table = match map.jump_table.as_ref() {
+            Some(table) if table.fits(map) => None,
+            _ => Some(JumpTable::new(map)),
+        };
+        Box::new(BestFirstSearch::new(
+            map,
+            JpsPlusExpander { table },
+            start,
+            goal,
+        ))
+    }
+}
+
+/// Expands a node into the jump points its `JumpTable` entries lead to.
+pub struct JpsPlusExpander {
+    /// Built for this search when the map has no table of its own.
+    table: Option<JumpTable>,
+}
+
+impl Expander for JpsPlusExpander {
+    fn successors(
+        &self,
+        map: &Map,
+        node: Position,
+        parent: Option<Position>,
+        goal: Position,
+        generated: &mut Vec<Position>,
+    ) -> Vec<(Position, i32)> {
+        let Some(table) = self.table.as_ref().or(map.jump_table.as_ref()) else {
+            return vec![];
+        };
+        successors(map, table, node, parent, goal)
+            .into_iter()
+            .map(|successor| {
+                generated.push(successor);
+                (successor, Jps::segment_cost(map, node, successor))
+            })
+            .collect()
+    }
+
+    fn refine(&self, _map: &Map, nodes: Vec<Position>) -> Vec<Position> {
+        Jps::fill_segments(&nodes)
+    }
+}
+
+fn successors(
+    map: &Map,
+    table: &JumpTable,
+    node: Position,
+    parent: Option<Position>,
+    goal: Position,
+) -> Vec<Position> {
+    let (x, y) = (node.x(), node.y());
+    let (gx, gy) = (goal.x() - x, goal.y() - y);
+
+    let mut result = vec![];
+    for neighbor in Jps::find_neighbors(map, node, parent) {
+        let (dx, dy) = (neighbor.x() - x, neighbor.y() - y);
+        let distance = table.distance(x, y, dx, dy);
+        let steps = distance.abs();
+
+        if dx == 0 || dy == 0 {
+            // the goal is straight ahead and nothing is in the way
+            let on_line = if dx == 0 {
+                gx == 0 && gy.signum() == dy
+            } else {
+                gy == 0 && gx.signum() == dx
+            };
+            if on_line && gx.abs().max(gy.abs()) <= steps {
+                result.push(goal);
+                continue;
+            }
+            // on a 4-way grid a horizontal jump crossing the goal's column stops there, so the
+            // vertical jump from that tile can reach it
+            if !map.diagonals() && dy == 0 && gx.signum() == dx && gx.abs() <= steps {
+                result.push(Position(x + gx, y));
+                continue;
+            }
+        } else if gx.signum() == dx && gy.signum() == dy {
+            // the goal's row or column is crossed before the jump ends, stop there so the
+            // straight jump from that tile can reach it
+            let t = gx.abs().min(gy.abs());
+            if t <= steps {
+                result.push(Position(x + t * dx, y + t * dy));
+                continue;
+            }
+        }
+
+        if distance > 0 {
+            result.push(Position(x + distance * dx, y + distance * dy));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        random_pairs, solve, split_mix, test_support::random_map, JumpTable, Map,
+        PathfindingAlgorithm,
+    };
+
+    #[test]
+    fn updates_stop_at_the_new_jump_point() {
+        let mut map = Map::new(8, 3, true);
+        map.build_jump_table();
+        // nothing to jump to along the middle row, only the 7 steps to the map edge
+        assert_eq!(map.jump_table.as_ref().unwrap().distance(0, 1, 1, 0), -7);
+
+        // the wall above (4, 1) makes it a jump point, 4 steps on
+        map.set_blocked(4, 0, true);
+        let table = map.jump_table.as_ref().unwrap();
+        assert_eq!(table.distance(0, 1, 1, 0), 4);
+        assert_eq!(table.distance(3, 1, 1, 0), 1);
+        assert_eq!(table.distance(4, 1, 1, 0), -3);
+        // a wall straight ahead
+        assert_eq!(table.distance(4, 1, 0, -1), 0);
+
+        map.set_blocked(4, 0, false);
+        assert_eq!(map.jump_table.as_ref().unwrap().distance(0, 1, 1, 0), -7);
+    }
+
+    #[test]
+    fn updates_match_a_fresh_table() {
+        for seed in 0..10 {
+            let mut map = random_map(20, 17, seed);
+            map.build_jump_table();
+            let mut state = seed;
+            for edit in 0..40 {
+                let value = split_mix(&mut state);
+                let (x, y) = ((value % 20) as i32, ((value >> 32) % 17) as i32);
+                if edit % 3 == 0 {
+                    map.set_cost(x, y, Some(1 + (value >> 48) as i32 % 4));
+                } else {
+                    map.set_blocked(x, y, !map.is_blocked(x, y));
+                }
+                assert_eq!(
+                    map.jump_table,
+                    Some(JumpTable::new(&map)),
+                    "seed {seed}, edit {edit}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn matches_dijkstra_after_updates() {
+        for seed in 0..10 {
+            let mut map = random_map(24, 20, seed);
+            map.build_jump_table();
+            for (x, y) in [(3, 4), (10, 10), (11, 10), (20, 2)] {
+                map.set_blocked(x, y, !map.is_blocked(x, y));
+            }
+            for (start, goal) in random_pairs(&map, 10, seed) {
+                let dijkstra = solve(&map, PathfindingAlgorithm::Dijkstra, start, goal);
+                let jps_plus = solve(&map, PathfindingAlgorithm::JpsPlus, start, goal);
+                assert_eq!(jps_plus.cost, dijkstra.cost, "{start:?} to {goal:?}");
+            }
+        }
+    }
+}
+
+// References
+// 1. JPS+: An Extreme A* Speed Optimization for Static Uniform Cost Grids, Steve Rabin
+// http://www.gameaipro.com/GameAIPro2/GameAIPro2_Chapter14_JPS_Plus_An_Extreme_A_Star_Speed_Optimization_for_Static_Uniform_Cost_Grids.pdf
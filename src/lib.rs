@@ -0,0 +1,60 @@
+#![warn(clippy::all, clippy::pedantic)]
+// Tiles are addressed with `i32` coordinates (steps can go negative) and stored in `usize`-indexed
+// vectors, and costs are scaled to and from floats. Maps are far smaller than where any of these
+// casts could lose a value, and coordinates are checked with `Map::inside` before they index.
+#![allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+
+//! Headless grid pathfinding.
+//!
+//! Everything in here is plain Rust: the `Map` grid, the `Pathfinder` implementations and the
+//! `solve` entry point can be used without Bevy. The windowed example app in
+//! `main.rs` is a thin front-end over this crate.
+
+mod benchmark;
+mod bidirectional;
+mod corner_cutting;
+mod cost_model;
+mod flow_field;
+mod hpa;
+mod incremental;
+mod jps;
+mod jps_plus;
+mod map;
+mod map_file;
+mod moving_ai;
+mod multi_agent;
+mod pathfinder;
+mod search;
+mod smoothing;
+mod solver;
+#[cfg(test)]
+mod test_support;
+mod theta;
+mod timeline;
+mod weighted;
+
+pub use benchmark::*;
+pub use bidirectional::*;
+pub use corner_cutting::*;
+pub use cost_model::*;
+pub use flow_field::*;
+pub use hpa::*;
+pub use incremental::*;
+pub use jps::{Jps, JpsExpander};
+pub use jps_plus::*;
+pub use map::*;
+pub use map_file::*;
+pub use moving_ai::*;
+pub use multi_agent::*;
+pub use pathfinder::*;
+pub use search::*;
+pub use smoothing::*;
+pub use solver::*;
+pub use theta::*;
+pub use timeline::*;
+pub use weighted::*;
@@ -1,10 +1,10 @@
 #![warn(clippy::all, clippy::pedantic)]
 
+mod agent;
 mod camera;
 mod game;
-mod jps;
-mod map;
 mod mouse;
+mod multi_agent_playback;
 mod physics;
 mod tilemap;
 mod user_interface;
@@ -16,10 +16,12 @@ use bevy_ecs_tilemap::prelude::*;
 use bevy_inspector_egui::WorldInspectorPlugin;
 use bevy_rapier2d::prelude::*;
 
+pub use agent::*;
+pub use bevy_pathfinding::*;
 pub use camera::*;
 pub use game::*;
-pub use map::*;
 pub use mouse::*;
+pub use multi_agent_playback::*;
 pub use physics::*;
 pub use tilemap::*;
 pub use user_interface::*;
@@ -43,6 +45,10 @@ fn main() {
             ..default()
         })
         .insert_resource(ImageSettings::default_nearest()) // prevents blurry sprites
+        .insert_resource(MapSource::from_args())
+        .insert_resource(SearchPlayback::default())
+        .insert_resource(Replanner::default())
+        .insert_resource(MultiAgentPlayback::default())
         .add_plugins(DefaultPlugins)
         .add_plugin(WorldInspectorPlugin::new()) // bevy_inspector_egui
         .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(
@@ -51,6 +57,7 @@ fn main() {
         // .add_plugin(RapierDebugRenderPlugin::default())// bevy_rapier2d debugger
         .add_plugin(TilemapPlugin) // bevy_ecs_tilemap
         .add_event::<MapUpdatedEvent>()
+        .add_event::<TileChangedEvent>()
         .add_event::<UserInterfaceInteractionEvent>()
         .add_event::<StepEvent>()
         .add_event::<SolveEvent>()
@@ -60,6 +67,35 @@ fn main() {
         .add_event::<CycleAlgorithmRightEvent>()
         .add_event::<PathfindingAlgorithmSelectionChangedEvent>()
         .add_event::<PathfindingAlgorithmChangedEvent>()
+        .add_event::<CycleCostModelEvent>()
+        .add_event::<CostModelChangedEvent>()
+        .add_event::<ToggleDiagonalsEvent>()
+        .add_event::<DiagonalsChangedEvent>()
+        .add_event::<CycleCornerCuttingEvent>()
+        .add_event::<CornerCuttingChangedEvent>()
+        .add_event::<CompareEvent>()
+        .add_event::<SpawnAgentEvent>()
+        .add_event::<PlanAgentsEvent>()
+        .add_event::<CycleMultiAgentPlannerEvent>()
+        .add_event::<MultiAgentPlannerChangedEvent>()
+        .add_event::<MultiAgentChangedEvent>()
+        .add_event::<ToggleNodeStatsEvent>()
+        .add_event::<NodeStatsChangedEvent>()
+        .add_event::<CycleHeatMapEvent>()
+        .add_event::<HeatMapChangedEvent>()
+        .add_event::<ToggleFlowFieldEvent>()
+        .add_event::<FlowFieldChangedEvent>()
+        .add_event::<CycleWeightEvent>()
+        .add_event::<WeightChangedEvent>()
+        .add_event::<CycleSmoothingEvent>()
+        .add_event::<SmoothingChangedEvent>()
+        .add_event::<SaveMapEvent>()
+        .add_event::<LoadMapEvent>()
+        .add_event::<TogglePlayEvent>()
+        .add_event::<CyclePlaybackSpeedEvent>()
+        .add_event::<StepBackEvent>()
+        .add_event::<RewindEvent>()
+        .add_event::<SeekEvent>()
         .add_startup_system(setup_physics)
         .add_startup_system(setup_map.label(Setup::Map))
         .add_startup_system_set_to_stage(
@@ -79,11 +115,34 @@ fn main() {
         .add_startup_system(setup_camera)
         .add_startup_system(setup_user_interface)
         .add_system(draw_path_tilemap)
+        .add_system(draw_comparison)
+        .add_system(draw_path_lines)
+        .add_system(draw_node_stats)
+        .add_system(draw_flow_field)
+        .add_system(draw_multi_agent)
         .add_system(update_cost_tilemap)
+        .add_system(sync_tile_colliders_system)
+        .add_system(sync_border_colliders_system)
         .add_system(placement_system)
         .add_system(cost_system)
         .add_system(step_system)
+        .add_system(step_back_system)
+        .add_system(rewind_system)
+        .add_system(seek_system)
+        .add_system(play_system)
+        .add_system(cycle_playback_speed_system)
         .add_system(solve_system)
+        .add_system(replan_system.after(placement_system).after(cost_system))
+        .add_system(compare_system)
+        .add_system(spawn_agent_system)
+        .add_system(agent_steering_system)
+        .add_system(agent_replan_system)
+        .add_system(despawn_agents_system)
+        .add_system(multi_agent_placement_system)
+        .add_system(cycle_multi_agent_planner_system)
+        .add_system(plan_agents_system)
+        .add_system(multi_agent_playback_system)
+        .add_system(reset_multi_agent_system)
         .add_system(reset_system)
         .add_system(clear_system)
         .add_system(camera_movement_system)
@@ -96,9 +155,23 @@ fn main() {
         .add_system(goal_button_system)
         .add_system(increase_cost_button_system)
         .add_system(decrease_cost_button_system)
+        .add_system(agent_pair_button_system)
+        .add_system(multi_agent_planner_button_system)
+        .add_system(update_multi_agent_planner_text_system)
+        .add_system(plan_agents_button_system)
         .add_system(show_hide_increase_decrease_cost_buttons)
+        .add_system(rewind_button_system)
+        .add_system(step_back_button_system)
         .add_system(step_button_system)
+        .add_system(play_button_system)
+        .add_system(playback_speed_button_system)
+        .add_system(update_playback_text_system)
+        .add_system(timeline_bar_system)
+        .add_system(update_timeline_system)
         .add_system(solve_button_system)
+        .add_system(compare_button_system)
+        .add_system(agent_button_system)
+        .add_system(update_comparison_panel_system)
         .add_system(reset_button_system)
         .add_system(clear_button_system)
         .add_system(change_pathfinding_algorithm_system)
@@ -106,6 +179,37 @@ fn main() {
         .add_system(cycle_algorithm_right_button_system)
         .add_system(cycle_algorithm_selection_system)
         .add_system(update_current_algorithm_text_system)
+        .add_system(change_cost_model_system)
+        .add_system(cost_model_button_system)
+        .add_system(update_cost_model_text_system)
+        .add_system(toggle_diagonals_system)
+        .add_system(diagonals_button_system)
+        .add_system(update_diagonals_text_system)
+        .add_system(toggle_node_stats_system)
+        .add_system(node_stats_button_system)
+        .add_system(update_node_stats_text_system)
+        .add_system(toggle_flow_field_system)
+        .add_system(update_flow_field_system)
+        .add_system(flow_field_button_system)
+        .add_system(update_flow_field_text_system)
+        .add_system(cycle_heat_map_system)
+        .add_system(heat_map_button_system)
+        .add_system(update_heat_map_text_system)
+        .add_system(update_heat_map_legend_system)
+        .add_system(cycle_weight_system)
+        .add_system(weight_button_system)
+        .add_system(update_weight_text_system)
+        .add_system(show_hide_weight_button)
+        .add_system(cycle_smoothing_system)
+        .add_system(smoothing_button_system)
+        .add_system(update_smoothing_text_system)
+        .add_system(change_corner_cutting_system)
+        .add_system(corner_cutting_button_system)
+        .add_system(update_corner_cutting_text_system)
+        .add_system(save_map_system)
+        .add_system(load_map_system)
+        .add_system(save_button_system)
+        .add_system(load_button_system)
         .add_system(show_hide_cost_tilemap)
         .add_system(send_ui_interaction_events_system)
         .run();
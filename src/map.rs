@@ -1,26 +1,31 @@
-use bevy::prelude::*;
+use std::cmp::Ordering;
+
+use crate::{CornerCutting, CostModel, Hierarchy, JumpTable};
 use noise::{
     utils::{NoiseMapBuilder, PlaneMapBuilder},
     Fbm, MultiFractal,
 };
 
-pub const MAP_WIDTH: i32 = 64;
-pub const MAP_HEIGHT: i32 = 64;
-
-/// === Events ===
-pub struct MapUpdatedEvent {}
-
-/// === Resources ===
 #[derive(Debug)]
 pub struct Map {
     pub width: i32,
     pub height: i32,
     pub costs: Vec<Option<i32>>,
     pub blocked: Vec<bool>,
+    /// Whether diagonal steps exist at all, change it with `set_allow_diagonals`.
     pub allow_diagonals: bool,
+    /// How steps are priced, change it with `set_cost_model`.
+    pub cost_model: CostModel,
+    /// When diagonal steps may pass blocked tiles, change it with `set_corner_cutting`.
+    pub corner_cutting: CornerCutting,
+    /// JPS+ jumps, kept in sync by `set_blocked` and `set_cost` once built.
+    pub jump_table: Option<JumpTable>,
+    /// HPA* clusters, kept in sync by `set_blocked` and `set_cost` once built.
+    pub hierarchy: Option<Hierarchy>,
 }
 
 impl Map {
+    #[must_use]
     pub fn new(width: i32, height: i32, allow_diagonals: bool) -> Map {
         Map {
             width,
@@ -28,29 +33,163 @@ impl Map {
             costs: vec![Some(1); (width * height) as usize],
             blocked: vec![false; (width * height) as usize],
             allow_diagonals,
+            cost_model: CostModel::Octile,
+            corner_cutting: CornerCutting::OneSideOpen,
+            jump_table: None,
+            hierarchy: None,
+        }
+    }
+
+    /// Generates a map whose obstacles are placed by fractal noise.
+    #[must_use]
+    pub fn from_noise(width: i32, height: i32, allow_diagonals: bool) -> Map {
+        let mut map = Map::new(width, height, allow_diagonals);
+        //噪音函数，自动生成阻挡物
+        let fbm = Fbm::new()
+            .set_octaves(16)
+            .set_frequency(1.5)
+            .set_lacunarity(3.0)
+            .set_persistence(0.9);
+        let plane = PlaneMapBuilder::new(&fbm)
+            .set_size(width as usize, height as usize)
+            .build();
+        //阻挡物生成阈值
+        let threshold = 0.3;
+        for w in 0..width {
+            for h in 0..height {
+                if threshold < plane.get_value(w as usize, h as usize) {
+                    let idx = map.xy_idx(w, h);
+                    map.blocked[idx] = true;
+                }
+            }
+        }
+
+        map
+    }
+
+    /// Precomputes the JPS+ jumps for the current tiles.
+    pub fn build_jump_table(&mut self) {
+        self.jump_table = Some(JumpTable::new(self));
+    }
+
+    /// Splits the map into `cluster_size` x `cluster_size` clusters for HPA*.
+    pub fn build_hierarchy(&mut self, cluster_size: i32) {
+        self.hierarchy = Some(Hierarchy::new(self, cluster_size));
+    }
+
+    pub fn set_blocked(&mut self, x: i32, y: i32, blocked: bool) {
+        let index = self.xy_idx(x, y);
+        if self.blocked[index] != blocked {
+            self.blocked[index] = blocked;
+            self.update_caches(x, y);
+        }
+    }
+
+    pub fn set_cost(&mut self, x: i32, y: i32, cost: Option<i32>) {
+        let index = self.xy_idx(x, y);
+        if self.costs[index] != cost {
+            self.costs[index] = cost;
+            self.update_caches(x, y);
+        }
+    }
+
+    /// Switches the cost model and rebuilds whatever was precomputed with the old one.
+    pub fn set_cost_model(&mut self, cost_model: CostModel) {
+        self.cost_model = cost_model;
+        self.rebuild_caches();
+    }
+
+    /// Switches between 8-way and 4-way movement and rebuilds whatever was precomputed.
+    pub fn set_allow_diagonals(&mut self, allow_diagonals: bool) {
+        self.allow_diagonals = allow_diagonals;
+        self.rebuild_caches();
+    }
+
+    /// Switches the corner cutting policy and rebuilds whatever was precomputed.
+    pub fn set_corner_cutting(&mut self, corner_cutting: CornerCutting) {
+        self.corner_cutting = corner_cutting;
+        self.rebuild_caches();
+    }
+
+    /// Whether diagonal steps are generated: they must be allowed, and neither the cost model nor
+    /// the corner cutting policy may rule them out.
+    #[must_use]
+    pub fn diagonals(&self) -> bool {
+        self.allow_diagonals
+            && self.cost_model.allows_diagonals()
+            && self.corner_cutting.allows_diagonals()
+    }
+
+    /// Whether `hierarchy` is there and was built for a map of this size, so HPA* need not build
+    /// one for every query.
+    #[must_use]
+    pub fn has_hierarchy(&self) -> bool {
+        self.hierarchy
+            .as_ref()
+            .is_some_and(|hierarchy| hierarchy.fits(self))
+    }
+
+    fn rebuild_caches(&mut self) {
+        if self.jump_table.is_some() {
+            self.build_jump_table();
+        }
+        if let Some(cluster_size) = self.hierarchy.as_ref().map(Hierarchy::cluster_size) {
+            self.build_hierarchy(cluster_size);
+        }
+    }
+
+    fn update_caches(&mut self, x: i32, y: i32) {
+        if let Some(mut jump_table) = self.jump_table.take() {
+            jump_table.update(self, x, y);
+            self.jump_table = Some(jump_table);
+        }
+        if let Some(mut hierarchy) = self.hierarchy.take() {
+            hierarchy.update(self, x, y);
+            self.hierarchy = Some(hierarchy);
         }
     }
 
+    #[must_use]
     pub fn xy_idx(&self, x: i32, y: i32) -> usize {
         (y as usize * self.width as usize) + x as usize
     }
 
+    #[must_use]
     pub fn inside(&self, x: i32, y: i32) -> bool {
         0 <= x && x < self.width && 0 <= y && y < self.height
     }
 
+    #[must_use]
     pub fn outside(&self, x: i32, y: i32) -> bool {
         !self.inside(x, y)
     }
 
+    #[must_use]
     pub fn is_blocked(&self, x: i32, y: i32) -> bool {
         self.outside(x, y) || self.blocked[self.xy_idx(x, y)]
     }
 
+    #[must_use]
     pub fn is_path(&self, x: i32, y: i32) -> bool {
         self.inside(x, y) & !self.is_blocked(x, y)
     }
 
+    /// Whether a single step from `(x, y)` in direction `(dx, dy)` is allowed.
+    #[must_use]
+    pub fn can_move(&self, x: i32, y: i32, dx: i32, dy: i32) -> bool {
+        if !self.is_path(x + dx, y + dy) {
+            return false;
+        }
+        if dx == 0 || dy == 0 {
+            return true;
+        }
+        self.diagonals()
+            && self
+                .corner_cutting
+                .allows(self.is_path(x + dx, y), self.is_path(x, y + dy))
+    }
+
+    #[must_use]
     pub fn walkable_position(&self, x: i32, y: i32) -> Option<Position> {
         if self.outside(x, y) {
             return None;
@@ -63,52 +202,125 @@ impl Map {
         Some(Position(x, y))
     }
 
+    /// Terrain cost of the tile at `(x, y)`.
+    #[must_use]
+    pub fn cost(&self, x: i32, y: i32) -> i32 {
+        self.costs[self.xy_idx(x, y)].unwrap_or(1)
+    }
+
+    /// Cost of stepping from `from` onto the neighboring tile `to`, the value `get_successors`
+    /// reports.
+    #[must_use]
+    pub fn step_cost(&self, from: &Position, to: &Position) -> i32 {
+        self.cost_model.step_cost(
+            to.x() - from.x(),
+            to.y() - from.y(),
+            self.cost(to.x(), to.y()),
+        )
+    }
+
+    /// Sum of the step costs along `path`, the start tile itself is free.
+    #[must_use]
+    pub fn path_cost(&self, path: &[Position]) -> i32 {
+        path.windows(2)
+            .map(|step| self.step_cost(&step[0], &step[1]))
+            .sum()
+    }
+
+    /// Admissible estimate of the cost from `from` to `to` under the current cost model, or the
+    /// Manhattan distance when movement is 4-way.
+    #[must_use]
+    pub fn heuristic(&self, from: &Position, to: &Position) -> i32 {
+        if self.diagonals() {
+            self.cost_model.heuristic(from, to)
+        } else {
+            CostModel::Manhattan.heuristic(from, to)
+        }
+    }
+
+    /// Whether a straight line between the centres of `from` and `to` only crosses open tiles.
+    ///
+    /// Where the line passes exactly through the corner of four tiles, the two tiles beside it
+    /// are treated like the sides of a diagonal step: both open always lets it through, otherwise
+    /// the corner cutting policy decides.
+    #[must_use]
+    pub fn line_of_sight(&self, from: &Position, to: &Position) -> bool {
+        let (dx, dy) = (to.x() - from.x(), to.y() - from.y());
+        let (nx, ny) = (dx.abs(), dy.abs());
+        let (sx, sy) = (dx.signum(), dy.signum());
+        let (mut x, mut y) = (from.x(), from.y());
+        let (mut ix, mut iy) = (0, 0);
+        if !self.is_path(x, y) {
+            return false;
+        }
+        while ix < nx || iy < ny {
+            // which tile border the line reaches first, compared without dividing
+            let horizontal = (1 + 2 * ix) * ny;
+            let vertical = (1 + 2 * iy) * nx;
+            match horizontal.cmp(&vertical) {
+                Ordering::Equal => {
+                    let side_a = self.is_path(x + sx, y);
+                    let side_b = self.is_path(x, y + sy);
+                    if !((side_a && side_b) || self.corner_cutting.allows(side_a, side_b)) {
+                        return false;
+                    }
+                    x += sx;
+                    y += sy;
+                    ix += 1;
+                    iy += 1;
+                }
+                Ordering::Less => {
+                    x += sx;
+                    ix += 1;
+                }
+                Ordering::Greater => {
+                    y += sy;
+                    iy += 1;
+                }
+            }
+            if !self.is_path(x, y) {
+                return false;
+            }
+        }
+        true
+    }
+
+    #[must_use]
     pub fn get_successors(&self, node: &Position) -> Vec<Successor> {
         let (x, y) = (node.x(), node.y());
 
-        let n = self.walkable_position(x, y - 1);
-        let e = self.walkable_position(x + 1, y);
-        let s = self.walkable_position(x, y + 1);
-        let w = self.walkable_position(x - 1, y);
+        let north = self.walkable_position(x, y - 1);
+        let east = self.walkable_position(x + 1, y);
+        let south = self.walkable_position(x, y + 1);
+        let west = self.walkable_position(x - 1, y);
 
-        let nw = if n.is_some() || w.is_some() {
+        let nw = if self.can_move(x, y, -1, -1) {
             self.walkable_position(x - 1, y - 1)
         } else {
             None
         };
-        let ne = if n.is_some() || e.is_some() {
+        let ne = if self.can_move(x, y, 1, -1) {
             self.walkable_position(x + 1, y - 1)
         } else {
             None
         };
-        let se = if s.is_some() || e.is_some() {
+        let se = if self.can_move(x, y, 1, 1) {
             self.walkable_position(x + 1, y + 1)
         } else {
             None
         };
-        let sw = if s.is_some() || w.is_some() {
+        let sw = if self.can_move(x, y, -1, 1) {
             self.walkable_position(x - 1, y + 1)
         } else {
             None
         };
 
-        vec![n, e, s, w, nw, ne, se, sw]
+        vec![north, east, south, west, nw, ne, se, sw]
             .into_iter()
             .flatten()
-            .map(|node| {
-                let neighbor_index = self.xy_idx(node.x(), node.y());
-                let neighbor_cost = self.costs[neighbor_index];
-                if let Some(neighbor_cost) = neighbor_cost {
-                    Successor {
-                        position: node,
-                        cost: neighbor_cost,
-                    }
-                } else {
-                    Successor {
-                        position: node,
-                        cost: 1,
-                    }
-                }
+            .map(|position| Successor {
+                position,
+                cost: self.step_cost(node, &position),
             })
             .collect()
     }
@@ -118,14 +330,17 @@ impl Map {
 pub struct Position(pub i32, pub i32);
 
 impl Position {
+    #[must_use]
     pub fn distance(&self, other: &Position) -> i32 {
         (self.0.abs_diff(other.0) + self.1.abs_diff(other.1)) as i32
     }
 
+    #[must_use]
     pub fn x(&self) -> i32 {
         self.0
     }
 
+    #[must_use]
     pub fn y(&self) -> i32 {
         self.1
     }
@@ -136,30 +351,3 @@ pub struct Successor {
     pub position: Position,
     pub cost: i32,
 }
-
-/// === Systems ===
-pub fn setup_map(mut commands: Commands) {
-    println!("Setup Map...");
-    let mut map = Map::new(MAP_WIDTH, MAP_HEIGHT, true);
-    //噪音函数，自动生成阻挡物
-    let fbm = Fbm::new()
-        .set_octaves(16)
-        .set_frequency(1.5)
-        .set_lacunarity(3.0)
-        .set_persistence(0.9);
-    let plane = PlaneMapBuilder::new(&fbm)
-        .set_size(MAP_WIDTH as usize, MAP_HEIGHT as usize)
-        .build();
-    //阻挡物生成阈值
-    let threshold = 0.3;
-    for w in 0..MAP_WIDTH {
-        for h in 0..MAP_HEIGHT {
-            if threshold < plane.get_value(w as usize, h as usize) {
-                let idx = map.xy_idx(w, h);
-                map.blocked[idx] = true;
-            }
-        }
-    }
-
-    commands.insert_resource(map);
-}
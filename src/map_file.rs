@@ -0,0 +1,288 @@
+use std::{
+    fmt::{self, Write},
+    fs, io,
+    path::Path,
+};
+
+use crate::{Map, Position};
+
+/// A map together with the start and goal it was saved with.
+///
+/// The text format is meant to be edited by hand:
+///
+/// ```text
+/// size 8 4
+/// start 1 1
+/// goal 6 2
+/// map
+/// ........
+/// ..##.3..
+/// .+.#....
+/// ........
+/// cost 1 1 12
+/// ```
+///
+/// Rows are written top to bottom as they appear on screen, so the first row is
+/// `y = height - 1`. A tile is `#` when blocked, `.` when it costs 1, a digit for costs 2 to 9,
+/// and `+` for anything higher, with the value given by a `cost x y value` line after the rows.
+/// Blocked tiles that cost more than 1 get a `cost` line too, so opening them again restores it.
+#[derive(Debug)]
+pub struct MapFile {
+    pub map: Map,
+    pub start: Position,
+    pub goal: Position,
+}
+
+#[derive(Debug)]
+pub enum MapFileError {
+    Io(io::Error),
+    /// A line does not follow the format, `line` is 1-based.
+    Parse {
+        line: usize,
+        message: String,
+    },
+    /// The file as a whole does not describe a valid map.
+    Invalid(String),
+}
+
+impl fmt::Display for MapFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapFileError::Io(error) => write!(f, "{error}"),
+            MapFileError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            MapFileError::Invalid(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for MapFileError {}
+
+impl From<io::Error> for MapFileError {
+    fn from(error: io::Error) -> Self {
+        MapFileError::Io(error)
+    }
+}
+
+impl MapFile {
+    /// Reads and parses the map stored at `path`.
+    ///
+    /// # Errors
+    ///
+    /// `MapFileError::Io` when the file cannot be read, otherwise the errors of `MapFile::parse`.
+    pub fn load(path: impl AsRef<Path>) -> Result<MapFile, MapFileError> {
+        MapFile::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Writes `map`, `start` and `goal` to `path`.
+    ///
+    /// # Errors
+    ///
+    /// `MapFileError::Io` when the file cannot be written.
+    pub fn save(
+        path: impl AsRef<Path>,
+        map: &Map,
+        start: Position,
+        goal: Position,
+    ) -> Result<(), MapFileError> {
+        fs::write(path, MapFile::format(map, start, goal))?;
+        Ok(())
+    }
+
+    /// Renders `map`, `start` and `goal` in the text format.
+    #[must_use]
+    pub fn format(map: &Map, start: Position, goal: Position) -> String {
+        let mut text = format!(
+            "size {} {}\nstart {} {}\ngoal {} {}\nmap\n",
+            map.width,
+            map.height,
+            start.x(),
+            start.y(),
+            goal.x(),
+            goal.y()
+        );
+        let mut listed_costs = vec![];
+        for y in (0..map.height).rev() {
+            for x in 0..map.width {
+                let cost = map.cost(x, y);
+                text.push(if map.is_blocked(x, y) {
+                    if cost != 1 {
+                        listed_costs.push((x, y, cost));
+                    }
+                    '#'
+                } else if cost == 1 {
+                    '.'
+                } else if (2..=9).contains(&cost) {
+                    char::from_digit(cost as u32, 10).unwrap_or('+')
+                } else {
+                    listed_costs.push((x, y, cost));
+                    '+'
+                });
+            }
+            text.push('\n');
+        }
+        for (x, y, cost) in listed_costs {
+            let _ = writeln!(text, "cost {x} {y} {cost}");
+        }
+        text
+    }
+
+    /// Parses a map in the text format.
+    ///
+    /// # Errors
+    ///
+    /// `MapFileError::Parse` for a line that does not follow the format, and
+    /// `MapFileError::Invalid` when the size, start or goal is missing or out of range.
+    pub fn parse(text: &str) -> Result<MapFile, MapFileError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim_end()))
+            .filter(|(_, line)| !line.is_empty());
+
+        let mut size = None;
+        let mut start = None;
+        let mut goal = None;
+        for (number, line) in lines.by_ref() {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("size") => size = Some(parse_pair(number, words)?),
+                Some("start") => start = Some(parse_pair(number, words)?),
+                Some("goal") => goal = Some(parse_pair(number, words)?),
+                Some("map") => break,
+                _ => return Err(parse_error(number, format!("unexpected `{line}`"))),
+            }
+        }
+
+        let (width, height) =
+            size.ok_or_else(|| MapFileError::Invalid("missing `size`".to_string()))?;
+        if width <= 0 || height <= 0 {
+            return Err(MapFileError::Invalid(
+                "the size must be positive".to_string(),
+            ));
+        }
+        let start = start.ok_or_else(|| MapFileError::Invalid("missing `start`".to_string()))?;
+        let goal = goal.ok_or_else(|| MapFileError::Invalid("missing `goal`".to_string()))?;
+
+        let mut map = Map::new(width, height, true);
+        for y in (0..height).rev() {
+            let (number, row) = lines
+                .next()
+                .ok_or_else(|| MapFileError::Invalid(format!("expected {height} rows")))?;
+            if row.chars().count() != width as usize {
+                return Err(parse_error(number, format!("expected {width} tiles")));
+            }
+            for (x, tile) in row.chars().enumerate() {
+                let index = map.xy_idx(x as i32, y);
+                match tile {
+                    '#' => map.blocked[index] = true,
+                    '.' | '+' => {}
+                    '2'..='9' => map.costs[index] = tile.to_digit(10).map(|cost| cost as i32),
+                    _ => return Err(parse_error(number, format!("unknown tile `{tile}`"))),
+                }
+            }
+        }
+
+        for (number, line) in lines {
+            let mut words = line.split_whitespace();
+            if words.next() != Some("cost") {
+                return Err(parse_error(number, format!("unexpected `{line}`")));
+            }
+            let (x, y) = parse_pair(number, words.by_ref())?;
+            let cost = parse_number(number, words.next())?;
+            if !map.inside(x, y) || cost < 1 {
+                return Err(parse_error(number, "cost outside the map or below 1"));
+            }
+            let index = map.xy_idx(x, y);
+            map.costs[index] = Some(cost);
+        }
+
+        let (start, goal) = (Position(start.0, start.1), Position(goal.0, goal.1));
+        if !map.inside(start.x(), start.y()) || !map.inside(goal.x(), goal.y()) {
+            return Err(MapFileError::Invalid(
+                "start and goal must be inside the map".to_string(),
+            ));
+        }
+        Ok(MapFile { map, start, goal })
+    }
+}
+
+pub(crate) fn parse_error(line: usize, message: impl Into<String>) -> MapFileError {
+    MapFileError::Parse {
+        line,
+        message: message.into(),
+    }
+}
+
+pub(crate) fn parse_number(line: usize, word: Option<&str>) -> Result<i32, MapFileError> {
+    word.and_then(|word| word.parse().ok())
+        .ok_or_else(|| parse_error(line, "expected a number"))
+}
+
+fn parse_pair<'a>(
+    line: usize,
+    mut words: impl Iterator<Item = &'a str>,
+) -> Result<(i32, i32), MapFileError> {
+    Ok((
+        parse_number(line, words.next())?,
+        parse_number(line, words.next())?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let mut map = Map::new(7, 4, true);
+        map.set_blocked(2, 1, true);
+        map.set_blocked(3, 3, true);
+        map.set_cost(3, 3, Some(6));
+        map.set_cost(5, 0, Some(4));
+        map.set_cost(6, 2, Some(25));
+        let (start, goal) = (Position(0, 0), Position(6, 3));
+
+        let text = MapFile::format(&map, start, goal);
+        let parsed = MapFile::parse(&text).unwrap();
+        assert_eq!((parsed.start, parsed.goal), (start, goal));
+        assert_eq!((parsed.map.width, parsed.map.height), (7, 4));
+        assert_eq!(parsed.map.blocked, map.blocked);
+        assert_eq!(parsed.map.costs, map.costs);
+        assert_eq!(MapFile::format(&parsed.map, start, goal), text);
+    }
+
+    #[test]
+    fn writes_the_top_row_first() {
+        let mut map = Map::new(3, 2, true);
+        map.set_blocked(0, 1, true);
+        map.set_cost(2, 0, Some(3));
+        map.set_cost(0, 1, Some(12));
+        let text = MapFile::format(&map, Position(1, 0), Position(1, 1));
+        assert_eq!(
+            text,
+            "size 3 2\nstart 1 0\ngoal 1 1\nmap\n#..\n..3\ncost 0 1 12\n"
+        );
+    }
+
+    #[test]
+    fn rejects_broken_files() {
+        let parse = |text: &str| MapFile::parse(text).unwrap_err().to_string();
+        assert_eq!(parse("start 0 0\ngoal 1 0\nmap\n.."), "missing `size`");
+        assert_eq!(
+            parse("size 2 1\nstart 0 0\ngoal 2 0\nmap\n.."),
+            "start and goal must be inside the map"
+        );
+        assert_eq!(
+            parse("size 2 1\nstart 0 0\ngoal 1 0\nmap\n.x"),
+            "line 5: unknown tile `x`"
+        );
+        assert_eq!(
+            parse("size 2 1\nstart 0 0\ngoal 1 0\nmap\n...\n"),
+            "line 5: expected 2 tiles"
+        );
+        assert_eq!(
+            parse("size 2 1\nstart 0 0\ngoal 1 0\nmap\n..\ncost 3 0 4"),
+            "line 6: cost outside the map or below 1"
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use std::{fs, path::Path};
+
+use crate::{
+    benchmark::skip_unprepared,
+    map_file::{parse_error, parse_number},
+    repeatable_pathfinder, CornerCutting, CostModel, Map, MapFileError, PathfindingAlgorithm,
+    Position, SearchResult, DEFAULT_CLUSTER_SIZE,
+};
+
+/// How far a path may be from the published optimal length and still match. `DIAGONAL_COST`
+/// rounds sqrt(2) down by 0.3%, so a path that is optimal for our costs can be that much longer.
+pub const LENGTH_TOLERANCE: f64 = 0.003;
+
+/// Loader for the grid maps of the Moving AI Lab benchmarks
+/// (<https://movingai.com/benchmarks/formats.html>):
+///
+/// ```text
+/// type octile
+/// height 4
+/// width 8
+/// map
+/// @@@@@@@@
+/// @..T...@
+/// @..S.W.@
+/// @@@@@@@@
+/// ```
+///
+/// `.` and `G` are open ground and `S` (swamp) is walkable as well, all with cost 1 since the
+/// published lengths treat them alike. `@` and `O` (out of bounds), `T` (trees) and `W` (water,
+/// which can not be entered from ground) are blocked. Rows go top to bottom, the first one
+/// becomes `y = height - 1`.
+///
+/// The benchmarks price diagonals at sqrt(2) and never cut corners, so the map is set to
+/// `CostModel::Octile` and `CornerCutting::BothSidesOpen`.
+pub struct MovingAiMap;
+
+impl MovingAiMap {
+    /// Reads a `.map` file and builds the JPS+ jumps and HPA* clusters for it.
+    ///
+    /// # Errors
+    ///
+    /// `MapFileError::Io` when the file cannot be read, otherwise the errors of
+    /// `MovingAiMap::parse`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Map, MapFileError> {
+        let mut map = MovingAiMap::parse(&fs::read_to_string(path)?)?;
+        map.build_jump_table();
+        map.build_hierarchy(DEFAULT_CLUSTER_SIZE);
+        Ok(map)
+    }
+
+    /// Parses the `type`, `height` and `width` header and the rows of a `.map` file.
+    ///
+    /// # Errors
+    ///
+    /// `MapFileError::Parse` for a line that does not follow the format, and
+    /// `MapFileError::Invalid` when the size is missing or a row is short.
+    pub fn parse(text: &str) -> Result<Map, MapFileError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim_end()))
+            .filter(|(_, line)| !line.is_empty());
+
+        let mut width = None;
+        let mut height = None;
+        for (number, line) in lines.by_ref() {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("type") => {}
+                Some("width") => width = Some(parse_number(number, words.next())?),
+                Some("height") => height = Some(parse_number(number, words.next())?),
+                Some("map") => break,
+                _ => return Err(parse_error(number, format!("unexpected `{line}`"))),
+            }
+        }
+
+        let width = width.ok_or_else(|| MapFileError::Invalid("missing `width`".to_string()))?;
+        let height = height.ok_or_else(|| MapFileError::Invalid("missing `height`".to_string()))?;
+        if width <= 0 || height <= 0 {
+            return Err(MapFileError::Invalid(
+                "the size must be positive".to_string(),
+            ));
+        }
+
+        let mut map = Map::new(width, height, true);
+        map.cost_model = CostModel::Octile;
+        map.corner_cutting = CornerCutting::BothSidesOpen;
+        for y in (0..height).rev() {
+            let (number, row) = lines
+                .next()
+                .ok_or_else(|| MapFileError::Invalid(format!("expected {height} rows")))?;
+            if row.chars().count() != width as usize {
+                return Err(parse_error(number, format!("expected {width} tiles")));
+            }
+            for (x, tile) in row.chars().enumerate() {
+                let index = map.xy_idx(x as i32, y);
+                match tile {
+                    '.' | 'G' | 'S' => {}
+                    '@' | 'O' | 'T' | 'W' => map.blocked[index] = true,
+                    _ => return Err(parse_error(number, format!("unknown tile `{tile}`"))),
+                }
+            }
+        }
+        Ok(map)
+    }
+}
+
+/// One line of a Moving AI `.scen` file: a start and goal on a map, with the length of the
+/// shortest path between them.
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub bucket: u32,
+    /// The `.map` file the scenario was made for.
+    pub map: String,
+    pub start: Position,
+    pub goal: Position,
+    pub optimal_length: f64,
+}
+
+impl Scenario {
+    /// Reads every scenario of a `.scen` file.
+    ///
+    /// # Errors
+    ///
+    /// `MapFileError::Io` when the file cannot be read, otherwise the errors of
+    /// `Scenario::parse`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Vec<Scenario>, MapFileError> {
+        Scenario::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the `version 1` format: a header line, then one tab separated
+    /// `bucket map width height start_x start_y goal_x goal_y optimal_length` line per
+    /// scenario. Coordinates count rows from the top, they are flipped to match `Map`.
+    ///
+    /// # Errors
+    ///
+    /// `MapFileError::Parse` for a line without nine fields or with a field that is not a number.
+    pub fn parse(text: &str) -> Result<Vec<Scenario>, MapFileError> {
+        let mut scenarios = vec![];
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with("version") {
+                continue;
+            }
+            let words: Vec<&str> = line.split('\t').collect();
+            if words.len() != 9 {
+                return Err(parse_error(number, "expected 9 tab separated fields"));
+            }
+            let bucket = words[0]
+                .parse()
+                .map_err(|_| parse_error(number, "expected a bucket number"))?;
+            let height = parse_number(number, Some(words[3]))?;
+            let start_x = parse_number(number, Some(words[4]))?;
+            let start_y = parse_number(number, Some(words[5]))?;
+            let goal_x = parse_number(number, Some(words[6]))?;
+            let goal_y = parse_number(number, Some(words[7]))?;
+            let optimal_length = words[8]
+                .parse()
+                .map_err(|_| parse_error(number, "expected a length"))?;
+            scenarios.push(Scenario {
+                bucket,
+                map: words[1].to_string(),
+                start: Position(start_x, height - 1 - start_y),
+                goal: Position(goal_x, height - 1 - goal_y),
+                optimal_length,
+            });
+        }
+        Ok(scenarios)
+    }
+}
+
+/// How one algorithm did on one scenario.
+#[derive(Debug)]
+pub struct ScenarioResult {
+    pub algorithm: PathfindingAlgorithm,
+    /// Index into the scenarios that were run.
+    pub scenario: usize,
+    /// Length of the path found, with diagonals counting sqrt(2) and any-angle lines their
+    /// Euclidean length.
+    pub length: Option<f64>,
+    /// Whether `length` is the published optimal length, within `LENGTH_TOLERANCE`.
+    pub optimal: bool,
+    pub result: SearchResult,
+}
+
+/// Solves every scenario with every algorithm in `algorithms`. HPA* is skipped on a map without a
+/// hierarchy, which it would build for every scenario, and ARA* runs without a time budget, see
+/// `repeatable_pathfinder`.
+#[must_use]
+pub fn run_scenarios(
+    map: &Map,
+    scenarios: &[Scenario],
+    algorithms: &[PathfindingAlgorithm],
+) -> Vec<ScenarioResult> {
+    let mut results = vec![];
+    for &algorithm in skip_unprepared(map, algorithms) {
+        for (index, scenario) in scenarios.iter().enumerate() {
+            let result =
+                repeatable_pathfinder(algorithm).find_path(map, scenario.start, scenario.goal);
+            let length = result.path.as_deref().map(euclidean_length);
+            let optimal = length.is_some_and(|length| {
+                (length - scenario.optimal_length).abs()
+                    <= scenario.optimal_length * LENGTH_TOLERANCE + 1e-6
+            });
+            results.push(ScenarioResult {
+                algorithm,
+                scenario: index,
+                length,
+                optimal,
+                result,
+            });
+        }
+    }
+    results
+}
+
+/// Length of a path whose segments are straight lines at any angle, as Theta* returns. Diagonal
+/// steps count sqrt(2), like in the optimal lengths of the scenarios.
+#[must_use]
+pub fn euclidean_length(path: &[Position]) -> f64 {
+    path.windows(2)
+        .map(|pair| {
+            let dx = f64::from(pair[1].x() - pair[0].x());
+            let dy = f64::from(pair[1].y() - pair[0].y());
+            dx.hypot(dy)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::SQRT_2;
+
+    use super::*;
+
+    const MAP: &str = "type octile\nheight 4\nwidth 6\nmap\n@@@@@@\n@..T.@\n@.S.W@\n@@@@@@\n";
+
+    #[test]
+    fn parses_the_map_top_row_first() {
+        let map = MovingAiMap::parse(MAP).unwrap();
+        assert_eq!((map.width, map.height), (6, 4));
+        assert_eq!(map.cost_model, CostModel::Octile);
+        assert_eq!(map.corner_cutting, CornerCutting::BothSidesOpen);
+        // the second row of the file is y = 2, the third y = 1
+        assert!(map.is_blocked(3, 2) && map.is_blocked(4, 1));
+        assert!(!map.is_blocked(1, 2) && !map.is_blocked(2, 1));
+        assert!((0..6).all(|x| map.is_blocked(x, 0) && map.is_blocked(x, 3)));
+    }
+
+    #[test]
+    fn rejects_broken_maps() {
+        let error = |text: &str| MovingAiMap::parse(text).unwrap_err().to_string();
+        assert_eq!(error("type octile\nwidth 2\nmap\n..\n"), "missing `height`");
+        assert_eq!(
+            error("type octile\nheight 1\nwidth 2\nmap\n.X\n"),
+            "line 5: unknown tile `X`"
+        );
+        assert_eq!(
+            error("type octile\nheight 2\nwidth 2\nmap\n..\n"),
+            "expected 2 rows"
+        );
+    }
+
+    #[test]
+    fn flips_scenario_rows() {
+        let text = "version 1\n\
+                    0\tsmall.map\t6\t4\t1\t1\t4\t2\t3.41421356\n\
+                    3\tsmall.map\t6\t4\t2\t2\t2\t2\t0\n";
+        let scenarios = Scenario::parse(text).unwrap();
+        assert_eq!(scenarios.len(), 2);
+        assert_eq!(scenarios[0].bucket, 0);
+        assert_eq!(scenarios[0].map, "small.map");
+        assert_eq!(scenarios[0].start, Position(1, 2));
+        assert_eq!(scenarios[0].goal, Position(4, 1));
+        assert!((scenarios[0].optimal_length - (2.0 + SQRT_2)).abs() < 1e-6);
+        assert_eq!(scenarios[1].bucket, 3);
+        assert_eq!(scenarios[1].start, Position(2, 1));
+    }
+
+    #[test]
+    fn rejects_broken_scenarios() {
+        let error = |text: &str| Scenario::parse(text).unwrap_err().to_string();
+        assert_eq!(
+            error("version 1\n0\tsmall.map\t6\t4\t1\t1\t4\t2\n"),
+            "line 2: expected 9 tab separated fields"
+        );
+        assert_eq!(
+            error("version 1\n0\tsmall.map\t6\t4\t1\tone\t4\t2\t3\n"),
+            "line 2: expected a number"
+        );
+    }
+
+    #[test]
+    fn solves_scenarios_at_their_optimal_length() {
+        let mut map = MovingAiMap::parse(MAP).unwrap();
+        map.build_hierarchy(DEFAULT_CLUSTER_SIZE);
+        let scenarios =
+            Scenario::parse("version 1\n0\tsmall.map\t6\t4\t1\t1\t3\t2\t2.41421356\n").unwrap();
+        let results = run_scenarios(&map, &scenarios, &PathfindingAlgorithm::ALL);
+        assert_eq!(results.len(), PathfindingAlgorithm::ALL.len());
+        for result in results {
+            let length = result.length.unwrap();
+            if result.algorithm.any_angle() {
+                // straight lines at any angle can beat the octile length
+                assert!(
+                    length <= scenarios[0].optimal_length,
+                    "{:?}",
+                    result.algorithm
+                );
+            } else {
+                assert!(result.optimal, "{:?}: {length}", result.algorithm);
+            }
+        }
+    }
+}
@@ -0,0 +1,660 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use crate::{solve, Map, PathfindingAlgorithm, Position, STRAIGHT_COST};
+
+/// High-level nodes CBS may expand before it gives up.
+pub const CBS_NODE_LIMIT: usize = 5_000;
+
+/// How several agents are planned so that they never collide.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MultiAgentPlanner {
+    /// Conflict-Based Search: optimal for the sum of costs, but can take long on crowded maps.
+    Cbs,
+    /// Cooperative A*: agents are planned one after the other around a reservation table of the
+    /// ones before them. Fast, but neither optimal nor sure to find a plan when one exists.
+    CooperativeAStar,
+}
+
+impl MultiAgentPlanner {
+    pub const ALL: [MultiAgentPlanner; 2] =
+        [MultiAgentPlanner::Cbs, MultiAgentPlanner::CooperativeAStar];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            MultiAgentPlanner::Cbs => "CBS",
+            MultiAgentPlanner::CooperativeAStar => "Coop A*",
+        }
+    }
+
+    #[must_use]
+    pub fn next(self) -> MultiAgentPlanner {
+        let index = Self::ALL
+            .iter()
+            .position(|planner| *planner == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Two agents that would run into each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Conflict {
+    /// Agents `a` and `b` are on `position` at `time`.
+    Vertex {
+        a: usize,
+        b: usize,
+        position: Position,
+        time: usize,
+    },
+    /// Agent `a` moves from `from` to `to` between `time` and `time + 1` while `b` moves the other
+    /// way, so they would swap through each other.
+    Edge {
+        a: usize,
+        b: usize,
+        from: Position,
+        to: Position,
+        time: usize,
+    },
+    /// Agents `a` and `b` move along the two diagonals of the same square of four tiles between
+    /// `time` and `time + 1`, so they would run into each other in its middle.
+    Crossing {
+        a: usize,
+        b: usize,
+        a_move: (Position, Position),
+        b_move: (Position, Position),
+        time: usize,
+    },
+}
+
+/// A plan for every agent, in the order they were given.
+#[derive(Clone, Debug, Default)]
+pub struct MultiAgentSolution {
+    /// Where each agent is at every time step, waits included, from its start to its goal.
+    /// Agents stay on their goal once they are done.
+    pub paths: Vec<Vec<Position>>,
+    /// Sum of the costs of all paths, waiting costs `STRAIGHT_COST` per step.
+    pub cost: i32,
+    /// Time steps until the last agent is on its goal.
+    pub makespan: usize,
+    /// Nodes the space-time searches of single agents expanded.
+    pub expanded: usize,
+    /// Constraint tree nodes CBS expanded, 0 for Cooperative A*.
+    pub high_level_expanded: usize,
+    pub elapsed: Duration,
+}
+
+/// Where an agent following `path` is at `time`, on its goal once the path is over.
+#[must_use]
+pub fn position_at(path: &[Position], time: usize) -> Option<Position> {
+    path.get(time).or_else(|| path.last()).copied()
+}
+
+/// Whether moving from `a_from` to `a_to` and from `b_from` to `b_to` in the same step crosses the
+/// diagonals of one square of four tiles.
+fn crosses(a_from: Position, a_to: Position, b_from: Position, b_to: Position) -> bool {
+    let (dx, dy) = (a_to.x() - a_from.x(), a_to.y() - a_from.y());
+    if dx == 0 || dy == 0 {
+        return false;
+    }
+    let corners = (
+        Position(a_from.x() + dx, a_from.y()),
+        Position(a_from.x(), a_from.y() + dy),
+    );
+    (b_from, b_to) == corners || (b_to, b_from) == corners
+}
+
+/// The first time two of `paths` share a tile, swap tiles or cross diagonally, if they ever do.
+#[must_use]
+pub fn first_conflict(paths: &[Vec<Position>]) -> Option<Conflict> {
+    let makespan = paths.iter().map(Vec::len).max().unwrap_or(0);
+    for time in 0..makespan {
+        for a in 0..paths.len() {
+            for b in a + 1..paths.len() {
+                let (a_now, b_now) = (position_at(&paths[a], time), position_at(&paths[b], time));
+                if a_now.is_some() && a_now == b_now {
+                    return Some(Conflict::Vertex {
+                        a,
+                        b,
+                        position: a_now?,
+                        time,
+                    });
+                }
+                let (a_next, b_next) = (
+                    position_at(&paths[a], time + 1),
+                    position_at(&paths[b], time + 1),
+                );
+                if a_now != a_next && a_now == b_next && a_next == b_now {
+                    return Some(Conflict::Edge {
+                        a,
+                        b,
+                        from: a_now?,
+                        to: a_next?,
+                        time,
+                    });
+                }
+                if let (Some(a_now), Some(a_next), Some(b_now), Some(b_next)) =
+                    (a_now, a_next, b_now, b_next)
+                {
+                    if crosses(a_now, a_next, b_now, b_next) {
+                        return Some(Conflict::Crossing {
+                            a,
+                            b,
+                            a_move: (a_now, a_next),
+                            b_move: (b_now, b_next),
+                            time,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Plans paths from every `(start, goal)` pair with `planner`, so that no two agents are ever on
+/// the same tile, swap tiles or cross diagonally. Every move takes one time step; an agent can also
+/// wait.
+#[must_use]
+pub fn plan_agents(
+    map: &Map,
+    agents: &[(Position, Position)],
+    planner: MultiAgentPlanner,
+) -> Option<MultiAgentSolution> {
+    let now = Instant::now();
+    let starts: HashSet<Position> = agents.iter().map(|(start, _)| *start).collect();
+    let goals: HashSet<Position> = agents.iter().map(|(_, goal)| *goal).collect();
+    if starts.len() < agents.len() || goals.len() < agents.len() {
+        return None;
+    }
+    for &(start, goal) in agents {
+        solve(map, PathfindingAlgorithm::AStar, start, goal).path?;
+    }
+    let mut solution = match planner {
+        MultiAgentPlanner::Cbs => cbs(map, agents),
+        MultiAgentPlanner::CooperativeAStar => cooperative_a_star(map, agents),
+    }?;
+    solution.makespan = solution
+        .paths
+        .iter()
+        .map(|path| path.len().saturating_sub(1))
+        .max()
+        .unwrap_or(0);
+    solution.elapsed = now.elapsed();
+    Some(solution)
+}
+
+/// Cost of going from `path[t]` to `path[t + 1]` for every step, waits priced `STRAIGHT_COST`.
+fn timed_path_cost(map: &Map, path: &[Position]) -> i32 {
+    path.windows(2)
+        .map(|step| {
+            if step[0] == step[1] {
+                STRAIGHT_COST
+            } else {
+                map.step_cost(&step[0], &step[1])
+            }
+        })
+        .sum()
+}
+
+/// What a single agent's space-time search may not do.
+trait Restrictions {
+    /// Whether moving (or waiting, when `from == to`) from `from` at `time` to `to` at `time + 1`
+    /// is forbidden.
+    fn forbids(&self, from: Position, to: Position, time: usize) -> bool;
+
+    /// The last time anyone else needs `goal`, the agent may only stop there after it.
+    fn goal_needed_until(&self, goal: Position) -> Option<usize>;
+
+    /// The time after which nothing is forbidden that was not forbidden before.
+    fn latest(&self) -> usize;
+}
+
+/// A* over `(tile, time)` from `start` to `goal`, where the agent can also wait a step. Returns
+/// the path, one tile per time step, and how many nodes were expanded.
+///
+/// Past `Restrictions::latest` the restrictions no longer change, so all later times are folded
+/// into one layer, which keeps the search finite when the goal cannot be reached.
+fn space_time_a_star(
+    map: &Map,
+    start: Position,
+    goal: Position,
+    restrictions: &dyn Restrictions,
+) -> (Option<Vec<Position>>, usize) {
+    let mut expanded = 0;
+    if map.is_blocked(start.x(), start.y()) || map.is_blocked(goal.x(), goal.y()) {
+        return (None, expanded);
+    }
+    let earliest_stop = restrictions
+        .goal_needed_until(goal)
+        .map_or(0, |time| time + 1);
+    let last_layer = restrictions.latest().max(earliest_stop) + 1;
+
+    let mut open = BinaryHeap::new();
+    let mut g: HashMap<(Position, usize), i32> = HashMap::new();
+    let mut parents: HashMap<(Position, usize), (Position, usize)> = HashMap::new();
+    let mut closed: HashSet<(Position, usize)> = HashSet::new();
+    g.insert((start, 0), 0);
+    open.push(Reverse((map.heuristic(&start, &goal), 0, start, 0)));
+
+    while let Some(Reverse((_, cost, node, time))) = open.pop() {
+        if !closed.insert((node, time)) {
+            continue;
+        }
+        expanded += 1;
+        if node == goal && time >= earliest_stop {
+            let mut path = vec![node];
+            let mut key = (node, time);
+            while let Some(&parent) = parents.get(&key) {
+                path.push(parent.0);
+                key = parent;
+            }
+            path.reverse();
+            return (Some(path), expanded);
+        }
+
+        let moves = map
+            .get_successors(&node)
+            .into_iter()
+            .map(|successor| (successor.position, successor.cost))
+            .chain(std::iter::once((node, STRAIGHT_COST)));
+        for (next, step_cost) in moves {
+            let next_time = (time + 1).min(last_layer);
+            if restrictions.forbids(node, next, time) || closed.contains(&(next, next_time)) {
+                continue;
+            }
+            let next_cost = cost + step_cost;
+            let key = (next, next_time);
+            if g.get(&key).is_none_or(|best| next_cost < *best) {
+                g.insert(key, next_cost);
+                parents.insert(key, (node, time));
+                let f = next_cost + map.heuristic(&next, &goal);
+                open.push(Reverse((f, next_cost, next, next_time)));
+            }
+        }
+    }
+    (None, expanded)
+}
+
+/// A CBS constraint on one agent: it may not be on `to` at `time + 1` having come from `from`,
+/// or from anywhere when `from` is `None`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Constraint {
+    agent: usize,
+    from: Option<Position>,
+    to: Position,
+    time: usize,
+}
+
+struct AgentConstraints<'a> {
+    agent: usize,
+    constraints: &'a [Constraint],
+}
+
+impl Restrictions for AgentConstraints<'_> {
+    fn forbids(&self, from: Position, to: Position, time: usize) -> bool {
+        self.constraints.iter().any(|constraint| {
+            constraint.agent == self.agent
+                && constraint.to == to
+                && constraint.time == time
+                && constraint
+                    .from
+                    .is_none_or(|constraint_from| constraint_from == from)
+        })
+    }
+
+    fn goal_needed_until(&self, goal: Position) -> Option<usize> {
+        self.constraints
+            .iter()
+            .filter(|constraint| {
+                constraint.agent == self.agent && constraint.from.is_none() && constraint.to == goal
+            })
+            .map(|constraint| constraint.time + 1)
+            .max()
+    }
+
+    fn latest(&self) -> usize {
+        self.constraints
+            .iter()
+            .filter(|constraint| constraint.agent == self.agent)
+            .map(|constraint| constraint.time + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// A node of the CBS constraint tree.
+struct ConstraintNode {
+    constraints: Vec<Constraint>,
+    paths: Vec<Vec<Position>>,
+    cost: i32,
+}
+
+fn plan_constrained(
+    map: &Map,
+    agents: &[(Position, Position)],
+    agent: usize,
+    constraints: &[Constraint],
+    expanded: &mut usize,
+) -> Option<Vec<Position>> {
+    let (start, goal) = agents[agent];
+    let restrictions = AgentConstraints { agent, constraints };
+    let (path, nodes) = space_time_a_star(map, start, goal, &restrictions);
+    *expanded += nodes;
+    path
+}
+
+/// Conflict-Based Search: every agent is planned on its own, and whenever two collide the search
+/// branches on which of them has to avoid the tile (or the move) at that time, always expanding
+/// the branch with the lowest sum of costs first.
+fn cbs(map: &Map, agents: &[(Position, Position)]) -> Option<MultiAgentSolution> {
+    let mut solution = MultiAgentSolution::default();
+    let mut paths = Vec::with_capacity(agents.len());
+    for agent in 0..agents.len() {
+        paths.push(plan_constrained(
+            map,
+            agents,
+            agent,
+            &[],
+            &mut solution.expanded,
+        )?);
+    }
+    let cost = paths.iter().map(|path| timed_path_cost(map, path)).sum();
+
+    let mut nodes = vec![ConstraintNode {
+        constraints: Vec::new(),
+        paths,
+        cost,
+    }];
+    let mut open = BinaryHeap::new();
+    open.push(Reverse((cost, 0)));
+    while let Some(Reverse((_, index))) = open.pop() {
+        solution.high_level_expanded += 1;
+        if solution.high_level_expanded > CBS_NODE_LIMIT {
+            return None;
+        }
+        let Some(conflict) = first_conflict(&nodes[index].paths) else {
+            let node = nodes.swap_remove(index);
+            solution.paths = node.paths;
+            solution.cost = node.cost;
+            return Some(solution);
+        };
+        let children = match conflict {
+            Conflict::Vertex {
+                a,
+                b,
+                position,
+                time,
+            } => [a, b].map(|agent| Constraint {
+                agent,
+                from: None,
+                to: position,
+                // starts are all different, so a tile is never shared at time 0
+                time: time - 1,
+            }),
+            Conflict::Edge {
+                a,
+                b,
+                from,
+                to,
+                time,
+            } => [
+                Constraint {
+                    agent: a,
+                    from: Some(from),
+                    to,
+                    time,
+                },
+                Constraint {
+                    agent: b,
+                    from: Some(to),
+                    to: from,
+                    time,
+                },
+            ],
+            Conflict::Crossing {
+                a,
+                b,
+                a_move,
+                b_move,
+                time,
+            } => [(a, a_move), (b, b_move)].map(|(agent, (from, to))| Constraint {
+                agent,
+                from: Some(from),
+                to,
+                time,
+            }),
+        };
+        for constraint in children {
+            let mut constraints = nodes[index].constraints.clone();
+            constraints.push(constraint);
+            let agent = constraint.agent;
+            if let Some(path) =
+                plan_constrained(map, agents, agent, &constraints, &mut solution.expanded)
+            {
+                let mut paths = nodes[index].paths.clone();
+                paths[agent] = path;
+                let cost = paths.iter().map(|path| timed_path_cost(map, path)).sum();
+                open.push(Reverse((cost, nodes.len())));
+                nodes.push(ConstraintNode {
+                    constraints,
+                    paths,
+                    cost,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Tiles and moves taken by the agents planned so far, for Cooperative A*.
+#[derive(Default)]
+struct ReservationTable {
+    tiles: HashSet<(Position, usize)>,
+    /// `(from, to, time)` of every move from `time` to `time + 1`.
+    moves: HashSet<(Position, Position, usize)>,
+    /// Goals of finished agents, taken from the time they stop there on.
+    parked: HashMap<Position, usize>,
+    /// The last time each tile is reserved, while passing through.
+    last_use: HashMap<Position, usize>,
+}
+
+impl ReservationTable {
+    fn reserve(&mut self, path: &[Position]) {
+        for (time, position) in path.iter().enumerate() {
+            self.tiles.insert((*position, time));
+            let last = self.last_use.entry(*position).or_insert(time);
+            *last = (*last).max(time);
+        }
+        for (time, step) in path.windows(2).enumerate() {
+            self.moves.insert((step[0], step[1], time));
+        }
+        if let Some(goal) = path.last() {
+            self.parked.insert(*goal, path.len() - 1);
+        }
+    }
+}
+
+impl Restrictions for ReservationTable {
+    fn forbids(&self, from: Position, to: Position, time: usize) -> bool {
+        // the other diagonal of the square a diagonal move goes through
+        let (side, other_side) = (Position(to.x(), from.y()), Position(from.x(), to.y()));
+        let diagonal = from.x() != to.x() && from.y() != to.y();
+        self.tiles.contains(&(to, time + 1))
+            || self.moves.contains(&(to, from, time))
+            || (diagonal
+                && (self.moves.contains(&(side, other_side, time))
+                    || self.moves.contains(&(other_side, side, time))))
+            || self
+                .parked
+                .get(&to)
+                .is_some_and(|parked| *parked <= time + 1)
+    }
+
+    fn goal_needed_until(&self, goal: Position) -> Option<usize> {
+        self.last_use.get(&goal).copied()
+    }
+
+    fn latest(&self) -> usize {
+        self.last_use.values().copied().max().unwrap_or(0)
+    }
+}
+
+/// Cooperative A*: plans the agents in order, each avoiding the tiles and moves the ones before
+/// it reserved, then reserves its own.
+fn cooperative_a_star(map: &Map, agents: &[(Position, Position)]) -> Option<MultiAgentSolution> {
+    let mut solution = MultiAgentSolution::default();
+    let mut table = ReservationTable::default();
+    for &(start, goal) in agents {
+        // one of the agents before went through this one's start at time 0
+        if table.tiles.contains(&(start, 0)) {
+            return None;
+        }
+        let (path, nodes) = space_time_a_star(map, start, goal, &table);
+        solution.expanded += nodes;
+        let path = path?;
+        table.reserve(&path);
+        solution.cost += timed_path_cost(map, &path);
+        solution.paths.push(path);
+    }
+    Some(solution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{random_pairs, solve, PathfindingAlgorithm};
+
+    fn assert_plan(map: &Map, agents: &[(Position, Position)], solution: &MultiAgentSolution) {
+        assert_eq!(first_conflict(&solution.paths), None);
+        assert_eq!(solution.paths.len(), agents.len());
+        for (path, (start, goal)) in solution.paths.iter().zip(agents) {
+            assert_eq!((path.first(), path.last()), (Some(start), Some(goal)));
+            for step in path.windows(2) {
+                let (dx, dy) = (step[1].x() - step[0].x(), step[1].y() - step[0].y());
+                assert!(step[0] == step[1] || map.can_move(step[0].x(), step[0].y(), dx, dy));
+            }
+        }
+    }
+
+    /// A corridor one tile high, with an open tile below its middle when `pocket` is set.
+    fn corridor(pocket: bool) -> Map {
+        let mut map = Map::new(5, 3, true);
+        for x in 0..5 {
+            map.set_blocked(x, 0, x != 2 || !pocket);
+            map.set_blocked(x, 2, true);
+        }
+        map
+    }
+
+    #[test]
+    fn plans_have_no_conflicts() {
+        for seed in 0..8 {
+            let map = Map::from_noise(24, 24, seed % 2 == 0);
+            let mut starts = HashSet::new();
+            let mut goals = HashSet::new();
+            let agents: Vec<_> = random_pairs(&map, 30, seed)
+                .into_iter()
+                .filter(|(start, goal)| {
+                    !starts.contains(goal)
+                        && !goals.contains(start)
+                        && starts.insert(*start)
+                        && goals.insert(*goal)
+                })
+                .take(2 + seed as usize % 4)
+                .collect();
+            let alone: i32 = agents
+                .iter()
+                .filter_map(|(start, goal)| {
+                    solve(&map, PathfindingAlgorithm::AStar, *start, *goal).cost
+                })
+                .sum();
+
+            let cbs = plan_agents(&map, &agents, MultiAgentPlanner::Cbs).unwrap();
+            assert_plan(&map, &agents, &cbs);
+            assert!(cbs.cost >= alone);
+            if let Some(cooperative) =
+                plan_agents(&map, &agents, MultiAgentPlanner::CooperativeAStar)
+            {
+                assert_plan(&map, &agents, &cooperative);
+                assert!(cbs.cost <= cooperative.cost);
+            }
+        }
+    }
+
+    #[test]
+    fn agents_pass_each_other_in_a_pocket() {
+        let agents = [
+            (Position(0, 1), Position(4, 1)),
+            (Position(4, 1), Position(0, 1)),
+        ];
+        let map = corridor(true);
+        let solution = plan_agents(&map, &agents, MultiAgentPlanner::Cbs).unwrap();
+        assert_plan(&map, &agents, &solution);
+
+        let map = corridor(false);
+        for planner in MultiAgentPlanner::ALL {
+            assert!(plan_agents(&map, &agents, planner).is_none());
+        }
+    }
+
+    #[test]
+    fn finds_swaps_and_shared_tiles() {
+        let swap = vec![
+            vec![Position(0, 0), Position(1, 0)],
+            vec![Position(1, 0), Position(0, 0)],
+        ];
+        assert!(matches!(
+            first_conflict(&swap),
+            Some(Conflict::Edge { time: 0, .. })
+        ));
+        // the first agent waits on its goal, where the second one ends up
+        let shared = vec![
+            vec![Position(1, 0)],
+            vec![Position(3, 0), Position(2, 0), Position(1, 0)],
+        ];
+        assert_eq!(
+            first_conflict(&shared),
+            Some(Conflict::Vertex {
+                a: 0,
+                b: 1,
+                position: Position(1, 0),
+                time: 2
+            })
+        );
+    }
+
+    #[test]
+    fn agents_do_not_cross_diagonally() {
+        let agents = [
+            (Position(0, 0), Position(1, 1)),
+            (Position(1, 0), Position(0, 1)),
+        ];
+        let straight = agents.map(|(start, goal)| vec![start, goal]).to_vec();
+        assert_eq!(
+            first_conflict(&straight),
+            Some(Conflict::Crossing {
+                a: 0,
+                b: 1,
+                a_move: agents[0],
+                b_move: agents[1],
+                time: 0
+            })
+        );
+
+        let map = Map::new(2, 2, true);
+        for planner in MultiAgentPlanner::ALL {
+            let solution = plan_agents(&map, &agents, planner).unwrap();
+            assert_plan(&map, &agents, &solution);
+        }
+    }
+}
+
+// References
+// 1. Sharon, Stern, Felner and Sturtevant, "Conflict-Based Search for Optimal Multi-Agent
+// Pathfinding", Artificial Intelligence 219 (2015)
+// 2. Silver, "Cooperative Pathfinding", AIIDE (2005)
@@ -0,0 +1,326 @@
+// Bevy hands system parameters over by value, and tile coordinates and step counts are far
+// smaller than where a cast to `f32` could lose a value.
+#![allow(clippy::needless_pass_by_value, clippy::cast_precision_loss)]
+
+use bevy::prelude::*;
+
+use super::{
+    first_conflict, index_to_world_position, line_sprite, plan_agents, position_at,
+    world_position_to_index, ClearEvent, CycleMultiAgentPlannerEvent, GameState, LoadMapEvent, Map,
+    Mouse, MultiAgentChangedEvent, MultiAgentPlanner, MultiAgentPlannerChangedEvent,
+    MultiAgentSolution, PlacementMode, PlanAgentsEvent, Position, UserInterfaceInteractionEvent,
+    COMPARISON_COLORS, STRAIGHT_COST, TILE_SIZE,
+};
+
+/// Time steps of a multi-agent plan played per second.
+pub const MULTI_AGENT_STEPS_PER_SECOND: f32 = 2.0;
+
+/// Steps the playback stays on the last one before it starts over.
+pub const MULTI_AGENT_PLAYBACK_PAUSE: f32 = 2.0;
+
+/// === Components ===
+/// Start, goal or path of a start/goal pair placed for multi-agent planning, or a planned agent.
+#[derive(Component)]
+pub struct MultiAgentMarker {}
+
+/// The agent of a multi-agent plan with this index, moved along its path by the playback.
+#[derive(Component)]
+pub struct MultiAgentSprite {
+    pub agent: usize,
+}
+
+/// === Resources ===
+/// The start/goal pairs placed with the Agents button, the plan found for them and how far its
+/// playback has got.
+pub struct MultiAgentPlayback {
+    /// Start and goal of every agent, the goal `None` until the second click.
+    pub pairs: Vec<(Position, Option<Position>)>,
+    pub planner: MultiAgentPlanner,
+    pub solution: Option<MultiAgentSolution>,
+    /// Time steps played, fractional between two of them.
+    pub time: f32,
+}
+
+impl Default for MultiAgentPlayback {
+    fn default() -> Self {
+        MultiAgentPlayback {
+            pairs: Vec::new(),
+            planner: MultiAgentPlanner::Cbs,
+            solution: None,
+            time: 0.0,
+        }
+    }
+}
+
+impl MultiAgentPlayback {
+    /// The pairs that have both a start and a goal.
+    #[must_use]
+    pub fn agents(&self) -> Vec<(Position, Position)> {
+        self.pairs
+            .iter()
+            .filter_map(|(start, goal)| Some((*start, (*goal)?)))
+            .collect()
+    }
+
+    /// Forgets the plan, it no longer matches the pairs or the map.
+    pub fn stop(&mut self) {
+        self.solution = None;
+        self.time = 0.0;
+    }
+}
+
+/// === Helper Functions ===
+#[must_use]
+pub fn multi_agent_color(agent: usize) -> Color {
+    COMPARISON_COLORS[agent % COMPARISON_COLORS.len()]
+}
+
+/// Where agent `agent` is drawn at `time`, between the tiles of the time steps either side.
+// `time` is never negative
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+#[must_use]
+pub fn multi_agent_world_position(solution: &MultiAgentSolution, agent: usize, time: f32) -> Vec2 {
+    let path = &solution.paths[agent];
+    let step = time.floor() as usize;
+    let (Some(from), Some(to)) = (position_at(path, step), position_at(path, step + 1)) else {
+        return Vec2::ZERO;
+    };
+    index_to_world_position(from.0, from.1).lerp(index_to_world_position(to.0, to.1), time.fract())
+}
+
+/// === Systems ===
+// The first click places a start, the next one its goal. Clicking a start again removes its pair,
+// unless another start is still waiting for its goal.
+pub fn multi_agent_placement_system(
+    mut user_interface_interaction_event_reader: EventReader<UserInterfaceInteractionEvent>,
+    mut multi_agent_changed_event_writer: EventWriter<MultiAgentChangedEvent>,
+    mouse: Res<Mouse>,
+    mouse_input: Res<Input<MouseButton>>,
+    game_state: Res<GameState>,
+    mut multi_agent: ResMut<MultiAgentPlayback>,
+    map: Res<Map>,
+) {
+    // This is a hack to prevent placement when buttons are clicked.
+    if user_interface_interaction_event_reader
+        .iter()
+        .next()
+        .is_some()
+    {
+        return;
+    }
+    if !matches!(game_state.placement_mode, PlacementMode::AgentPair)
+        || !mouse_input.just_pressed(MouseButton::Left)
+    {
+        return;
+    }
+    let (x, y) = world_position_to_index(mouse.world_position);
+    if map.outside(x, y) || map.is_blocked(x, y) {
+        return;
+    }
+    let clicked_position = Position(x, y);
+
+    let pairs = multi_agent.pairs.len();
+    let pending_goal = multi_agent
+        .pairs
+        .last()
+        .is_some_and(|(_, goal)| goal.is_none());
+    let clicked_start = multi_agent
+        .pairs
+        .iter()
+        .position(|(start, _)| *start == clicked_position);
+    if let Some(index) = clicked_start.filter(|index| !pending_goal || index + 1 == pairs) {
+        multi_agent.pairs.remove(index);
+    } else if clicked_start.is_some()
+        || multi_agent
+            .pairs
+            .iter()
+            .any(|(_, goal)| *goal == Some(clicked_position))
+    {
+        // every agent needs a start and a goal of its own
+        return;
+    } else if let Some(pair) = multi_agent.pairs.last_mut().filter(|pair| pair.1.is_none()) {
+        pair.1 = Some(clicked_position);
+    } else {
+        multi_agent.pairs.push((clicked_position, None));
+    }
+    multi_agent.stop();
+    multi_agent_changed_event_writer.send(MultiAgentChangedEvent {});
+}
+
+pub fn cycle_multi_agent_planner_system(
+    mut cycle_multi_agent_planner_event_reader: EventReader<CycleMultiAgentPlannerEvent>,
+    mut multi_agent_planner_changed_event_writer: EventWriter<MultiAgentPlannerChangedEvent>,
+    mut multi_agent_changed_event_writer: EventWriter<MultiAgentChangedEvent>,
+    mut multi_agent: ResMut<MultiAgentPlayback>,
+) {
+    for _ in cycle_multi_agent_planner_event_reader.iter() {
+        multi_agent.planner = multi_agent.planner.next();
+        multi_agent.stop();
+        multi_agent_planner_changed_event_writer.send(MultiAgentPlannerChangedEvent {});
+        multi_agent_changed_event_writer.send(MultiAgentChangedEvent {});
+    }
+}
+
+pub fn plan_agents_system(
+    mut plan_agents_event_reader: EventReader<PlanAgentsEvent>,
+    mut multi_agent_changed_event_writer: EventWriter<MultiAgentChangedEvent>,
+    mut multi_agent: ResMut<MultiAgentPlayback>,
+    map: Res<Map>,
+) {
+    for _ in plan_agents_event_reader.iter() {
+        let agents = multi_agent.agents();
+        multi_agent.stop();
+        multi_agent_changed_event_writer.send(MultiAgentChangedEvent {});
+        if agents.is_empty() {
+            println!("Place a start and a goal for every agent first");
+            continue;
+        }
+        let planner = multi_agent.planner;
+        let Some(solution) = plan_agents(&map, &agents, planner) else {
+            println!(
+                "{} found no plan for {} agents",
+                planner.name(),
+                agents.len()
+            );
+            continue;
+        };
+
+        println!(
+            "{} planned {} agents: cost {:.1}, {} steps, {} nodes expanded ({} constraint tree nodes) in {:?}",
+            planner.name(),
+            agents.len(),
+            solution.cost as f32 / STRAIGHT_COST as f32,
+            solution.makespan,
+            solution.expanded,
+            solution.high_level_expanded,
+            solution.elapsed
+        );
+        match first_conflict(&solution.paths) {
+            None => println!("No two agents share a tile, swap places or cross at any step"),
+            Some(conflict) => println!("The plan has a conflict: {conflict:?}"),
+        }
+        multi_agent.solution = Some(solution);
+    }
+}
+
+// Plays the plan one time step after the other, then starts over after a pause
+pub fn multi_agent_playback_system(
+    mut sprite_query: Query<(&MultiAgentSprite, &mut Transform)>,
+    mut multi_agent: ResMut<MultiAgentPlayback>,
+    time: Res<Time>,
+) {
+    let makespan = match &multi_agent.solution {
+        Some(solution) => solution.makespan as f32,
+        None => return,
+    };
+    multi_agent.time += time.delta_seconds() * MULTI_AGENT_STEPS_PER_SECOND;
+    if multi_agent.time > makespan + MULTI_AGENT_PLAYBACK_PAUSE {
+        multi_agent.time = 0.0;
+    }
+
+    let played = multi_agent.time.min(makespan);
+    if let Some(solution) = &multi_agent.solution {
+        for (sprite, mut transform) in &mut sprite_query {
+            let position = multi_agent_world_position(solution, sprite.agent, played);
+            transform.translation.x = position.x;
+            transform.translation.y = position.y;
+        }
+    }
+}
+
+// A square on every start, a frame around every goal, and once planned each agent's path and the
+// agent itself in its colour
+pub fn draw_multi_agent(
+    mut multi_agent_changed_event_reader: EventReader<MultiAgentChangedEvent>,
+    marker_query: Query<Entity, With<MultiAgentMarker>>,
+    multi_agent: Res<MultiAgentPlayback>,
+    mut commands: Commands,
+) {
+    if multi_agent_changed_event_reader.iter().last().is_none() {
+        return;
+    }
+    for entity in marker_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let tile = TILE_SIZE as f32;
+    for (agent, (start, goal)) in multi_agent.pairs.iter().enumerate() {
+        let color = multi_agent_color(agent);
+        let center = index_to_world_position(start.0, start.1);
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(tile * 0.3)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(center.x, center.y, 2.5),
+                ..default()
+            })
+            .insert(MultiAgentMarker {});
+
+        if let Some(goal) = goal {
+            let center = index_to_world_position(goal.0, goal.1);
+            let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                .map(|(x, y)| center + Vec2::new(x, y) * tile * 0.35);
+            for side in 0..corners.len() {
+                let next = corners[(side + 1) % corners.len()];
+                commands
+                    .spawn_bundle(line_sprite(corners[side], next, 3.0, color, 2.5))
+                    .insert(MultiAgentMarker {});
+            }
+        }
+    }
+
+    let Some(solution) = &multi_agent.solution else {
+        return;
+    };
+    for (agent, path) in solution.paths.iter().enumerate() {
+        let color = multi_agent_color(agent);
+        for step in path.windows(2).filter(|step| step[0] != step[1]) {
+            commands
+                .spawn_bundle(line_sprite(
+                    index_to_world_position(step[0].0, step[0].1),
+                    index_to_world_position(step[1].0, step[1].1),
+                    2.0,
+                    color,
+                    2.0,
+                ))
+                .insert(MultiAgentMarker {});
+        }
+
+        let position = multi_agent_world_position(solution, agent, multi_agent.time);
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(tile * 0.6)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(position.x, position.y, 4.0),
+                ..default()
+            })
+            .insert(Name::new("Planned Agent"))
+            .insert(MultiAgentMarker {})
+            .insert(MultiAgentSprite { agent });
+    }
+}
+
+// The plan no longer fits a map that was painted on, and Clear and Load remove the pairs too
+pub fn reset_multi_agent_system(
+    mut clear_event_reader: EventReader<ClearEvent>,
+    mut load_map_event_reader: EventReader<LoadMapEvent>,
+    mut multi_agent_changed_event_writer: EventWriter<MultiAgentChangedEvent>,
+    mut multi_agent: ResMut<MultiAgentPlayback>,
+    map: Res<Map>,
+) {
+    let cleared = clear_event_reader.iter().last().is_some();
+    let loaded = load_map_event_reader.iter().last().is_some();
+    if cleared || loaded {
+        multi_agent.pairs = Vec::new();
+    } else if !map.is_changed() || multi_agent.solution.is_none() {
+        return;
+    }
+    multi_agent.stop();
+    multi_agent_changed_event_writer.send(MultiAgentChangedEvent {});
+}
@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    BestFirstSearch, ExpandedNode, GridExpander, IncrementalPlanner, Map, Position, SearchSteps,
+};
+
+/// Everything a search produced, in a form every algorithm can fill in.
+#[derive(Clone, Debug, Default)]
+pub struct SearchResult {
+    /// The path from start to goal (both included), if one was found.
+    pub path: Option<Vec<Position>>,
+    /// Total cost of `path` according to `Map::get_successors`, or its Euclidean length for
+    /// any-angle searches.
+    pub cost: Option<i32>,
+    /// For searches that give up optimality for speed, how many times the optimal cost the path
+    /// may cost at most.
+    pub bound: Option<f64>,
+    /// Nodes whose successors were generated, in expansion order.
+    pub expanded: Vec<Position>,
+    /// `g`, `h` and parent of every node in `expanded`, in the same order.
+    pub expanded_nodes: Vec<ExpandedNode>,
+    /// Nodes produced as successors (may contain duplicates).
+    pub generated: Vec<Position>,
+    /// Wall clock time spent inside the search.
+    pub elapsed: Duration,
+}
+
+impl SearchResult {
+    #[must_use]
+    pub fn expanded_count(&self) -> usize {
+        self.expanded.len()
+    }
+
+    #[must_use]
+    pub fn generated_count(&self) -> usize {
+        self.generated.len()
+    }
+}
+
+/// A single-agent search over a `Map`.
+///
+/// Implementations only provide `steps`; `search` runs it to the end and `find_path` wraps that
+/// with timing so every algorithm reports `elapsed` the same way.
+pub trait Pathfinder {
+    /// Display name, used by the UI and by reports.
+    fn name(&self) -> &'static str;
+
+    /// Whether the algorithm takes `Map::costs` into account.
+    fn uses_costs(&self) -> bool {
+        true
+    }
+
+    /// Whether the path is waypoints joined by straight lines at any angle, rather than tiles
+    /// that follow each other.
+    fn any_angle(&self) -> bool {
+        false
+    }
+
+    /// Whether the heuristic is inflated by a weight, so paths may cost more than the optimal
+    /// one.
+    fn weighted(&self) -> bool {
+        false
+    }
+
+    /// For incremental searches, a planner that keeps its state so the path can be repaired
+    /// after tiles change instead of searched again.
+    fn incremental(
+        &self,
+        _map: &Map,
+        _start: Position,
+        _goal: Position,
+    ) -> Option<IncrementalPlanner> {
+        None
+    }
+
+    /// Starts a search that expands one node per `SearchSteps::step`.
+    fn steps(&self, map: &Map, start: Position, goal: Position) -> Box<dyn SearchSteps>;
+
+    fn search(&self, map: &Map, start: Position, goal: Position) -> SearchResult {
+        let mut steps = self.steps(map, start, goal);
+        while !steps.step(map).is_done() {}
+        steps.into_result(map)
+    }
+
+    fn find_path(&self, map: &Map, start: Position, goal: Position) -> SearchResult {
+        let now = Instant::now();
+        let mut result = self.search(map, start, goal);
+        result.elapsed = now.elapsed();
+        result
+    }
+}
+
+pub struct AStar;
+
+impl Pathfinder for AStar {
+    fn name(&self) -> &'static str {
+        "AStar"
+    }
+
+    fn steps(&self, map: &Map, start: Position, goal: Position) -> Box<dyn SearchSteps> {
+        let expander = GridExpander {
+            uses_heuristic: true,
+            uses_costs: true,
+        };
+        Box::new(BestFirstSearch::new(map, expander, start, goal))
+    }
+}
+
+pub struct Bfs;
+
+impl Pathfinder for Bfs {
+    fn name(&self) -> &'static str {
+        "BFS"
+    }
+
+    fn uses_costs(&self) -> bool {
+        false
+    }
+
+    fn steps(&self, map: &Map, start: Position, goal: Position) -> Box<dyn SearchSteps> {
+        let expander = GridExpander {
+            uses_heuristic: false,
+            uses_costs: false,
+        };
+        Box::new(BestFirstSearch::new(map, expander, start, goal))
+    }
+}
+
+pub struct Dijkstra;
+
+impl Pathfinder for Dijkstra {
+    fn name(&self) -> &'static str {
+        "Dijkstra"
+    }
+
+    fn steps(&self, map: &Map, start: Position, goal: Position) -> Box<dyn SearchSteps> {
+        let expander = GridExpander {
+            uses_heuristic: false,
+            uses_costs: true,
+        };
+        Box::new(BestFirstSearch::new(map, expander, start, goal))
+    }
+}
+
+// References
+// 1. Introduction to the A* Algorithm, Amit Patel
+// https://www.redblobgames.com/pathfinding/a-star/introduction.html
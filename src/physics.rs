@@ -1,11 +1,121 @@
+// Bevy hands system parameters over by value, and tile coordinates and sizes are far smaller than
+// where a cast between `u32`, `i32` and `f32` could lose a value.
+#![allow(
+    clippy::needless_pass_by_value,
+    clippy::cast_possible_wrap,
+    clippy::cast_precision_loss
+)]
+
 use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::TilePos;
 use bevy_rapier2d::prelude::*;
 
+use super::{Map, PathTile, TILE_SIZE};
+
 pub fn setup_physics(mut rapier_config: ResMut<RapierConfiguration>) {
     // Set Gravity to 0.0
     rapier_config.gravity = Vec2::ZERO;
 }
 
+/// === Components ===
+/// One of the four walls just outside the map, for a map of this size.
+#[derive(Component)]
+pub struct MapBorder {
+    pub width: i32,
+    pub height: i32,
+}
+
+/// === Helper Functions ===
+/// The fixed body and collider a blocked tile carries, the size of the tile.
+#[must_use]
+pub fn tile_collider() -> (RigidBody, Collider) {
+    (
+        RigidBody::Fixed,
+        Collider::cuboid(TILE_SIZE as f32 / 2.0, TILE_SIZE as f32 / 2.0),
+    )
+}
+
+/// === Systems ===
+// Only blocked tiles carry a collider, so agents bump into obstacles and walk over open tiles
+pub fn sync_tile_colliders_system(
+    tile_query: Query<(Entity, &TilePos, Option<&Collider>), With<PathTile>>,
+    map: Res<Map>,
+    mut commands: Commands,
+) {
+    if !map.is_changed() {
+        return;
+    }
+    for (entity, tile_position, collider) in tile_query.iter() {
+        let blocked = map.is_blocked(tile_position.x as i32, tile_position.y as i32);
+        if blocked && collider.is_none() {
+            commands.entity(entity).insert_bundle(tile_collider());
+        } else if !blocked && collider.is_some() {
+            commands
+                .entity(entity)
+                .remove::<RigidBody>()
+                .remove::<Collider>();
+        }
+    }
+}
+
+// A wall along every edge of the map keeps agents from being pushed off it. They are made again
+// only when a map of another size is loaded
+pub fn sync_border_colliders_system(
+    border_query: Query<(Entity, &MapBorder)>,
+    map: Res<Map>,
+    mut commands: Commands,
+) {
+    if !map.is_changed() {
+        return;
+    }
+    if border_query
+        .iter()
+        .any(|(_, border)| border.width == map.width && border.height == map.height)
+    {
+        return;
+    }
+    for (entity, _) in border_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let tile = TILE_SIZE as f32;
+    let (width, height) = (map.width as f32 * tile, map.height as f32 * tile);
+    // centre and half extents of each wall, a tile thick and long enough to close the corners
+    let walls = [
+        (
+            Vec2::new(width / 2.0, -tile / 2.0),
+            Vec2::new(width / 2.0 + tile, tile / 2.0),
+        ),
+        (
+            Vec2::new(width / 2.0, height + tile / 2.0),
+            Vec2::new(width / 2.0 + tile, tile / 2.0),
+        ),
+        (
+            Vec2::new(-tile / 2.0, height / 2.0),
+            Vec2::new(tile / 2.0, height / 2.0 + tile),
+        ),
+        (
+            Vec2::new(width + tile / 2.0, height / 2.0),
+            Vec2::new(tile / 2.0, height / 2.0 + tile),
+        ),
+    ];
+    for (center, half_extents) in walls {
+        commands
+            .spawn_bundle(TransformBundle::from(Transform::from_xyz(
+                center.x, center.y, 0.0,
+            )))
+            .insert(Name::new("Map Border"))
+            .insert(MapBorder {
+                width: map.width,
+                height: map.height,
+            })
+            .insert(RigidBody::Fixed)
+            .insert(Collider::cuboid(half_extents.x, half_extents.y));
+    }
+}
+
 // References:
 // 1. Rapier Gravity
 // https://rapier.rs/docs/user_guides/bevy_plugin/rigid_bodies#gravity
+// 2. Rapier Colliders
+// https://rapier.rs/docs/user_guides/bevy_plugin/colliders
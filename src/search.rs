@@ -0,0 +1,321 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+};
+
+use crate::{Map, Position, SearchResult};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchStatus {
+    Searching,
+    Found,
+    NotFound,
+}
+
+impl SearchStatus {
+    #[must_use]
+    pub fn is_done(self) -> bool {
+        self != SearchStatus::Searching
+    }
+}
+
+/// What the search knew about a node when it expanded it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExpandedNode {
+    pub position: Position,
+    /// Cost from the start, or to the goal for `backward` nodes.
+    pub g: i32,
+    /// Estimate of the cost to the goal, or from the start for `backward` nodes.
+    pub h: i32,
+    /// The node it was reached from, `None` for the start.
+    pub parent: Option<Position>,
+    /// Whether it was expanded by the half of a bidirectional search that runs from the goal.
+    pub backward: bool,
+}
+
+impl ExpandedNode {
+    #[must_use]
+    pub fn f(&self) -> i32 {
+        self.g + self.h
+    }
+}
+
+/// What a search looks like between two expansions, for drawing it.
+#[derive(Clone, Debug, Default)]
+pub struct SearchSnapshot {
+    /// The node expanded by the last step.
+    pub current: Option<Position>,
+    /// Nodes waiting to be expanded.
+    pub open: Vec<Position>,
+    /// Nodes waiting to be expanded from the goal, for bidirectional searches.
+    pub backward_open: Vec<Position>,
+    /// Nodes already expanded, in expansion order.
+    pub closed: Vec<Position>,
+    /// `g`, `h` and parent of every node in `closed`, in the same order.
+    pub expanded: Vec<ExpandedNode>,
+    /// The path from start to goal, once it was found.
+    pub path: Option<Vec<Position>>,
+}
+
+/// A search that is advanced one expansion at a time, so it can be drawn while it runs.
+///
+/// Every `Pathfinder` hands one out through `Pathfinder::steps`; `Pathfinder::search` just runs it
+/// to the end.
+pub trait SearchSteps: Send + Sync {
+    /// Expands the next node, unless the search is already over.
+    fn step(&mut self, map: &Map) -> SearchStatus;
+
+    fn status(&self) -> SearchStatus;
+
+    /// The node expanded by the last step.
+    fn current(&self) -> Option<ExpandedNode>;
+
+    /// Nodes waiting to be expanded.
+    fn open(&self) -> Vec<Position>;
+
+    /// Nodes waiting to be expanded by the half of a bidirectional search that runs from the
+    /// goal, `open` then only holds the ones of the half that runs from the start.
+    fn backward_open(&self) -> Vec<Position> {
+        Vec::new()
+    }
+
+    fn snapshot(&self) -> SearchSnapshot;
+
+    /// Everything the search produced so far, with the path and its cost once it was found.
+    fn into_result(self: Box<Self>, map: &Map) -> SearchResult;
+}
+
+/// The part of a best-first search that differs between algorithms.
+pub trait Expander: Send + Sync {
+    /// Nodes reachable from `node` with the cost of getting there. `parent` is the node `node`
+    /// was reached from. Every tile looked at is pushed to `generated`.
+    fn successors(
+        &self,
+        map: &Map,
+        node: Position,
+        parent: Option<Position>,
+        goal: Position,
+        generated: &mut Vec<Position>,
+    ) -> Vec<(Position, i32)>;
+
+    /// Estimate of the cost from `node` to `goal`, `0` turns the search into Dijkstra.
+    fn heuristic(&self, map: &Map, node: &Position, goal: &Position) -> i32 {
+        map.heuristic(node, goal)
+    }
+
+    /// How many times the optimal cost a path may cost at most, for expanders that give up
+    /// optimality for speed.
+    fn bound(&self) -> Option<f64> {
+        None
+    }
+
+    /// Turns the nodes the search went through into a path of neighbouring tiles.
+    fn refine(&self, _map: &Map, nodes: Vec<Position>) -> Vec<Position> {
+        nodes
+    }
+}
+
+/// Best-first search over the nodes handed out by an `Expander`.
+///
+/// Open nodes are ordered by `g + h`, ties go to the lower `h` and then to the node that was
+/// opened first, so a search with unit costs and no heuristic is a breadth first search.
+pub struct BestFirstSearch<E: Expander> {
+    expander: E,
+    goal: Position,
+    open: BinaryHeap<Reverse<(i32, i32, usize, Position)>>,
+    opened: usize,
+    /// Per tile, indexed like `Map::costs`, sized for the map the search started on.
+    width: i32,
+    g: Vec<Option<i32>>,
+    parents: Vec<Option<Position>>,
+    closed: Vec<bool>,
+    expanded: Vec<ExpandedNode>,
+    generated: Vec<Position>,
+    path: Option<Vec<Position>>,
+    status: SearchStatus,
+}
+
+impl<E: Expander> BestFirstSearch<E> {
+    pub fn new(map: &Map, expander: E, start: Position, goal: Position) -> Self {
+        let tiles = (map.width * map.height) as usize;
+        let mut search = BestFirstSearch {
+            expander,
+            goal,
+            open: BinaryHeap::new(),
+            opened: 0,
+            width: map.width,
+            g: vec![None; tiles],
+            parents: vec![None; tiles],
+            closed: vec![false; tiles],
+            expanded: Vec::new(),
+            generated: Vec::new(),
+            path: None,
+            status: SearchStatus::Searching,
+        };
+        if map.is_blocked(start.x(), start.y()) || map.is_blocked(goal.x(), goal.y()) {
+            search.status = SearchStatus::NotFound;
+        } else {
+            search.open_node(map, start, 0);
+        }
+        search
+    }
+
+    fn index(&self, node: Position) -> usize {
+        (node.y() * self.width + node.x()) as usize
+    }
+
+    fn open_node(&mut self, map: &Map, node: Position, g: i32) {
+        let h = self.expander.heuristic(map, &node, &self.goal);
+        let index = self.index(node);
+        self.g[index] = Some(g);
+        self.open.push(Reverse((g + h, h, self.opened, node)));
+        self.opened += 1;
+    }
+
+    fn back_trace(&self) -> Vec<Position> {
+        let mut nodes = vec![self.goal];
+        let mut node = self.goal;
+        while let Some(parent) = self.parents[self.index(node)] {
+            nodes.push(parent);
+            node = parent;
+        }
+        nodes.reverse();
+        nodes
+    }
+}
+
+impl<E: Expander> SearchSteps for BestFirstSearch<E> {
+    fn step(&mut self, map: &Map) -> SearchStatus {
+        if self.status.is_done() {
+            return self.status;
+        }
+
+        // nodes opened again with a lower cost leave stale entries behind, skip those
+        let node = loop {
+            if let Some(Reverse((_, _, _, node))) = self.open.pop() {
+                let index = self.index(node);
+                if !self.closed[index] {
+                    self.closed[index] = true;
+                    break node;
+                }
+            } else {
+                self.status = SearchStatus::NotFound;
+                return self.status;
+            }
+        };
+        let index = self.index(node);
+        let parent = self.parents[index];
+        let g = self.g[index].unwrap_or(0);
+        self.expanded.push(ExpandedNode {
+            position: node,
+            g,
+            h: self.expander.heuristic(map, &node, &self.goal),
+            parent,
+            backward: false,
+        });
+
+        if node == self.goal {
+            self.path = Some(self.expander.refine(map, self.back_trace()));
+            self.status = SearchStatus::Found;
+            return self.status;
+        }
+
+        let successors =
+            self.expander
+                .successors(map, node, parent, self.goal, &mut self.generated);
+        for (successor, cost) in successors {
+            let index = self.index(successor);
+            if self.closed[index] {
+                continue;
+            }
+            let ng = g + cost;
+            if self.g[index].is_none_or(|g| ng < g) {
+                self.parents[index] = Some(node);
+                self.open_node(map, successor, ng);
+            }
+        }
+        self.status
+    }
+
+    fn status(&self) -> SearchStatus {
+        self.status
+    }
+
+    fn current(&self) -> Option<ExpandedNode> {
+        if self.status == SearchStatus::NotFound {
+            None
+        } else {
+            self.expanded.last().copied()
+        }
+    }
+
+    fn open(&self) -> Vec<Position> {
+        let mut seen = HashSet::new();
+        self.open
+            .iter()
+            .map(|Reverse((_, _, _, node))| *node)
+            .filter(|node| !self.closed[self.index(*node)] && seen.insert(*node))
+            .collect()
+    }
+
+    fn snapshot(&self) -> SearchSnapshot {
+        SearchSnapshot {
+            current: self.current().map(|node| node.position),
+            open: self.open(),
+            backward_open: Vec::new(),
+            closed: self.expanded.iter().map(|node| node.position).collect(),
+            expanded: self.expanded.clone(),
+            path: self.path.clone(),
+        }
+    }
+
+    fn into_result(self: Box<Self>, map: &Map) -> SearchResult {
+        let cost = self.path.as_ref().map(|path| map.path_cost(path));
+        let bound = self.path.as_ref().and_then(|_| self.expander.bound());
+        SearchResult {
+            path: self.path,
+            cost,
+            bound,
+            expanded: self.expanded.iter().map(|node| node.position).collect(),
+            expanded_nodes: self.expanded,
+            generated: self.generated,
+            ..Default::default()
+        }
+    }
+}
+
+/// Expands a node into its neighbouring tiles, as given by `Map::get_successors`.
+pub struct GridExpander {
+    /// Whether nodes are ordered by an estimate of the remaining cost (A*).
+    pub uses_heuristic: bool,
+    /// Whether tile costs count, otherwise every step costs the same (BFS).
+    pub uses_costs: bool,
+}
+
+impl Expander for GridExpander {
+    fn successors(
+        &self,
+        map: &Map,
+        node: Position,
+        _parent: Option<Position>,
+        _goal: Position,
+        generated: &mut Vec<Position>,
+    ) -> Vec<(Position, i32)> {
+        map.get_successors(&node)
+            .into_iter()
+            .map(|successor| {
+                generated.push(successor.position);
+                let cost = if self.uses_costs { successor.cost } else { 1 };
+                (successor.position, cost)
+            })
+            .collect()
+    }
+
+    fn heuristic(&self, map: &Map, node: &Position, goal: &Position) -> i32 {
+        if self.uses_heuristic {
+            map.heuristic(node, goal)
+        } else {
+            0
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use crate::{Map, Position};
+
+/// Points of the curve between two waypoints, the first included and the second left to the
+/// next segment.
+pub const CURVE_SAMPLES: usize = 8;
+
+/// How far a path is post-processed. Each level does everything the one before it does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathSmoothing {
+    /// The path as the search returned it.
+    Off,
+    /// Only the tiles where the path turns.
+    Waypoints,
+    /// Waypoints that can see each other are joined by a straight line, skipping the ones in
+    /// between.
+    StringPull,
+    /// A Catmull-Rom curve through the string-pulled waypoints, straightened wherever it would
+    /// touch a blocked tile.
+    Curve,
+}
+
+impl PathSmoothing {
+    pub const ALL: [PathSmoothing; 4] = [
+        PathSmoothing::Off,
+        PathSmoothing::Waypoints,
+        PathSmoothing::StringPull,
+        PathSmoothing::Curve,
+    ];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            PathSmoothing::Off => "Off",
+            PathSmoothing::Waypoints => "Waypoints",
+            PathSmoothing::StringPull => "String Pull",
+            PathSmoothing::Curve => "Curve",
+        }
+    }
+
+    #[must_use]
+    pub fn next(self) -> PathSmoothing {
+        let index = Self::ALL
+            .iter()
+            .position(|smoothing| *smoothing == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// A path after smoothing.
+#[derive(Clone, Debug, Default)]
+pub struct SmoothedPath {
+    /// The tiles to walk through, start and goal included.
+    pub waypoints: Vec<Position>,
+    /// With `PathSmoothing::Curve`, points along the curve in tile coordinates (the centre of tile
+    /// `(x, y)` is `(x, y)`), otherwise empty.
+    pub curve: Vec<(f32, f32)>,
+}
+
+/// Runs `path` through the steps `smoothing` asks for.
+#[must_use]
+pub fn smooth_path(map: &Map, path: &[Position], smoothing: PathSmoothing) -> SmoothedPath {
+    let mut waypoints = path.to_vec();
+    if smoothing != PathSmoothing::Off {
+        waypoints = remove_collinear(&waypoints);
+    }
+    if smoothing == PathSmoothing::StringPull || smoothing == PathSmoothing::Curve {
+        waypoints = string_pull(map, &waypoints);
+    }
+    let curve = if smoothing == PathSmoothing::Curve {
+        catmull_rom(map, &waypoints, CURVE_SAMPLES)
+    } else {
+        Vec::new()
+    };
+    SmoothedPath { waypoints, curve }
+}
+
+/// Drops every point that lies on the straight line between its neighbours and goes on in the
+/// same direction, so only the start, the turns and the goal are left.
+#[must_use]
+pub fn remove_collinear(path: &[Position]) -> Vec<Position> {
+    let mut waypoints: Vec<Position> = Vec::with_capacity(path.len());
+    for &point in path {
+        if waypoints.last() == Some(&point) {
+            continue;
+        }
+        if let [.., before, middle] = waypoints[..] {
+            let (ax, ay) = (middle.x() - before.x(), middle.y() - before.y());
+            let (bx, by) = (point.x() - middle.x(), point.y() - middle.y());
+            if ax * by == ay * bx && ax * bx + ay * by > 0 {
+                waypoints.pop();
+            }
+        }
+        waypoints.push(point);
+    }
+    waypoints
+}
+
+/// Walks the path and, from each waypoint kept, skips ahead for as long as the next one is still
+/// in line of sight. Tile costs are not looked at, so on a map with costs the result can cross
+/// tiles the search went around.
+#[must_use]
+pub fn string_pull(map: &Map, path: &[Position]) -> Vec<Position> {
+    let mut waypoints = Vec::new();
+    let mut anchor = match path.first() {
+        Some(first) => *first,
+        None => return waypoints,
+    };
+    waypoints.push(anchor);
+    let mut index = 1;
+    while index < path.len() {
+        let mut reach = index;
+        while reach + 1 < path.len() && map.line_of_sight(&anchor, &path[reach + 1]) {
+            reach += 1;
+        }
+        anchor = path[reach];
+        waypoints.push(anchor);
+        index = reach + 1;
+    }
+    waypoints
+}
+
+/// A Catmull-Rom spline through `waypoints`, `samples` points per segment. A segment whose curve
+/// would pass over a blocked tile, as drawn from one point to the next, is kept straight instead;
+/// the waypoints have line of sight to each other, so that is always clear.
+#[must_use]
+// p0 to p3 are the usual names of a spline segment's control points
+#[allow(clippy::similar_names)]
+pub fn catmull_rom(map: &Map, waypoints: &[Position], samples: usize) -> Vec<(f32, f32)> {
+    let point = |index: usize| {
+        let position = waypoints[index.min(waypoints.len() - 1)];
+        (position.x() as f32, position.y() as f32)
+    };
+    let mut curve = Vec::new();
+    for segment in 0..waypoints.len().saturating_sub(1) {
+        let p0 = point(segment.saturating_sub(1));
+        let (p1, p2, p3) = (point(segment), point(segment + 1), point(segment + 2));
+        let sampled: Vec<(f32, f32)> = (0..samples)
+            .map(|sample| {
+                let t = sample as f32 / samples as f32;
+                (
+                    catmull_rom_1d(p0.0, p1.0, p2.0, p3.0, t),
+                    catmull_rom_1d(p0.1, p1.1, p2.1, p3.1, t),
+                )
+            })
+            .collect();
+
+        let clear = sampled
+            .iter()
+            .copied()
+            .chain([p2])
+            .collect::<Vec<_>>()
+            .windows(2)
+            .all(|pair| sweeps_open_tiles(map, pair[0], pair[1]));
+        if clear {
+            curve.extend(sampled);
+        } else {
+            curve.push(p1);
+        }
+    }
+    if let Some(last) = waypoints.last() {
+        curve.push((last.x() as f32, last.y() as f32));
+    }
+    curve
+}
+
+/// Whether the straight line from `from` to `to`, in tile coordinates, only crosses open tiles.
+/// Unlike `Map::line_of_sight` the ends need not be tile centres. Where the line passes exactly
+/// through the corner of four tiles, the corner cutting policy decides as for a diagonal step.
+fn sweeps_open_tiles(map: &Map, from: (f32, f32), to: (f32, f32)) -> bool {
+    // tile (x, y) covers x - 0.5 to x + 0.5, shifted so that it starts at x
+    let (x0, y0) = (from.0 + 0.5, from.1 + 0.5);
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let (mut x, mut y) = (x0.floor() as i32, y0.floor() as i32);
+    let (step_x, step_y) = (sign(dx), sign(dy));
+    // how far along the line, from 0 to 1, the next vertical and horizontal tile border is
+    let next_border = |start: f32, tile: i32, delta: f32| {
+        if delta > 0.0 {
+            (tile as f32 + 1.0 - start) / delta
+        } else if delta < 0.0 {
+            (start - tile as f32) / -delta
+        } else {
+            f32::INFINITY
+        }
+    };
+    let (mut border_x, mut border_y) = (next_border(x0, x, dx), next_border(y0, y, dy));
+    let (delta_x, delta_y) = (1.0 / dx.abs(), 1.0 / dy.abs());
+    loop {
+        if !map.is_path(x, y) {
+            return false;
+        }
+        if border_x.min(border_y) >= 1.0 {
+            return true;
+        }
+        if (border_x - border_y).abs() < 1e-5 {
+            let side_a = map.is_path(x + step_x, y);
+            let side_b = map.is_path(x, y + step_y);
+            if !((side_a && side_b) || map.corner_cutting.allows(side_a, side_b)) {
+                return false;
+            }
+            x += step_x;
+            y += step_y;
+            border_x += delta_x;
+            border_y += delta_y;
+        } else if border_x < border_y {
+            x += step_x;
+            border_x += delta_x;
+        } else {
+            y += step_y;
+            border_y += delta_y;
+        }
+    }
+}
+
+fn sign(value: f32) -> i32 {
+    if value > 0.0 {
+        1
+    } else if value < 0.0 {
+        -1
+    } else {
+        0
+    }
+}
+
+/// The uniform Catmull-Rom spline between `p1` (`t = 0`) and `p2` (`t = 1`).
+fn catmull_rom_1d(p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{catmull_rom, remove_collinear, string_pull, sweeps_open_tiles, CURVE_SAMPLES};
+    use crate::{
+        random_pairs, solve, test_support::random_map, CornerCutting, Map, PathfindingAlgorithm,
+        Position,
+    };
+
+    fn length(path: &[Position]) -> f64 {
+        path.windows(2)
+            .map(|step| {
+                f64::from(step[1].x() - step[0].x()).hypot(f64::from(step[1].y() - step[0].y()))
+            })
+            .sum()
+    }
+
+    #[test]
+    fn smoothing_never_lengthens_or_blocks_a_path() {
+        for seed in 0..12 {
+            let mut map = random_map(24, 20, seed);
+            map.set_corner_cutting(CornerCutting::ALL[seed as usize % CornerCutting::ALL.len()]);
+            for (start, goal) in random_pairs(&map, 10, seed) {
+                let path = solve(&map, PathfindingAlgorithm::AStar, start, goal)
+                    .path
+                    .unwrap();
+                let waypoints = remove_collinear(&path);
+                let pulled = string_pull(&map, &waypoints);
+                for smoothed in [&waypoints, &pulled] {
+                    assert_eq!(
+                        (smoothed.first(), smoothed.last()),
+                        (Some(&start), Some(&goal))
+                    );
+                    assert!(smoothed
+                        .windows(2)
+                        .all(|segment| map.line_of_sight(&segment[0], &segment[1])));
+                }
+                assert!((length(&waypoints) - length(&path)).abs() < 1e-9);
+                assert!(length(&pulled) <= length(&waypoints) + 1e-9);
+
+                let curve = catmull_rom(&map, &pulled, CURVE_SAMPLES);
+                assert!(curve
+                    .windows(2)
+                    .all(|segment| sweeps_open_tiles(&map, segment[0], segment[1])));
+            }
+        }
+    }
+
+    #[test]
+    fn curves_go_straight_past_blocked_corners() {
+        // the curve from (5, 6) bends towards (2, 1) early and cuts through the corner of (2, 4),
+        // although every point it is drawn through lies on an open tile
+        let waypoints = [Position(5, 6), Position(2, 3), Position(2, 1)];
+        let mut map = Map::new(7, 7, true);
+        let curve = catmull_rom(&map, &waypoints, CURVE_SAMPLES);
+        assert_eq!(curve.len(), 2 * CURVE_SAMPLES + 1);
+
+        map.set_blocked(2, 4, true);
+        let curve = catmull_rom(&map, &waypoints, CURVE_SAMPLES);
+        assert_eq!(curve[..2], [(5.0, 6.0), (2.0, 3.0)]);
+        assert_eq!(curve.len(), CURVE_SAMPLES + 2);
+        assert!(curve
+            .iter()
+            .all(|&(x, y)| !map.is_blocked(x.round() as i32, y.round() as i32)));
+    }
+}
+
+// References
+// 1. Toward More Realistic Pathfinding, Marco Pinter (2001)
+// https://www.gamedeveloper.com/programming/toward-more-realistic-pathfinding
+// 2. Catmull and Rom, "A Class of Local Interpolating Splines" (1974)
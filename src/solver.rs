@@ -0,0 +1,138 @@
+use crate::{
+    AStar, AraStar, Bfs, BidirectionalAStar, BidirectionalBfs, DStarLite, Dijkstra, Hpa,
+    IncrementalPlanner, Jps, JpsPlus, LazyThetaStar, LpaStar, Map, Pathfinder, Position,
+    SearchResult, ThetaStar, WeightedAStar,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathfindingAlgorithm {
+    AStar,
+    BFS,
+    Dijkstra,
+    Jps,
+    JpsPlus,
+    Hpa,
+    BidirectionalBfs,
+    BidirectionalAStar,
+    ThetaStar,
+    LazyThetaStar,
+    WeightedAStar,
+    AraStar,
+    LpaStar,
+    DStarLite,
+}
+
+impl PathfindingAlgorithm {
+    /// Every registered algorithm, in the order the UI cycles through them.
+    pub const ALL: [PathfindingAlgorithm; 14] = [
+        PathfindingAlgorithm::BFS,
+        PathfindingAlgorithm::Dijkstra,
+        PathfindingAlgorithm::AStar,
+        PathfindingAlgorithm::Jps,
+        PathfindingAlgorithm::JpsPlus,
+        PathfindingAlgorithm::Hpa,
+        PathfindingAlgorithm::BidirectionalBfs,
+        PathfindingAlgorithm::BidirectionalAStar,
+        PathfindingAlgorithm::ThetaStar,
+        PathfindingAlgorithm::LazyThetaStar,
+        PathfindingAlgorithm::WeightedAStar,
+        PathfindingAlgorithm::AraStar,
+        PathfindingAlgorithm::LpaStar,
+        PathfindingAlgorithm::DStarLite,
+    ];
+
+    /// The single place an algorithm is registered: map the variant to its `Pathfinder`.
+    #[must_use]
+    pub fn pathfinder(self) -> Box<dyn Pathfinder> {
+        match self {
+            PathfindingAlgorithm::AStar => Box::new(AStar),
+            PathfindingAlgorithm::BFS => Box::new(Bfs),
+            PathfindingAlgorithm::Dijkstra => Box::new(Dijkstra),
+            PathfindingAlgorithm::Jps => Box::new(Jps),
+            PathfindingAlgorithm::JpsPlus => Box::new(JpsPlus),
+            PathfindingAlgorithm::Hpa => Box::new(Hpa),
+            PathfindingAlgorithm::BidirectionalBfs => Box::new(BidirectionalBfs),
+            PathfindingAlgorithm::BidirectionalAStar => Box::new(BidirectionalAStar),
+            PathfindingAlgorithm::ThetaStar => Box::new(ThetaStar),
+            PathfindingAlgorithm::LazyThetaStar => Box::new(LazyThetaStar),
+            PathfindingAlgorithm::WeightedAStar => Box::new(WeightedAStar::default()),
+            PathfindingAlgorithm::AraStar => Box::new(AraStar::default()),
+            PathfindingAlgorithm::LpaStar => Box::new(LpaStar),
+            PathfindingAlgorithm::DStarLite => Box::new(DStarLite),
+        }
+    }
+
+    /// The `Pathfinder` with `weight` as the heuristic weight of weighted A* and ARA*, the other
+    /// algorithms have none.
+    #[must_use]
+    pub fn weighted_pathfinder(self, weight: f64) -> Box<dyn Pathfinder> {
+        match self {
+            PathfindingAlgorithm::WeightedAStar => Box::new(WeightedAStar { weight }),
+            PathfindingAlgorithm::AraStar => Box::new(AraStar {
+                weight,
+                ..AraStar::default()
+            }),
+            _ => self.pathfinder(),
+        }
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        self.pathfinder().name()
+    }
+
+    #[must_use]
+    pub fn uses_costs(self) -> bool {
+        self.pathfinder().uses_costs()
+    }
+
+    #[must_use]
+    pub fn any_angle(self) -> bool {
+        self.pathfinder().any_angle()
+    }
+
+    #[must_use]
+    pub fn weighted(self) -> bool {
+        self.pathfinder().weighted()
+    }
+
+    #[must_use]
+    pub fn incremental(
+        self,
+        map: &Map,
+        start: Position,
+        goal: Position,
+    ) -> Option<IncrementalPlanner> {
+        self.pathfinder().incremental(map, start, goal)
+    }
+
+    #[must_use]
+    pub fn next(self) -> PathfindingAlgorithm {
+        let index = self.index();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    #[must_use]
+    pub fn previous(self) -> PathfindingAlgorithm {
+        let index = self.index();
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|algorithm| *algorithm == self)
+            .unwrap_or(0)
+    }
+}
+
+/// Runs `algorithm` from `start` to `goal` without touching any game state.
+#[must_use]
+pub fn solve(
+    map: &Map,
+    algorithm: PathfindingAlgorithm,
+    start: Position,
+    goal: Position,
+) -> SearchResult {
+    algorithm.pathfinder().find_path(map, start, goal)
+}
@@ -0,0 +1,34 @@
+//! Maps and checks shared by the unit tests of several algorithms.
+
+use crate::{split_mix, Map, Position};
+
+/// A map with about a quarter of its tiles blocked and a third of the open ones costing 2 to 5, the
+/// same for the same `seed`.
+pub(crate) fn random_map(width: i32, height: i32, seed: u64) -> Map {
+    let mut state = seed;
+    let mut map = Map::new(width, height, true);
+    for index in 0..map.blocked.len() {
+        let value = split_mix(&mut state);
+        map.blocked[index] = value.is_multiple_of(4);
+        if value.is_multiple_of(3) {
+            map.costs[index] = Some(2 + ((value >> 32) % 4) as i32);
+        }
+    }
+    map
+}
+
+/// Asserts that `path` leads from `start` to `goal` one allowed step at a time.
+pub(crate) fn assert_walkable_path(map: &Map, path: &[Position], start: Position, goal: Position) {
+    assert_eq!((path.first(), path.last()), (Some(&start), Some(&goal)));
+    for step in path.windows(2) {
+        let successors = map.get_successors(&step[0]);
+        assert!(
+            successors
+                .iter()
+                .any(|successor| successor.position == step[1]),
+            "{:?} to {:?}",
+            step[0],
+            step[1]
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+};
+
+use crate::{
+    euclidean_cost, ExpandedNode, Map, Pathfinder, Position, SearchResult, SearchSnapshot,
+    SearchStatus, SearchSteps,
+};
+
+pub struct ThetaStar;
+
+impl Pathfinder for ThetaStar {
+    fn name(&self) -> &'static str {
+        "Theta*"
+    }
+
+    fn uses_costs(&self) -> bool {
+        false
+    }
+
+    fn any_angle(&self) -> bool {
+        true
+    }
+
+    fn steps(&self, map: &Map, start: Position, goal: Position) -> Box<dyn SearchSteps> {
+        Box::new(AnyAngleSearch::new(map, start, goal, false))
+    }
+}
+
+pub struct LazyThetaStar;
+
+impl Pathfinder for LazyThetaStar {
+    fn name(&self) -> &'static str {
+        "Lazy Theta*"
+    }
+
+    fn uses_costs(&self) -> bool {
+        false
+    }
+
+    fn any_angle(&self) -> bool {
+        true
+    }
+
+    fn steps(&self, map: &Map, start: Position, goal: Position) -> Box<dyn SearchSteps> {
+        Box::new(AnyAngleSearch::new(map, start, goal, true))
+    }
+}
+
+/// A* over the grid whose nodes may take the parent of the node they were reached from as their
+/// own, whenever there is a line of sight to it, so paths are straight lines at any angle between
+/// waypoints instead of steps between neighbours.
+///
+/// Theta* checks the line of sight for every successor it generates. Lazy Theta* assumes it and
+/// only checks once a node is expanded, falling back to the best expanded neighbour when the line
+/// is blocked, which needs far fewer checks. Both price a line by its Euclidean length and ignore
+/// tile costs.
+pub struct AnyAngleSearch {
+    lazy: bool,
+    goal: Position,
+    width: i32,
+    open: BinaryHeap<Reverse<(i32, i32, usize, Position)>>,
+    g: Vec<Option<i32>>,
+    parents: Vec<Option<Position>>,
+    closed: Vec<bool>,
+    opened: usize,
+    expanded: Vec<ExpandedNode>,
+    generated: Vec<Position>,
+    current: Option<ExpandedNode>,
+    path: Option<Vec<Position>>,
+    status: SearchStatus,
+}
+
+impl AnyAngleSearch {
+    #[must_use]
+    pub fn new(map: &Map, start: Position, goal: Position, lazy: bool) -> AnyAngleSearch {
+        let tiles = (map.width * map.height) as usize;
+        let mut search = AnyAngleSearch {
+            lazy,
+            goal,
+            width: map.width,
+            open: BinaryHeap::new(),
+            g: vec![None; tiles],
+            parents: vec![None; tiles],
+            closed: vec![false; tiles],
+            opened: 0,
+            expanded: Vec::new(),
+            generated: Vec::new(),
+            current: None,
+            path: None,
+            status: SearchStatus::Searching,
+        };
+        if map.is_blocked(start.x(), start.y()) || map.is_blocked(goal.x(), goal.y()) {
+            search.status = SearchStatus::NotFound;
+        } else {
+            search.open_node(start, None, 0);
+        }
+        search
+    }
+
+    fn index(&self, node: Position) -> usize {
+        (node.y() * self.width + node.x()) as usize
+    }
+
+    fn open_node(&mut self, node: Position, parent: Option<Position>, g: i32) {
+        let index = self.index(node);
+        self.g[index] = Some(g);
+        self.parents[index] = parent;
+        let h = euclidean_cost(&node, &self.goal);
+        self.open.push(Reverse((g + h, h, self.opened, node)));
+        self.opened += 1;
+    }
+
+    /// Lazy Theta* only: when `node` cannot see the parent it was given, takes the expanded
+    /// neighbour it is cheapest to reach it from instead.
+    fn set_vertex(&mut self, map: &Map, node: Position) {
+        let index = self.index(node);
+        let Some(parent) = self.parents[index] else {
+            return;
+        };
+        if map.line_of_sight(&parent, &node) {
+            return;
+        }
+        let best = map
+            .get_successors(&node)
+            .into_iter()
+            .map(|successor| successor.position)
+            .filter(|neighbour| self.closed[self.index(*neighbour)])
+            .filter_map(|neighbour| {
+                let g = self.g[self.index(neighbour)]?;
+                Some((g + euclidean_cost(&neighbour, &node), neighbour))
+            })
+            .min();
+        // the node that generated `node` is always an expanded neighbour
+        if let Some((g, neighbour)) = best {
+            self.g[index] = Some(g);
+            self.parents[index] = Some(neighbour);
+        }
+    }
+
+    /// The cheapest way to reach `neighbour` from `node`: straight from the parent of `node` when
+    /// it can be seen (or, for Lazy Theta*, assumed to be), otherwise from `node` itself.
+    fn relax(&self, map: &Map, node: Position, g: i32, neighbour: Position) -> (i32, Position) {
+        if let Some(parent) = self.parents[self.index(node)] {
+            if self.lazy || map.line_of_sight(&parent, &neighbour) {
+                let parent_g = self.g[self.index(parent)].unwrap_or(0);
+                return (parent_g + euclidean_cost(&parent, &neighbour), parent);
+            }
+        }
+        (g + euclidean_cost(&node, &neighbour), node)
+    }
+
+    fn build_path(&self) -> Vec<Position> {
+        let mut path = vec![self.goal];
+        let mut node = self.goal;
+        while let Some(parent) = self.parents[self.index(node)] {
+            path.push(parent);
+            node = parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+impl SearchSteps for AnyAngleSearch {
+    fn step(&mut self, map: &Map) -> SearchStatus {
+        if self.status.is_done() {
+            return self.status;
+        }
+        self.current = None;
+
+        let node = loop {
+            match self.open.pop() {
+                Some(Reverse((_, _, _, node))) if self.closed[self.index(node)] => {}
+                Some(Reverse((_, _, _, node))) => break node,
+                None => {
+                    self.status = SearchStatus::NotFound;
+                    return self.status;
+                }
+            }
+        };
+        if self.lazy {
+            self.set_vertex(map, node);
+        }
+        let index = self.index(node);
+        self.closed[index] = true;
+        let g = self.g[index].unwrap_or(0);
+        let expanded = ExpandedNode {
+            position: node,
+            g,
+            h: euclidean_cost(&node, &self.goal),
+            parent: self.parents[index],
+            backward: false,
+        };
+        self.expanded.push(expanded);
+        self.current = Some(expanded);
+
+        if node == self.goal {
+            self.path = Some(self.build_path());
+            self.status = SearchStatus::Found;
+            return self.status;
+        }
+
+        for successor in map.get_successors(&node) {
+            let neighbour = successor.position;
+            self.generated.push(neighbour);
+            let neighbour_index = self.index(neighbour);
+            if self.closed[neighbour_index] {
+                continue;
+            }
+            let (ng, parent) = self.relax(map, node, g, neighbour);
+            if self.g[neighbour_index].is_none_or(|g| ng < g) {
+                self.open_node(neighbour, Some(parent), ng);
+            }
+        }
+        self.status
+    }
+
+    fn status(&self) -> SearchStatus {
+        self.status
+    }
+
+    fn current(&self) -> Option<ExpandedNode> {
+        self.current
+    }
+
+    fn open(&self) -> Vec<Position> {
+        let mut seen = HashSet::new();
+        self.open
+            .iter()
+            .map(|Reverse((_, _, _, node))| *node)
+            .filter(|node| !self.closed[self.index(*node)] && seen.insert(*node))
+            .collect()
+    }
+
+    fn snapshot(&self) -> SearchSnapshot {
+        SearchSnapshot {
+            current: self.current.map(|node| node.position),
+            open: self.open(),
+            backward_open: Vec::new(),
+            closed: self.expanded.iter().map(|node| node.position).collect(),
+            expanded: self.expanded.clone(),
+            path: self.path.clone(),
+        }
+    }
+
+    /// The path is the waypoints only, consecutive ones are usually not neighbours.
+    fn into_result(self: Box<Self>, _map: &Map) -> SearchResult {
+        let cost = self
+            .path
+            .as_ref()
+            .map(|_| self.g[self.index(self.goal)].unwrap_or(0));
+        SearchResult {
+            path: self.path,
+            cost,
+            expanded: self.expanded.iter().map(|node| node.position).collect(),
+            expanded_nodes: self.expanded,
+            generated: self.generated,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        euclidean_cost, random_pairs, solve, test_support::random_map, CornerCutting, Map,
+        PathfindingAlgorithm, Position,
+    };
+
+    const ANY_ANGLE: [PathfindingAlgorithm; 2] = [
+        PathfindingAlgorithm::ThetaStar,
+        PathfindingAlgorithm::LazyThetaStar,
+    ];
+
+    #[test]
+    fn segments_have_line_of_sight() {
+        for seed in 0..12 {
+            let mut map = random_map(24, 20, seed);
+            map.set_corner_cutting(CornerCutting::ALL[seed as usize % CornerCutting::ALL.len()]);
+            for (start, goal) in random_pairs(&map, 10, seed) {
+                for algorithm in ANY_ANGLE {
+                    let result = solve(&map, algorithm, start, goal);
+                    let path = result.path.unwrap();
+                    assert_eq!((path.first(), path.last()), (Some(&start), Some(&goal)));
+                    for segment in path.windows(2) {
+                        assert!(
+                            map.line_of_sight(&segment[0], &segment[1]),
+                            "{} from {start:?} to {goal:?}: {:?} to {:?}",
+                            algorithm.name(),
+                            segment[0],
+                            segment[1]
+                        );
+                    }
+                    let length = path
+                        .windows(2)
+                        .map(|segment| euclidean_cost(&segment[0], &segment[1]))
+                        .sum();
+                    assert_eq!(result.cost, Some(length));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn goes_straight_across_open_ground() {
+        let mut map = Map::new(10, 6, true);
+        let (start, goal) = (Position(0, 0), Position(7, 3));
+        for algorithm in ANY_ANGLE {
+            assert_eq!(
+                solve(&map, algorithm, start, goal).path,
+                Some(vec![start, goal])
+            );
+        }
+
+        // a wall in the way leaves one waypoint at its corner
+        for y in 0..3 {
+            map.set_blocked(4, y, true);
+        }
+        for algorithm in ANY_ANGLE {
+            assert_eq!(
+                solve(&map, algorithm, start, goal).path,
+                Some(vec![start, Position(4, 3), goal])
+            );
+        }
+    }
+}
+
+// References
+// 1. Nash, Daniel, Koenig and Felner, "Theta*: Any-Angle Path Planning on Grids" (2007)
+// 2. Nash, Koenig and Tovey, "Lazy Theta*: Any-Angle Path Planning and Path Length Analysis in
+// 3D" (2010)
+// 3. An Empirical Comparison of Any-Angle Path-Planning Algorithms, Uras and Koenig (2015)
@@ -1,14 +1,19 @@
 use bevy::prelude::*;
 use bevy_ecs_tilemap::prelude::*;
-use bevy_rapier2d::prelude::*;
 
 use super::{
-    GameState, Map, MapUpdatedEvent, PathfindingAlgorithm, PathfindingAlgorithmChangedEvent,
-    Position, MAP_HEIGHT, MAP_WIDTH,
+    comparison_color, tile_collider, FlowField, FlowFieldChangedEvent, GameState, HeatMapMode, Map,
+    MapUpdatedEvent, PathfindingAlgorithm, PathfindingAlgorithmChangedEvent, Position,
+    STRAIGHT_COST,
 };
 
 pub const TILE_SIZE: i32 = 32;
 
+/// Tints of the white tile for the half of a bidirectional search that runs from the goal, in
+/// place of the grey and mint of the other half.
+pub const BACKWARD_TESTED_COLOR: Color = Color::rgb(0.95, 0.85, 0.7);
+pub const BACKWARD_SEARCHED_COLOR: Color = Color::rgb(0.95, 0.65, 0.4);
+
 /// === Components ===
 #[derive(Component)]
 pub struct PathTile {}
@@ -28,6 +33,22 @@ pub struct CostsTileMap {}
 #[derive(Component)]
 pub struct CostsTileMapStorage {}
 
+/// Text or parent arrow of the node stats overlay.
+#[derive(Component)]
+pub struct NodeStatsMarker {}
+
+/// One tile of an algorithm's path in comparison mode.
+#[derive(Component)]
+pub struct ComparisonMarker {}
+
+/// One segment of an any-angle or smoothed path.
+#[derive(Component)]
+pub struct PathLineMarker {}
+
+/// Shaft or head of a flow field arrow.
+#[derive(Component)]
+pub struct FlowFieldMarker {}
+
 /// === Helper Functions ===
 #[must_use]
 pub fn index_to_world_position(x: i32, y: i32) -> Vec2 {
@@ -43,11 +64,70 @@ pub fn world_position_to_index(position: Vec2) -> (i32, i32) {
     (x_index as i32, y_index as i32)
 }
 
-pub fn setup_path_tilemap(mut commands: Commands, asset_server: Res<AssetServer>) {
+/// A line `width` wide from `from` to `to`, drawn as a sprite stretched and turned to fit.
+#[must_use]
+pub fn line_sprite(from: Vec2, to: Vec2, width: f32, color: Color, z: f32) -> SpriteBundle {
+    let direction = to - from;
+    let center = (from + to) / 2.0;
+    SpriteBundle {
+        sprite: Sprite {
+            color,
+            custom_size: Some(Vec2::new(direction.length() + width, width)),
+            ..default()
+        },
+        transform: Transform::from_xyz(center.x, center.y, z)
+            .with_rotation(Quat::from_rotation_z(direction.y.atan2(direction.x))),
+        ..default()
+    }
+}
+
+/// Blue for `0.0` through cyan, green and yellow to red for `1.0`.
+#[must_use]
+pub fn heat_color(t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0) * 4.0;
+    let fraction = t.fract();
+    match t as i32 {
+        0 => Color::rgb(0.0, fraction, 1.0),
+        1 => Color::rgb(0.0, 1.0, 1.0 - fraction),
+        2 => Color::rgb(fraction, 1.0, 0.0),
+        3 => Color::rgb(1.0, 1.0 - fraction, 0.0),
+        _ => Color::rgb(1.0, 0.0, 0.0),
+    }
+}
+
+/// Where each expanded tile sits on the heat map scale, from `0.0` to `1.0`, in the order of
+/// `GameState::expanded_nodes`.
+#[must_use]
+pub fn heat_values(game_state: &GameState) -> Vec<f32> {
+    let nodes = &game_state.expanded_nodes;
+    match game_state.heat_map {
+        HeatMapMode::Off => Vec::new(),
+        HeatMapMode::ExpansionOrder => {
+            let last = nodes.len().saturating_sub(1).max(1) as f32;
+            (0..nodes.len()).map(|index| index as f32 / last).collect()
+        }
+        HeatMapMode::CostToCome => {
+            let max_g = nodes.iter().map(|node| node.g).max().unwrap_or(0).max(1) as f32;
+            nodes.iter().map(|node| node.g as f32 / max_g).collect()
+        }
+    }
+}
+
+/// A cost in tiles, or in steps for algorithms that count steps instead (BFS).
+#[must_use]
+pub fn format_cost(cost: i32, algorithm: PathfindingAlgorithm) -> String {
+    if algorithm.uses_costs() || algorithm.any_angle() {
+        format!("{:.1}", cost as f32 / STRAIGHT_COST as f32)
+    } else {
+        cost.to_string()
+    }
+}
+
+pub fn setup_path_tilemap(mut commands: Commands, asset_server: Res<AssetServer>, map: Res<Map>) {
     println!("Setup Path TileMap...");
     let tilemap_size = TilemapSize {
-        x: MAP_WIDTH as u32,
-        y: MAP_HEIGHT as u32,
+        x: map.width as u32,
+        y: map.height as u32,
     };
     let path_tilemap_entity = commands.spawn().id(); // Need one per layer.
     let mut path_tile_storage = TileStorage::empty(tilemap_size); // Need one per tilemap_entity.
@@ -57,8 +137,8 @@ pub fn setup_path_tilemap(mut commands: Commands, asset_server: Res<AssetServer>
         for x in 0..tilemap_size.x {
             let world_position = index_to_world_position(x as i32, y as i32);
             let tile_position = TilePos { x, y };
-            let tile_entity = commands
-                .spawn()
+            let mut tile_commands = commands.spawn();
+            tile_commands
                 .insert(Name::new(format!("Path Tile: {}, {}", x, y)))
                 .insert(PathTile {})
                 .insert_bundle(TileBundle {
@@ -69,13 +149,12 @@ pub fn setup_path_tilemap(mut commands: Commands, asset_server: Res<AssetServer>
                 .insert_bundle(TransformBundle {
                     local: Transform::from_xyz(world_position.x, world_position.y, 0.),
                     global: Default::default(),
-                })
-                .insert(RigidBody::Fixed)
-                .insert(Collider::cuboid(
-                    TILE_SIZE as f32 / 2.0,
-                    TILE_SIZE as f32 / 2.0,
-                ))
-                .id();
+                });
+            // `sync_tile_colliders_system` keeps these in step with the map from here on
+            if map.is_blocked(x as i32, y as i32) {
+                tile_commands.insert_bundle(tile_collider());
+            }
+            let tile_entity = tile_commands.id();
             path_tile_storage.set(&tile_position, Some(tile_entity));
         }
     }
@@ -88,8 +167,8 @@ pub fn setup_path_tilemap(mut commands: Commands, asset_server: Res<AssetServer>
 
     let mut tilemap_entity_transform =
         bevy_ecs_tilemap::helpers::get_centered_transform_2d(&tilemap_size, &tile_size, 0.0);
-    tilemap_entity_transform.translation.x += (MAP_WIDTH / 2) as f32;
-    tilemap_entity_transform.translation.y += (MAP_HEIGHT / 2) as f32;
+    tilemap_entity_transform.translation.x += (map.width / 2) as f32;
+    tilemap_entity_transform.translation.y += (map.height / 2) as f32;
 
     commands
         .entity(path_tilemap_entity)
@@ -112,8 +191,8 @@ pub fn setup_path_tilemap(mut commands: Commands, asset_server: Res<AssetServer>
 pub fn setup_costs_tilemap(mut commands: Commands, asset_server: Res<AssetServer>, map: Res<Map>) {
     println!("Setup Costs TileMap...");
     let tilemap_size = TilemapSize {
-        x: MAP_WIDTH as u32,
-        y: MAP_HEIGHT as u32,
+        x: map.width as u32,
+        y: map.height as u32,
     };
     let tilemap_entity = commands.spawn().id(); // Need one per layer.
     let mut tile_storage = TileStorage::empty(tilemap_size); // Need one per tilemap_entity.
@@ -163,8 +242,8 @@ pub fn setup_costs_tilemap(mut commands: Commands, asset_server: Res<AssetServer
 
     let mut tilemap_entity_transform =
         bevy_ecs_tilemap::helpers::get_centered_transform_2d(&tilemap_size, &tile_size, 0.0);
-    tilemap_entity_transform.translation.x += (MAP_WIDTH / 2) as f32;
-    tilemap_entity_transform.translation.y += (MAP_HEIGHT / 2) as f32;
+    tilemap_entity_transform.translation.x += (map.width / 2) as f32;
+    tilemap_entity_transform.translation.y += (map.height / 2) as f32;
 
     commands
         .entity(tilemap_entity)
@@ -187,7 +266,7 @@ pub fn setup_costs_tilemap(mut commands: Commands, asset_server: Res<AssetServer
 pub fn draw_path_tilemap(
     mut map_updated_event_reader: EventReader<MapUpdatedEvent>,
     tile_storage_query: Query<&TileStorage, With<PathTileMap>>,
-    mut tile_texture_query: Query<&mut TileTexture, With<PathTile>>,
+    mut tile_texture_query: Query<(&mut TileTexture, &mut TileColor), With<PathTile>>,
     map: Res<Map>,
     game_state: Res<GameState>,
 ) {
@@ -197,7 +276,10 @@ pub fn draw_path_tilemap(
                 for i in 0..map.width {
                     let tile_position = TilePos::new(i as u32, j as u32);
                     if let Some(tile_entity) = tile_storage.get(&tile_position) {
-                        if let Ok(mut tile_texture) = tile_texture_query.get_mut(tile_entity) {
+                        if let Ok((mut tile_texture, mut tile_color)) =
+                            tile_texture_query.get_mut(tile_entity)
+                        {
+                            tile_color.0 = Color::WHITE;
                             let index = map.xy_idx(i, j);
                             if map.blocked[index] {
                                 tile_texture.0 = 2;
@@ -212,7 +294,7 @@ pub fn draw_path_tilemap(
             for point in &game_state.tested {
                 let tile_position = TilePos::new(point.0 as u32, point.1 as u32);
                 if let Some(tile_entity) = tile_storage.get(&tile_position) {
-                    if let Ok(mut tile_texture) = tile_texture_query.get_mut(tile_entity) {
+                    if let Ok((mut tile_texture, _)) = tile_texture_query.get_mut(tile_entity) {
                         tile_texture.0 = 6;
                     }
                 }
@@ -221,51 +303,300 @@ pub fn draw_path_tilemap(
             for point in &game_state.searched {
                 let tile_position = TilePos::new(point.0 as u32, point.1 as u32);
                 if let Some(tile_entity) = tile_storage.get(&tile_position) {
-                    if let Ok(mut tile_texture) = tile_texture_query.get_mut(tile_entity) {
+                    if let Ok((mut tile_texture, _)) = tile_texture_query.get_mut(tile_entity) {
                         tile_texture.0 = 7;
                     }
                 }
             }
 
-            if !game_state.path.is_empty() {
-                for i in 1..game_state.step {
-                    let point = game_state.path[i];
-                    let tile_position = TilePos::new(point.0 as u32, point.1 as u32);
-                    if let Some(tile_entity) = tile_storage.get(&tile_position) {
-                        if let Ok(mut tile_texture) = tile_texture_query.get_mut(tile_entity) {
-                            tile_texture.0 = 5;
-                        }
+            let backward_searched = game_state
+                .expanded_nodes
+                .iter()
+                .filter(|node| node.backward)
+                .map(|node| (node.position, BACKWARD_SEARCHED_COLOR));
+            let backward_tested = game_state
+                .backward_tested
+                .iter()
+                .map(|point| (*point, BACKWARD_TESTED_COLOR));
+            for (point, color) in backward_tested.chain(backward_searched) {
+                let tile_position = TilePos::new(point.0 as u32, point.1 as u32);
+                if let Some(tile_entity) = tile_storage.get(&tile_position) {
+                    if let Ok((mut tile_texture, mut tile_color)) =
+                        tile_texture_query.get_mut(tile_entity)
+                    {
+                        tile_texture.0 = 1;
+                        tile_color.0 = color;
+                    }
+                }
+            }
+
+            // tints the white tile, so the heat map replaces the searched colour
+            let heat_values = heat_values(&game_state);
+            for (node, value) in game_state.expanded_nodes.iter().zip(heat_values) {
+                let tile_position = TilePos::new(node.position.0 as u32, node.position.1 as u32);
+                if let Some(tile_entity) = tile_storage.get(&tile_position) {
+                    if let Ok((mut tile_texture, mut tile_color)) =
+                        tile_texture_query.get_mut(tile_entity)
+                    {
+                        tile_texture.0 = 1;
+                        tile_color.0 = heat_color(value);
                     }
+                }
+            }
 
+            // the node a stepped search expanded last, then the path once it is found, unless
+            // `draw_path_lines` draws it as lines instead
+            let path_tiles: &[Position] = if game_state.draws_path_as_lines() {
+                &[]
+            } else {
+                game_state.path.as_slice()
+            };
+            for point in game_state.current.iter().chain(path_tiles) {
+                let tile_position = TilePos::new(point.0 as u32, point.1 as u32);
+                if let Some(tile_entity) = tile_storage.get(&tile_position) {
+                    if let Ok((mut tile_texture, mut tile_color)) =
+                        tile_texture_query.get_mut(tile_entity)
+                    {
+                        tile_texture.0 = 5;
+                        tile_color.0 = Color::WHITE;
+                    }
                 }
-                println!("path step:{:?}", game_state.step);
             }
-            // for point in &game_state.path {
-            //     let tile_position = TilePos::new(point.0 as u32, point.1 as u32);
-            //     if let Some(tile_entity) = tile_storage.get(&tile_position) {
-            //         if let Ok(mut tile_texture) = tile_texture_query.get_mut(tile_entity) {
-            //             tile_texture.0 = 5;
-            //         }
-            //     }
-            // }
             let start: &Position = &game_state.start;
             let tile_position = TilePos::new(start.0 as u32, start.1 as u32);
             if let Some(tile_entity) = tile_storage.get(&tile_position) {
-                if let Ok(mut tile_texture) = tile_texture_query.get_mut(tile_entity) {
+                if let Ok((mut tile_texture, mut tile_color)) =
+                    tile_texture_query.get_mut(tile_entity)
+                {
                     tile_texture.0 = 3;
+                    tile_color.0 = Color::WHITE;
                 }
             }
             let goal: &Position = &game_state.goal;
             let tile_position = TilePos::new(goal.0 as u32, goal.1 as u32);
             if let Some(tile_entity) = tile_storage.get(&tile_position) {
-                if let Ok(mut tile_texture) = tile_texture_query.get_mut(tile_entity) {
+                if let Ok((mut tile_texture, mut tile_color)) =
+                    tile_texture_query.get_mut(tile_entity)
+                {
                     tile_texture.0 = 4;
+                    tile_color.0 = Color::WHITE;
                 }
             }
         }
     }
 }
 
+// Every algorithm gets its own lane inside a tile, so paths that share tiles stay visible
+pub fn draw_comparison(
+    mut map_updated_event_reader: EventReader<MapUpdatedEvent>,
+    marker_query: Query<Entity, With<ComparisonMarker>>,
+    game_state: Res<GameState>,
+    mut commands: Commands,
+) {
+    if map_updated_event_reader.iter().last().is_none() {
+        return;
+    }
+    for entity in marker_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let lanes = (game_state.comparison.len() as f32).sqrt().ceil().max(1.0) as usize;
+    let lane_size = TILE_SIZE as f32 / lanes as f32;
+    for (index, compared) in game_state.comparison.iter().enumerate() {
+        let lane = Vec2::new((index % lanes) as f32, (index / lanes) as f32);
+        let offset = (lane + 0.5) * lane_size - TILE_SIZE as f32 / 2.0;
+        let color = comparison_color(compared.algorithm);
+        let path = compared.result.path.as_deref().unwrap_or_default();
+        if compared.algorithm.any_angle() {
+            for pair in path.windows(2) {
+                let from = index_to_world_position(pair[0].0, pair[0].1) + offset;
+                let to = index_to_world_position(pair[1].0, pair[1].1) + offset;
+                commands
+                    .spawn_bundle(line_sprite(from, to, lane_size * 0.4, color, 2.0))
+                    .insert(ComparisonMarker {});
+            }
+            continue;
+        }
+        for point in path {
+            let position = index_to_world_position(point.0, point.1) + offset;
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::splat(lane_size * 0.8)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(position.x, position.y, 2.0),
+                    ..default()
+                })
+                .insert(ComparisonMarker {});
+        }
+    }
+}
+
+// the waypoints of an any-angle or smoothed path joined by straight lines over the tiles, or the
+// smoothed curve
+pub fn draw_path_lines(
+    mut map_updated_event_reader: EventReader<MapUpdatedEvent>,
+    marker_query: Query<Entity, With<PathLineMarker>>,
+    game_state: Res<GameState>,
+    mut commands: Commands,
+) {
+    if map_updated_event_reader.iter().last().is_none() {
+        return;
+    }
+    for entity in marker_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    if !game_state.draws_path_as_lines() {
+        return;
+    }
+
+    let points: Vec<Vec2> = if game_state.path_curve.is_empty() {
+        game_state
+            .path
+            .iter()
+            .map(|point| index_to_world_position(point.0, point.1))
+            .collect()
+    } else {
+        game_state
+            .path_curve
+            .iter()
+            .map(|&(x, y)| (Vec2::new(x, y) + 0.5) * TILE_SIZE as f32)
+            .collect()
+    };
+    for pair in points.windows(2) {
+        commands
+            .spawn_bundle(line_sprite(
+                pair[0],
+                pair[1],
+                4.0,
+                Color::rgb(0.2, 0.4, 0.9),
+                2.0,
+            ))
+            .insert(PathLineMarker {});
+    }
+}
+
+// an arrow on every tile that can reach the goal, pointing at the tile to go to next
+pub fn draw_flow_field(
+    mut flow_field_changed_event_reader: EventReader<FlowFieldChangedEvent>,
+    marker_query: Query<Entity, With<FlowFieldMarker>>,
+    flow_field: Res<FlowField>,
+    game_state: Res<GameState>,
+    mut commands: Commands,
+) {
+    // a hidden field has nothing to redraw until it is switched on
+    let toggled = flow_field_changed_event_reader.iter().last().is_some();
+    let redraw = toggled || (game_state.show_flow_field && flow_field.is_changed());
+    if !redraw {
+        return;
+    }
+    for entity in marker_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    if !game_state.show_flow_field {
+        return;
+    }
+
+    let color = Color::rgb(0.2, 0.2, 0.2);
+    let length = TILE_SIZE as f32 * 0.3;
+    for y in 0..flow_field.height {
+        for x in 0..flow_field.width {
+            let (dx, dy) = match flow_field.direction(Position(x, y)) {
+                Some(direction) => direction,
+                None => continue,
+            };
+            let center = index_to_world_position(x, y);
+            let direction = Vec2::new(dx as f32, dy as f32).normalize_or_zero();
+            let tip = center + direction * length;
+            commands
+                .spawn_bundle(line_sprite(
+                    center - direction * length,
+                    tip,
+                    2.0,
+                    color,
+                    1.5,
+                ))
+                .insert(FlowFieldMarker {});
+            for side in [-1.0, 1.0] {
+                let back = Vec2::from_angle(side * 2.5).rotate(direction) * length * 0.6;
+                commands
+                    .spawn_bundle(line_sprite(tip, tip + back, 2.0, color, 1.5))
+                    .insert(FlowFieldMarker {});
+            }
+        }
+    }
+}
+
+// f on the first line, g and h on the second, and a stub pointing from the tile towards the
+// node it was reached from
+pub fn draw_node_stats(
+    mut map_updated_event_reader: EventReader<MapUpdatedEvent>,
+    marker_query: Query<Entity, With<NodeStatsMarker>>,
+    game_state: Res<GameState>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    if map_updated_event_reader.iter().last().is_none() {
+        return;
+    }
+    for entity in marker_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    if !game_state.show_node_stats {
+        return;
+    }
+
+    let algorithm = game_state.pathfinding_algorithm;
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans/FiraSans-Bold.ttf"),
+        font_size: 8.0,
+        color: Color::rgb(0.1, 0.1, 0.1),
+    };
+
+    for node in &game_state.expanded_nodes {
+        let center = index_to_world_position(node.position.0, node.position.1);
+        commands
+            .spawn_bundle(Text2dBundle {
+                text: Text::from_section(
+                    format!(
+                        "{}\n{} {}",
+                        format_cost(node.f(), algorithm),
+                        format_cost(node.g, algorithm),
+                        format_cost(node.h, algorithm)
+                    ),
+                    text_style.clone(),
+                )
+                .with_alignment(TextAlignment::CENTER),
+                transform: Transform::from_xyz(center.x, center.y, 3.0),
+                ..default()
+            })
+            .insert(NodeStatsMarker {});
+
+        if let Some(parent) = node.parent {
+            let direction = Vec2::new(
+                (parent.0 - node.position.0) as f32,
+                (parent.1 - node.position.1) as f32,
+            )
+            .normalize_or_zero();
+            let position = center + direction * (TILE_SIZE as f32 * 0.35);
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgb(0.1, 0.1, 0.1),
+                        custom_size: Some(Vec2::new(TILE_SIZE as f32 * 0.3, 2.0)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(position.x, position.y, 3.0)
+                        .with_rotation(Quat::from_rotation_z(direction.y.atan2(direction.x))),
+                    ..default()
+                })
+                .insert(NodeStatsMarker {});
+        }
+    }
+}
+
 pub fn update_cost_tilemap(
     mut map_updated_event_reader: EventReader<MapUpdatedEvent>,
     mut cost_tiles_query: Query<(&TilePos, &mut Text), With<CostsTile>>,
@@ -290,17 +621,9 @@ pub fn show_hide_cost_tilemap(
     game_state: Res<GameState>,
 ) {
     for _ in pathfinding_algorithm_changed_event_reader.iter() {
-        match game_state.pathfinding_algorithm {
-            PathfindingAlgorithm::BFS | PathfindingAlgorithm::Jps => {
-                for mut cost_tile_visibility in cost_tile_query.iter_mut() {
-                    cost_tile_visibility.is_visible = false;
-                }
-            }
-            _ => {
-                for mut cost_tile_visibility in cost_tile_query.iter_mut() {
-                    cost_tile_visibility.is_visible = true;
-                }
-            }
+        let is_visible = game_state.pathfinding_algorithm.uses_costs();
+        for mut cost_tile_visibility in cost_tile_query.iter_mut() {
+            cost_tile_visibility.is_visible = is_visible;
         }
     }
 }
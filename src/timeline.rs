@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+
+use crate::{ExpandedNode, Map, Position, SearchResult, SearchSnapshot, SearchSteps};
+
+/// What one expansion changed: the node that was expanded and the nodes it opened.
+#[derive(Clone, Debug)]
+struct TimelineStep {
+    expanded: ExpandedNode,
+    opened: Vec<Position>,
+    backward_opened: Vec<Position>,
+}
+
+/// A recording of a search that can be played back and forth.
+///
+/// The search only runs as far as the timeline has been moved, and every expansion is kept as
+/// the change it made, so any earlier step can be shown again without searching twice.
+pub struct SearchTimeline {
+    /// The live search, `None` once it is over.
+    search: Option<Box<dyn SearchSteps>>,
+    initial_open: Vec<Position>,
+    initial_backward_open: Vec<Position>,
+    steps: Vec<TimelineStep>,
+    /// Open nodes of the live search after the last recorded step.
+    open: HashSet<Position>,
+    backward_open: HashSet<Position>,
+    result: Option<SearchResult>,
+    position: usize,
+}
+
+impl SearchTimeline {
+    #[must_use]
+    pub fn new(search: Box<dyn SearchSteps>) -> SearchTimeline {
+        let initial_open = search.open();
+        let initial_backward_open = search.backward_open();
+        SearchTimeline {
+            open: initial_open.iter().copied().collect(),
+            backward_open: initial_backward_open.iter().copied().collect(),
+            initial_open,
+            initial_backward_open,
+            search: Some(search),
+            steps: Vec::new(),
+            result: None,
+            position: 0,
+        }
+    }
+
+    /// Number of expansions shown.
+    #[must_use]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Number of expansions recorded so far.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Whether the search is over, so `len` is final.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.search.is_none()
+    }
+
+    /// Whether the last step of a finished search is shown.
+    #[must_use]
+    pub fn at_end(&self) -> bool {
+        self.is_complete() && self.position == self.steps.len()
+    }
+
+    /// What the search produced, once it is over.
+    #[must_use]
+    pub fn result(&self) -> Option<&SearchResult> {
+        self.result.as_ref()
+    }
+
+    /// Runs the search to the end without moving the timeline.
+    pub fn complete(&mut self, map: &Map) {
+        while self.record_step(map) {}
+    }
+
+    /// Shows the first `position` expansions, running the search further when it has not got that
+    /// far. Stops at the end of the search.
+    pub fn seek(&mut self, map: &Map, position: usize) {
+        while self.steps.len() < position && self.record_step(map) {}
+        self.position = position.min(self.steps.len());
+    }
+
+    pub fn step_forward(&mut self, map: &Map) {
+        self.seek(map, self.position + 1);
+    }
+
+    pub fn step_back(&mut self) {
+        self.position = self.position.saturating_sub(1);
+    }
+
+    /// What the search looked like after `position` expansions.
+    #[must_use]
+    pub fn snapshot(&self) -> SearchSnapshot {
+        let steps = &self.steps[..self.position];
+        let expanded: Vec<ExpandedNode> = steps.iter().map(|step| step.expanded).collect();
+        let closed: Vec<Position> = expanded.iter().map(|node| node.position).collect();
+        // each half of a bidirectional search has its own closed nodes
+        let closed_by = |backward: bool| -> HashSet<Position> {
+            expanded
+                .iter()
+                .filter(|node| node.backward == backward)
+                .map(|node| node.position)
+                .collect()
+        };
+        let open = frontier(
+            &self.initial_open,
+            steps.iter().flat_map(|step| &step.opened),
+            &closed_by(false),
+        );
+        let backward_open = frontier(
+            &self.initial_backward_open,
+            steps.iter().flat_map(|step| &step.backward_opened),
+            &closed_by(true),
+        );
+
+        SearchSnapshot {
+            current: closed.last().copied(),
+            open,
+            backward_open,
+            closed,
+            expanded,
+            path: if self.at_end() {
+                self.result.as_ref().and_then(|result| result.path.clone())
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Advances the live search by one expansion and records it. Returns whether it goes on.
+    fn record_step(&mut self, map: &Map) -> bool {
+        let Some(search) = self.search.as_mut() else {
+            return false;
+        };
+        let status = search.step(map);
+
+        if let Some(expanded) = search.current() {
+            let open: HashSet<Position> = search.open().into_iter().collect();
+            let backward_open: HashSet<Position> = search.backward_open().into_iter().collect();
+            let opened = open.difference(&self.open).copied().collect();
+            let backward_opened = backward_open
+                .difference(&self.backward_open)
+                .copied()
+                .collect();
+            self.open = open;
+            self.backward_open = backward_open;
+            self.steps.push(TimelineStep {
+                expanded,
+                opened,
+                backward_opened,
+            });
+        }
+
+        if status.is_done() {
+            if let Some(search) = self.search.take() {
+                self.result = Some(search.into_result(map));
+            }
+            return false;
+        }
+        true
+    }
+}
+
+/// The nodes opened so far that are not closed yet, each once, in the order they were opened.
+fn frontier<'a>(
+    initial: &'a [Position],
+    opened: impl Iterator<Item = &'a Position>,
+    closed: &HashSet<Position>,
+) -> Vec<Position> {
+    let mut seen = HashSet::new();
+    initial
+        .iter()
+        .chain(opened)
+        .copied()
+        .filter(|node| !closed.contains(node) && seen.insert(*node))
+        .collect()
+}
@@ -2,9 +2,17 @@ use bevy::prelude::*;
 use bevy::ui::Display::Flex;
 
 use super::{
-    ClearEvent, CycleAlgorithmLeftEvent, CycleAlgorithmRightEvent, GameState, PathfindingAlgorithm,
-    PathfindingAlgorithmChangedEvent, PathfindingAlgorithmSelectionChangedEvent, PlacementMode,
-    ResetEvent, SolveEvent, StepEvent,
+    comparison_color, euclidean_length, format_cost, heat_color, ClearEvent, CompareEvent,
+    CornerCuttingChangedEvent, CostModelChangedEvent, CycleAlgorithmLeftEvent,
+    CycleAlgorithmRightEvent, CycleCornerCuttingEvent, CycleCostModelEvent, CycleHeatMapEvent,
+    CycleMultiAgentPlannerEvent, CyclePlaybackSpeedEvent, CycleSmoothingEvent, CycleWeightEvent,
+    DiagonalsChangedEvent, FlowFieldChangedEvent, GameState, HeatMapChangedEvent, HeatMapMode,
+    LoadMapEvent, Map, MapUpdatedEvent, MultiAgentPlanner, MultiAgentPlannerChangedEvent,
+    MultiAgentPlayback, NodeStatsChangedEvent, PathfindingAlgorithmChangedEvent,
+    PathfindingAlgorithmSelectionChangedEvent, PlacementMode, PlanAgentsEvent, ResetEvent,
+    RewindEvent, SaveMapEvent, SearchPlayback, SeekEvent, SmoothingChangedEvent, SolveEvent,
+    SpawnAgentEvent, StepBackEvent, StepEvent, ToggleDiagonalsEvent, ToggleFlowFieldEvent,
+    ToggleNodeStatsEvent, TogglePlayEvent, WeightChangedEvent, STRAIGHT_COST,
 };
 
 const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
@@ -30,18 +38,139 @@ pub struct IncreaseCostButton {}
 #[derive(Component, Debug)]
 pub struct DecreaseCostButton {}
 
+#[derive(Component, Debug)]
+pub struct AgentPairButton {}
+
+#[derive(Component, Debug)]
+pub struct MultiAgentPlannerButton {}
+
+#[derive(Component, Debug)]
+pub struct MultiAgentPlannerText {}
+
+#[derive(Component, Debug)]
+pub struct PlanAgentsButton {}
+
+#[derive(Component, Debug)]
+pub struct RewindButton {}
+
+#[derive(Component, Debug)]
+pub struct StepBackButton {}
+
 #[derive(Component, Debug)]
 pub struct StepButton {}
 
+#[derive(Component, Debug)]
+pub struct PlayButton {}
+
+#[derive(Component, Debug)]
+pub struct PlayText {}
+
+#[derive(Component, Debug)]
+pub struct PlaybackSpeedButton {}
+
+#[derive(Component, Debug)]
+pub struct PlaybackSpeedText {}
+
+/// Clicking or dragging along it scrubs through the search.
+#[derive(Component, Debug)]
+pub struct TimelineBar {}
+
+/// The part of `TimelineBar` that has been played.
+#[derive(Component, Debug)]
+pub struct TimelineFill {}
+
+#[derive(Component, Debug)]
+pub struct TimelineText {}
+
 #[derive(Component, Debug)]
 pub struct SolveButton {}
 
+#[derive(Component, Debug)]
+pub struct CompareButton {}
+
+#[derive(Component, Debug)]
+pub struct AgentButton {}
+
+/// Shown while there is a comparison, with one line of numbers per algorithm.
+#[derive(Component, Debug)]
+pub struct ComparisonPanel {}
+
+#[derive(Component, Debug)]
+pub struct ComparisonText {}
+
 #[derive(Component, Debug)]
 pub struct ResetButton {}
 
 #[derive(Component, Debug)]
 pub struct ClearButton {}
 
+#[derive(Component, Debug)]
+pub struct SaveButton {}
+
+#[derive(Component, Debug)]
+pub struct LoadButton {}
+
+#[derive(Component, Debug)]
+pub struct CostModelButton {}
+
+#[derive(Component, Debug)]
+pub struct CostModelText {}
+
+#[derive(Component, Debug)]
+pub struct DiagonalsButton {}
+
+#[derive(Component, Debug)]
+pub struct DiagonalsText {}
+
+#[derive(Component, Debug)]
+pub struct NodeStatsButton {}
+
+#[derive(Component, Debug)]
+pub struct NodeStatsText {}
+
+#[derive(Component, Debug)]
+pub struct FlowFieldButton {}
+
+#[derive(Component, Debug)]
+pub struct FlowFieldText {}
+
+#[derive(Component, Debug)]
+pub struct HeatMapButton {}
+
+#[derive(Component, Debug)]
+pub struct HeatMapText {}
+
+#[derive(Component, Debug)]
+pub struct WeightButton {}
+
+#[derive(Component, Debug)]
+pub struct WeightText {}
+
+#[derive(Component, Debug)]
+pub struct SmoothingButton {}
+
+#[derive(Component, Debug)]
+pub struct SmoothingText {}
+
+/// Shown while the heat map is on: what it colours by and the values at both ends of the scale.
+#[derive(Component, Debug)]
+pub struct HeatMapLegend {}
+
+#[derive(Component, Debug)]
+pub struct HeatMapLegendTitle {}
+
+#[derive(Component, Debug)]
+pub struct HeatMapLegendLowText {}
+
+#[derive(Component, Debug)]
+pub struct HeatMapLegendHighText {}
+
+#[derive(Component, Debug)]
+pub struct CornerCuttingButton {}
+
+#[derive(Component, Debug)]
+pub struct CornerCuttingText {}
+
 #[derive(Component, Debug)]
 pub struct CurrentAlgorithmText {}
 
@@ -135,7 +264,7 @@ pub fn setup_user_interface(mut commands: Commands, asset_server: Res<AssetServe
             style: Style {
                 display: Flex,
                 flex_direction: FlexDirection::Column,
-                size: Size::new(Val::Percent(100.0), Val::Percent(15.0)),
+                size: Size::new(Val::Percent(100.0), Val::Percent(22.0)),
                 justify_content: JustifyContent::Center,
                 ..default()
             },
@@ -214,6 +343,87 @@ pub fn setup_user_interface(mut commands: Commands, asset_server: Res<AssetServe
         .insert(Name::new("Top Buttons Container"))
         .id();
 
+    let timeline_container = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                display: Flex,
+                flex_direction: FlexDirection::Row,
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Timeline Container"))
+        .id();
+
+    // Rewind Button
+    let rewind_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Rewind Button Container"))
+        .id();
+
+    let rewind_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Rewind Button"))
+        .insert(RewindButton {})
+        .id();
+
+    let rewind_button_text = commands
+        .spawn_bundle(TextBundle::from_section(
+            "Rewind",
+            button_text_style.clone(),
+        ))
+        .id();
+
+    commands
+        .entity(rewind_button)
+        .push_children(&[rewind_button_text]);
+    commands
+        .entity(rewind_button_container)
+        .push_children(&[rewind_button]);
+
+    // Step Back Button
+    let step_back_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Step Back Button Container"))
+        .id();
+
+    let step_back_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Step Back Button"))
+        .insert(StepBackButton {})
+        .id();
+
+    let step_back_button_text = commands
+        .spawn_bundle(TextBundle::from_section("Back", button_text_style.clone()))
+        .id();
+
+    commands
+        .entity(step_back_button)
+        .push_children(&[step_back_button_text]);
+    commands
+        .entity(step_back_button_container)
+        .push_children(&[step_back_button]);
+
     // Step Button
     let step_button_container = commands
         .spawn_bundle(NodeBundle {
@@ -245,6 +455,113 @@ pub fn setup_user_interface(mut commands: Commands, asset_server: Res<AssetServe
         .entity(step_button_container)
         .push_children(&[step_button]);
 
+    // Play Button
+    let play_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Play Button Container"))
+        .id();
+
+    let play_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Play Button"))
+        .insert(PlayButton {})
+        .id();
+
+    let play_button_text = commands
+        .spawn_bundle(TextBundle::from_section("Play", button_text_style.clone()))
+        .insert(PlayText {})
+        .id();
+
+    commands
+        .entity(play_button)
+        .push_children(&[play_button_text]);
+    commands
+        .entity(play_button_container)
+        .push_children(&[play_button]);
+
+    // Playback Speed Button
+    let playback_speed_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Playback Speed Button Container"))
+        .id();
+
+    let playback_speed_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Playback Speed Button"))
+        .insert(PlaybackSpeedButton {})
+        .id();
+
+    let playback_speed_button_text = commands
+        .spawn_bundle(TextBundle::from_section(
+            "Speed: 10/s",
+            button_text_style.clone(),
+        ))
+        .insert(PlaybackSpeedText {})
+        .id();
+
+    commands
+        .entity(playback_speed_button)
+        .push_children(&[playback_speed_button_text]);
+    commands
+        .entity(playback_speed_button_container)
+        .push_children(&[playback_speed_button]);
+
+    // Timeline Bar
+    let timeline_bar = commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                display: Flex,
+                flex_direction: FlexDirection::Row,
+                size: Size::new(Val::Px(320.0), Val::Px(16.0)),
+                margin: UiRect::new(Val::Px(16.0), Val::Px(16.0), Val::Px(16.0), Val::Px(16.0)),
+                ..default()
+            },
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Timeline Bar"))
+        .insert(TimelineBar {})
+        .id();
+
+    let timeline_fill = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                ..default()
+            },
+            color: PRESSED_BUTTON.into(),
+            ..default()
+        })
+        .insert(Name::new("Timeline Fill"))
+        .insert(TimelineFill {})
+        .id();
+
+    let timeline_text = commands
+        .spawn_bundle(TextBundle::from_section("0 / 0", button_text_style.clone()))
+        .insert(Name::new("Timeline Text"))
+        .insert(TimelineText {})
+        .id();
+
+    commands
+        .entity(timeline_bar)
+        .push_children(&[timeline_fill]);
+
     // Solve Button
     let solve_button_container = commands
         .spawn_bundle(NodeBundle {
@@ -276,6 +593,102 @@ pub fn setup_user_interface(mut commands: Commands, asset_server: Res<AssetServe
         .entity(solve_button_container)
         .push_children(&[solve_button]);
 
+    // Agent Button
+    let agent_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Agent Button Container"))
+        .id();
+
+    let agent_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Agent Button"))
+        .insert(AgentButton {})
+        .id();
+
+    let agent_button_text = commands
+        .spawn_bundle(TextBundle::from_section("Walk", button_text_style.clone()))
+        .id();
+
+    commands
+        .entity(agent_button)
+        .push_children(&[agent_button_text]);
+    commands
+        .entity(agent_button_container)
+        .push_children(&[agent_button]);
+
+    // Compare Button
+    let compare_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Compare Button Container"))
+        .id();
+
+    let compare_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Compare Button"))
+        .insert(CompareButton {})
+        .id();
+
+    let compare_button_text = commands
+        .spawn_bundle(TextBundle::from_section(
+            "Compare",
+            button_text_style.clone(),
+        ))
+        .id();
+
+    commands
+        .entity(compare_button)
+        .push_children(&[compare_button_text]);
+    commands
+        .entity(compare_button_container)
+        .push_children(&[compare_button]);
+
+    // Comparison Panel
+    let comparison_panel = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                display: Display::None,
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(16.0),
+                    bottom: Val::Percent(12.0),
+                    ..default()
+                },
+                padding: UiRect::new(Val::Px(16.0), Val::Px(16.0), Val::Px(16.0), Val::Px(16.0)),
+                ..default()
+            },
+            color: Color::rgba(0.15, 0.15, 0.15, 0.9).into(),
+            ..default()
+        })
+        .insert(Name::new("Comparison Panel"))
+        .insert(ComparisonPanel {})
+        .id();
+
+    let comparison_text = commands
+        .spawn_bundle(TextBundle::from_section("", button_text_style.clone()))
+        .insert(Name::new("Comparison Text"))
+        .insert(ComparisonText {})
+        .id();
+
+    commands
+        .entity(comparison_panel)
+        .push_children(&[comparison_text]);
+
     // Reset Button
     let reset_button_container = commands
         .spawn_bundle(NodeBundle {
@@ -338,12 +751,427 @@ pub fn setup_user_interface(mut commands: Commands, asset_server: Res<AssetServe
         .entity(clear_button_container)
         .push_children(&[clear_button]);
 
-    // Algorithm Cycler
-    let algorithm_cycler_container = commands
+    // Save Button
+    let save_button_container = commands
         .spawn_bundle(NodeBundle {
-            style: Style {
-                display: Flex,
-                flex_direction: FlexDirection::Row,
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Save Button Container"))
+        .id();
+
+    let save_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Save Button"))
+        .insert(SaveButton {})
+        .id();
+
+    let save_button_text = commands
+        .spawn_bundle(TextBundle::from_section("Save", button_text_style.clone()))
+        .id();
+
+    commands
+        .entity(save_button)
+        .push_children(&[save_button_text]);
+    commands
+        .entity(save_button_container)
+        .push_children(&[save_button]);
+
+    // Load Button
+    let load_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Load Button Container"))
+        .id();
+
+    let load_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Load Button"))
+        .insert(LoadButton {})
+        .id();
+
+    let load_button_text = commands
+        .spawn_bundle(TextBundle::from_section("Load", button_text_style.clone()))
+        .id();
+
+    commands
+        .entity(load_button)
+        .push_children(&[load_button_text]);
+    commands
+        .entity(load_button_container)
+        .push_children(&[load_button]);
+
+    // Cost Model Button
+    let cost_model_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Cost Model Button Container"))
+        .id();
+
+    let cost_model_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Cost Model Button"))
+        .insert(CostModelButton {})
+        .id();
+
+    let cost_model_button_text = commands
+        .spawn_bundle(TextBundle::from_section(
+            "Octile",
+            button_text_style.clone(),
+        ))
+        .insert(CostModelText {})
+        .id();
+
+    commands
+        .entity(cost_model_button)
+        .push_children(&[cost_model_button_text]);
+    commands
+        .entity(cost_model_button_container)
+        .push_children(&[cost_model_button]);
+
+    // Weight Button
+    let weight_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Weight Button Container"))
+        .id();
+
+    let weight_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Weight Button"))
+        .insert(WeightButton {})
+        .id();
+
+    let weight_button_text = commands
+        .spawn_bundle(TextBundle::from_section(
+            "Weight: 2.00",
+            button_text_style.clone(),
+        ))
+        .insert(WeightText {})
+        .id();
+
+    commands
+        .entity(weight_button)
+        .push_children(&[weight_button_text]);
+    commands
+        .entity(weight_button_container)
+        .push_children(&[weight_button]);
+
+    // Diagonals Button
+    let diagonals_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Diagonals Button Container"))
+        .id();
+
+    let diagonals_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Diagonals Button"))
+        .insert(DiagonalsButton {})
+        .id();
+
+    let diagonals_button_text = commands
+        .spawn_bundle(TextBundle::from_section("8-Way", button_text_style.clone()))
+        .insert(DiagonalsText {})
+        .id();
+
+    commands
+        .entity(diagonals_button)
+        .push_children(&[diagonals_button_text]);
+    commands
+        .entity(diagonals_button_container)
+        .push_children(&[diagonals_button]);
+
+    // Node Stats Button
+    let node_stats_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Node Stats Button Container"))
+        .id();
+
+    let node_stats_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Node Stats Button"))
+        .insert(NodeStatsButton {})
+        .id();
+
+    let node_stats_button_text = commands
+        .spawn_bundle(TextBundle::from_section(
+            "Stats: Off",
+            button_text_style.clone(),
+        ))
+        .insert(NodeStatsText {})
+        .id();
+
+    commands
+        .entity(node_stats_button)
+        .push_children(&[node_stats_button_text]);
+    commands
+        .entity(node_stats_button_container)
+        .push_children(&[node_stats_button]);
+
+    // Flow Field Button
+    let flow_field_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Flow Field Button Container"))
+        .id();
+
+    let flow_field_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Flow Field Button"))
+        .insert(FlowFieldButton {})
+        .id();
+
+    let flow_field_button_text = commands
+        .spawn_bundle(TextBundle::from_section(
+            "Flow: Off",
+            button_text_style.clone(),
+        ))
+        .insert(FlowFieldText {})
+        .id();
+
+    commands
+        .entity(flow_field_button)
+        .push_children(&[flow_field_button_text]);
+    commands
+        .entity(flow_field_button_container)
+        .push_children(&[flow_field_button]);
+
+    // Heat Map Button
+    let heat_map_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Heat Map Button Container"))
+        .id();
+
+    let heat_map_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Heat Map Button"))
+        .insert(HeatMapButton {})
+        .id();
+
+    let heat_map_button_text = commands
+        .spawn_bundle(TextBundle::from_section(
+            "Heat: Off",
+            button_text_style.clone(),
+        ))
+        .insert(HeatMapText {})
+        .id();
+
+    commands
+        .entity(heat_map_button)
+        .push_children(&[heat_map_button_text]);
+    commands
+        .entity(heat_map_button_container)
+        .push_children(&[heat_map_button]);
+
+    // Smoothing Button
+    let smoothing_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Smoothing Button Container"))
+        .id();
+
+    let smoothing_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Smoothing Button"))
+        .insert(SmoothingButton {})
+        .id();
+
+    let smoothing_button_text = commands
+        .spawn_bundle(TextBundle::from_section(
+            "Smooth: Off",
+            button_text_style.clone(),
+        ))
+        .insert(SmoothingText {})
+        .id();
+
+    commands
+        .entity(smoothing_button)
+        .push_children(&[smoothing_button_text]);
+    commands
+        .entity(smoothing_button_container)
+        .push_children(&[smoothing_button]);
+
+    // Heat Map Legend
+    let heat_map_legend = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                display: Display::None,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Px(16.0),
+                    bottom: Val::Percent(12.0),
+                    ..default()
+                },
+                padding: UiRect::new(Val::Px(16.0), Val::Px(16.0), Val::Px(16.0), Val::Px(16.0)),
+                ..default()
+            },
+            color: Color::rgba(0.15, 0.15, 0.15, 0.9).into(),
+            ..default()
+        })
+        .insert(Name::new("Heat Map Legend"))
+        .insert(HeatMapLegend {})
+        .id();
+
+    let heat_map_legend_title = commands
+        .spawn_bundle(TextBundle::from_section("", button_text_style.clone()))
+        .insert(HeatMapLegendTitle {})
+        .id();
+
+    let heat_map_legend_high_text = commands
+        .spawn_bundle(TextBundle::from_section("", button_text_style.clone()))
+        .insert(HeatMapLegendHighText {})
+        .id();
+
+    let heat_map_legend_low_text = commands
+        .spawn_bundle(TextBundle::from_section("", button_text_style.clone()))
+        .insert(HeatMapLegendLowText {})
+        .id();
+
+    // Columns stack from the bottom, so the low end of the scale comes first
+    let heat_map_legend_bar = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                margin: UiRect::new(Val::Px(0.0), Val::Px(0.0), Val::Px(8.0), Val::Px(8.0)),
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Heat Map Legend Bar"))
+        .id();
+
+    let segments = 16;
+    for segment in 0..segments {
+        let heat_map_legend_segment = commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Px(24.0), Val::Px(8.0)),
+                    ..default()
+                },
+                color: heat_color(segment as f32 / (segments - 1) as f32).into(),
+                ..default()
+            })
+            .id();
+        commands
+            .entity(heat_map_legend_bar)
+            .push_children(&[heat_map_legend_segment]);
+    }
+
+    commands.entity(heat_map_legend).push_children(&[
+        heat_map_legend_low_text,
+        heat_map_legend_bar,
+        heat_map_legend_high_text,
+        heat_map_legend_title,
+    ]);
+
+    // Corner Cutting Button
+    let corner_cutting_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Corner Cutting Button Container"))
+        .id();
+
+    let corner_cutting_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Corner Cutting Button"))
+        .insert(CornerCuttingButton {})
+        .id();
+
+    let corner_cutting_button_text = commands
+        .spawn_bundle(TextBundle::from_section(
+            "Corners: One Open",
+            button_text_style.clone(),
+        ))
+        .insert(CornerCuttingText {})
+        .id();
+
+    commands
+        .entity(corner_cutting_button)
+        .push_children(&[corner_cutting_button_text]);
+    commands
+        .entity(corner_cutting_button_container)
+        .push_children(&[corner_cutting_button]);
+
+    // Algorithm Cycler
+    let algorithm_cycler_container = commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                display: Flex,
+                flex_direction: FlexDirection::Row,
                 size: Size::new(Val::Auto, Val::Auto),
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
@@ -688,30 +1516,160 @@ pub fn setup_user_interface(mut commands: Commands, asset_server: Res<AssetServe
         .entity(decrease_cost_button_container)
         .push_children(&[decrease_cost_button]);
 
-    commands.entity(bottom_container).push_children(&[
-        open_button_container,
-        obstacle_button_container,
-        origin_button_container,
-        goal_button_container,
-        increase_cost_button_container,
-        decrease_cost_button_container,
-    ]);
+    // Agent Pair Button
+    let agent_pair_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Agent Pair Button Container"))
+        .id();
 
-    commands.entity(top_buttons_container).push_children(&[
+    let agent_pair_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Agent Pair Button"))
+        .insert(AgentPairButton {})
+        .id();
+
+    let agent_pair_button_text = commands
+        .spawn_bundle(TextBundle::from_section(
+            "Agents",
+            button_text_style.clone(),
+        ))
+        .id();
+
+    commands
+        .entity(agent_pair_button)
+        .push_children(&[agent_pair_button_text]);
+    commands
+        .entity(agent_pair_button_container)
+        .push_children(&[agent_pair_button]);
+
+    // Multi-Agent Planner Button
+    let multi_agent_planner_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Multi-Agent Planner Button Container"))
+        .id();
+
+    let multi_agent_planner_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Multi-Agent Planner Button"))
+        .insert(MultiAgentPlannerButton {})
+        .id();
+
+    let multi_agent_planner_button_text = commands
+        .spawn_bundle(TextBundle::from_section(
+            format!("Planner: {}", MultiAgentPlanner::Cbs.name()),
+            button_text_style.clone(),
+        ))
+        .insert(MultiAgentPlannerText {})
+        .id();
+
+    commands
+        .entity(multi_agent_planner_button)
+        .push_children(&[multi_agent_planner_button_text]);
+    commands
+        .entity(multi_agent_planner_button_container)
+        .push_children(&[multi_agent_planner_button]);
+
+    // Plan Agents Button
+    let plan_agents_button_container = commands
+        .spawn_bundle(NodeBundle {
+            style: button_container_style.clone(),
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Name::new("Plan Agents Button Container"))
+        .id();
+
+    let plan_agents_button = commands
+        .spawn_bundle(ButtonBundle {
+            style: button_style.clone(),
+            color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .insert(Name::new("Plan Agents Button"))
+        .insert(PlanAgentsButton {})
+        .id();
+
+    let plan_agents_button_text = commands
+        .spawn_bundle(TextBundle::from_section("Plan", button_text_style.clone()))
+        .id();
+
+    commands
+        .entity(plan_agents_button)
+        .push_children(&[plan_agents_button_text]);
+    commands
+        .entity(plan_agents_button_container)
+        .push_children(&[plan_agents_button]);
+
+    commands.entity(bottom_container).push_children(&[
+        open_button_container,
+        obstacle_button_container,
+        origin_button_container,
+        goal_button_container,
+        increase_cost_button_container,
+        decrease_cost_button_container,
+        agent_pair_button_container,
+        multi_agent_planner_button_container,
+        plan_agents_button_container,
+    ]);
+
+    commands.entity(timeline_container).push_children(&[
+        rewind_button_container,
+        step_back_button_container,
         step_button_container,
+        play_button_container,
+        playback_speed_button_container,
+        timeline_bar,
+        timeline_text,
+    ]);
+
+    commands.entity(top_buttons_container).push_children(&[
         solve_button_container,
+        agent_button_container,
+        compare_button_container,
         reset_button_container,
         clear_button_container,
+        save_button_container,
+        load_button_container,
+        cost_model_button_container,
+        diagonals_button_container,
+        node_stats_button_container,
+        flow_field_button_container,
+        heat_map_button_container,
+        smoothing_button_container,
+        corner_cutting_button_container,
+        weight_button_container,
         algorithm_cycler_container,
     ]);
 
-    commands
-        .entity(top_container)
-        .push_children(&[top_buttons_container, title_container]);
+    commands.entity(top_container).push_children(&[
+        timeline_container,
+        top_buttons_container,
+        title_container,
+    ]);
 
-    commands
-        .entity(root_container)
-        .push_children(&[bottom_container, spacer, top_container]);
+    commands.entity(root_container).push_children(&[
+        bottom_container,
+        spacer,
+        top_container,
+        comparison_panel,
+        heat_map_legend,
+    ]);
 }
 
 pub fn open_button_system(
@@ -851,6 +1809,87 @@ pub fn decrease_cost_button_system(
     }
 }
 
+pub fn agent_pair_button_system(
+    mut agent_pair_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<AgentPairButton>),
+    >,
+    mut game_state: ResMut<GameState>,
+) {
+    for (interaction, mut color) in agent_pair_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                game_state.placement_mode = PlacementMode::AgentPair;
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn multi_agent_planner_button_system(
+    mut cycle_multi_agent_planner_event_writer: EventWriter<CycleMultiAgentPlannerEvent>,
+    mut multi_agent_planner_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<MultiAgentPlannerButton>),
+    >,
+) {
+    for (interaction, mut color) in multi_agent_planner_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                cycle_multi_agent_planner_event_writer.send(CycleMultiAgentPlannerEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn update_multi_agent_planner_text_system(
+    mut multi_agent_planner_changed_event_reader: EventReader<MultiAgentPlannerChangedEvent>,
+    mut multi_agent_planner_text_query: Query<&mut Text, With<MultiAgentPlannerText>>,
+    multi_agent: Res<MultiAgentPlayback>,
+) {
+    for _ in multi_agent_planner_changed_event_reader.iter() {
+        for mut text in &mut multi_agent_planner_text_query {
+            text.sections[0].value = format!("Planner: {}", multi_agent.planner.name());
+        }
+    }
+}
+
+pub fn plan_agents_button_system(
+    mut plan_agents_event_writer: EventWriter<PlanAgentsEvent>,
+    mut plan_agents_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<PlanAgentsButton>),
+    >,
+) {
+    for (interaction, mut color) in plan_agents_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                plan_agents_event_writer.send(PlanAgentsEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
 pub fn show_hide_increase_decrease_cost_buttons(
     mut pathfinding_algorithm_changed_event_reader: EventReader<PathfindingAlgorithmChangedEvent>,
     mut increase_cost_button_query: Query<
@@ -864,22 +1903,57 @@ pub fn show_hide_increase_decrease_cost_buttons(
     game_state: Res<GameState>,
 ) {
     for _ in pathfinding_algorithm_changed_event_reader.iter() {
-        match game_state.pathfinding_algorithm {
-            PathfindingAlgorithm::BFS | PathfindingAlgorithm::Jps => {
-                for mut visibility in increase_cost_button_query.iter_mut() {
-                    visibility.is_visible = false;
-                }
-                for mut visibility in decrease_cost_button_query.iter_mut() {
-                    visibility.is_visible = false;
-                }
+        let is_visible = game_state.pathfinding_algorithm.uses_costs();
+        for mut visibility in increase_cost_button_query.iter_mut() {
+            visibility.is_visible = is_visible;
+        }
+        for mut visibility in decrease_cost_button_query.iter_mut() {
+            visibility.is_visible = is_visible;
+        }
+    }
+}
+
+pub fn rewind_button_system(
+    mut rewind_event_writer: EventWriter<RewindEvent>,
+    mut rewind_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<RewindButton>),
+    >,
+) {
+    for (interaction, mut color) in rewind_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                rewind_event_writer.send(RewindEvent {});
             }
-            _ => {
-                for mut visibility in increase_cost_button_query.iter_mut() {
-                    visibility.is_visible = true;
-                }
-                for mut visibility in decrease_cost_button_query.iter_mut() {
-                    visibility.is_visible = true;
-                }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn step_back_button_system(
+    mut step_back_event_writer: EventWriter<StepBackEvent>,
+    mut step_back_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<StepBackButton>),
+    >,
+) {
+    for (interaction, mut color) in step_back_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                step_back_event_writer.send(StepBackEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
             }
         }
     }
@@ -908,6 +1982,127 @@ pub fn step_button_system(
     }
 }
 
+pub fn play_button_system(
+    mut toggle_play_event_writer: EventWriter<TogglePlayEvent>,
+    mut play_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<PlayButton>),
+    >,
+) {
+    for (interaction, mut color) in play_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                toggle_play_event_writer.send(TogglePlayEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn playback_speed_button_system(
+    mut cycle_playback_speed_event_writer: EventWriter<CyclePlaybackSpeedEvent>,
+    mut playback_speed_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<PlaybackSpeedButton>),
+    >,
+) {
+    for (interaction, mut color) in playback_speed_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                cycle_playback_speed_event_writer.send(CyclePlaybackSpeedEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+// Playback stops from several systems (reset, edits, solving), so follow the resource itself
+pub fn update_playback_text_system(
+    mut play_text_query: Query<&mut Text, (With<PlayText>, Without<PlaybackSpeedText>)>,
+    mut playback_speed_text_query: Query<&mut Text, (With<PlaybackSpeedText>, Without<PlayText>)>,
+    playback: Res<SearchPlayback>,
+) {
+    if !playback.is_changed() {
+        return;
+    }
+    for mut text in &mut play_text_query {
+        text.sections[0].value = if playback.playing {
+            "Pause".to_string()
+        } else {
+            "Play".to_string()
+        };
+    }
+    for mut text in &mut playback_speed_text_query {
+        text.sections[0].value = format!("Speed: {}/s", playback.expansions_per_second());
+    }
+}
+
+// Not filtered on `Changed<Interaction>`: the bar stays clicked while the mouse is held, and
+// following the cursor every frame lets it be dragged
+pub fn timeline_bar_system(
+    mut seek_event_writer: EventWriter<SeekEvent>,
+    timeline_bar_query: Query<(&Interaction, &Node, &GlobalTransform), With<TimelineBar>>,
+    windows: Res<Windows>,
+) {
+    let cursor = match windows.get_primary().and_then(Window::cursor_position) {
+        Some(cursor) => cursor,
+        None => return,
+    };
+    for (interaction, node, transform) in timeline_bar_query.iter() {
+        if *interaction == Interaction::Clicked && node.size.x > 0.0 {
+            let left = transform.translation().x - node.size.x / 2.0;
+            seek_event_writer.send(SeekEvent {
+                fraction: (cursor.x - left) / node.size.x,
+            });
+        }
+    }
+}
+
+pub fn update_timeline_system(
+    mut timeline_fill_query: Query<&mut Style, With<TimelineFill>>,
+    mut timeline_text_query: Query<&mut Text, With<TimelineText>>,
+    playback: Res<SearchPlayback>,
+) {
+    if !playback.is_changed() {
+        return;
+    }
+    let (position, length, complete) = playback
+        .timeline
+        .as_ref()
+        .map_or((0, 0, false), |timeline| {
+            (timeline.position(), timeline.len(), timeline.is_complete())
+        });
+
+    let played = if length == 0 {
+        0.0
+    } else {
+        position as f32 / length as f32
+    };
+    for mut style in &mut timeline_fill_query {
+        style.size.width = Val::Percent(played * 100.0);
+    }
+    for mut text in &mut timeline_text_query {
+        // the search only runs as far as it was played, so the end is not known yet
+        text.sections[0].value = if complete {
+            format!("{} / {}", position, length)
+        } else {
+            format!("{} / {}+", position, length)
+        };
+    }
+}
+
 pub fn solve_button_system(
     mut solve_event_writer: EventWriter<SolveEvent>,
     mut solve_button_query: Query<
@@ -931,6 +2126,109 @@ pub fn solve_button_system(
     }
 }
 
+pub fn agent_button_system(
+    mut spawn_agent_event_writer: EventWriter<SpawnAgentEvent>,
+    mut agent_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<AgentButton>),
+    >,
+) {
+    for (interaction, mut color) in agent_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                spawn_agent_event_writer.send(SpawnAgentEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn compare_button_system(
+    mut compare_event_writer: EventWriter<CompareEvent>,
+    mut compare_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<CompareButton>),
+    >,
+) {
+    for (interaction, mut color) in compare_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                compare_event_writer.send(CompareEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn update_comparison_panel_system(
+    mut map_updated_event_reader: EventReader<MapUpdatedEvent>,
+    mut comparison_panel_query: Query<&mut Style, With<ComparisonPanel>>,
+    mut comparison_text_query: Query<&mut Text, With<ComparisonText>>,
+    game_state: Res<GameState>,
+) {
+    if map_updated_event_reader.iter().last().is_none() {
+        return;
+    }
+    for mut style in &mut comparison_panel_query {
+        style.display = if game_state.comparison.is_empty() {
+            Display::None
+        } else {
+            Flex
+        };
+    }
+
+    for mut text in &mut comparison_text_query {
+        let style = text.sections[0].style.clone();
+        let mut sections = vec![TextSection::new(
+            "Algorithm: cost, length, expanded, time",
+            style.clone(),
+        )];
+        for compared in &game_state.comparison {
+            let result = &compared.result;
+            // searches that give up optimality say by how much at most
+            let bound = result
+                .bound
+                .map(|bound| format!(" (<= {:.2}x)", bound))
+                .unwrap_or_default();
+            let numbers = match &result.path {
+                Some(path) => format!(
+                    "{:.2}{}, {:.2}, {}, {:.2?}",
+                    result.cost.unwrap_or_default() as f32 / STRAIGHT_COST as f32,
+                    bound,
+                    euclidean_length(path),
+                    result.expanded_count(),
+                    result.elapsed
+                ),
+                None => format!(
+                    "no path, {} expanded, {:.2?}",
+                    result.expanded_count(),
+                    result.elapsed
+                ),
+            };
+            sections.push(TextSection::new(
+                format!("\n{}: {}", compared.algorithm.name(), numbers),
+                TextStyle {
+                    color: comparison_color(compared.algorithm),
+                    ..style.clone()
+                },
+            ));
+        }
+        text.sections = sections;
+    }
+}
+
 pub fn reset_button_system(
     mut reset_event_writer: EventWriter<ResetEvent>,
     mut reset_button_query: Query<
@@ -977,6 +2275,410 @@ pub fn clear_button_system(
     }
 }
 
+pub fn save_button_system(
+    mut save_map_event_writer: EventWriter<SaveMapEvent>,
+    mut save_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<SaveButton>),
+    >,
+) {
+    for (interaction, mut color) in save_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                save_map_event_writer.send(SaveMapEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn load_button_system(
+    mut load_map_event_writer: EventWriter<LoadMapEvent>,
+    mut load_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<LoadButton>),
+    >,
+) {
+    for (interaction, mut color) in load_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                load_map_event_writer.send(LoadMapEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn cost_model_button_system(
+    mut cycle_cost_model_event_writer: EventWriter<CycleCostModelEvent>,
+    mut cost_model_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<CostModelButton>),
+    >,
+) {
+    for (interaction, mut color) in cost_model_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                cycle_cost_model_event_writer.send(CycleCostModelEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn update_cost_model_text_system(
+    mut cost_model_changed_event_reader: EventReader<CostModelChangedEvent>,
+    mut cost_model_text_query: Query<&mut Text, With<CostModelText>>,
+    map: Res<Map>,
+) {
+    for _ in cost_model_changed_event_reader.iter() {
+        for mut text in &mut cost_model_text_query {
+            text.sections[0].value = map.cost_model.name().to_string();
+        }
+    }
+}
+
+pub fn weight_button_system(
+    mut cycle_weight_event_writer: EventWriter<CycleWeightEvent>,
+    mut weight_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<WeightButton>),
+    >,
+) {
+    for (interaction, mut color) in weight_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                cycle_weight_event_writer.send(CycleWeightEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn update_weight_text_system(
+    mut weight_changed_event_reader: EventReader<WeightChangedEvent>,
+    mut weight_text_query: Query<&mut Text, With<WeightText>>,
+    game_state: Res<GameState>,
+) {
+    for _ in weight_changed_event_reader.iter() {
+        for mut text in &mut weight_text_query {
+            text.sections[0].value = format!("Weight: {:.2}", game_state.weight);
+        }
+    }
+}
+
+/// The weight only matters to the algorithms that inflate the heuristic.
+pub fn show_hide_weight_button(
+    mut pathfinding_algorithm_changed_event_reader: EventReader<PathfindingAlgorithmChangedEvent>,
+    mut weight_button_query: Query<&mut Visibility, With<WeightButton>>,
+    game_state: Res<GameState>,
+) {
+    for _ in pathfinding_algorithm_changed_event_reader.iter() {
+        let is_visible = game_state.pathfinding_algorithm.weighted();
+        for mut visibility in weight_button_query.iter_mut() {
+            visibility.is_visible = is_visible;
+        }
+    }
+}
+
+pub fn diagonals_button_system(
+    mut toggle_diagonals_event_writer: EventWriter<ToggleDiagonalsEvent>,
+    mut diagonals_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<DiagonalsButton>),
+    >,
+) {
+    for (interaction, mut color) in diagonals_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                toggle_diagonals_event_writer.send(ToggleDiagonalsEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn update_diagonals_text_system(
+    mut diagonals_changed_event_reader: EventReader<DiagonalsChangedEvent>,
+    mut diagonals_text_query: Query<&mut Text, With<DiagonalsText>>,
+    map: Res<Map>,
+) {
+    for _ in diagonals_changed_event_reader.iter() {
+        for mut text in &mut diagonals_text_query {
+            text.sections[0].value = if map.allow_diagonals {
+                "8-Way".to_string()
+            } else {
+                "4-Way".to_string()
+            };
+        }
+    }
+}
+
+pub fn node_stats_button_system(
+    mut toggle_node_stats_event_writer: EventWriter<ToggleNodeStatsEvent>,
+    mut node_stats_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<NodeStatsButton>),
+    >,
+) {
+    for (interaction, mut color) in node_stats_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                toggle_node_stats_event_writer.send(ToggleNodeStatsEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn update_node_stats_text_system(
+    mut node_stats_changed_event_reader: EventReader<NodeStatsChangedEvent>,
+    mut node_stats_text_query: Query<&mut Text, With<NodeStatsText>>,
+    game_state: Res<GameState>,
+) {
+    for _ in node_stats_changed_event_reader.iter() {
+        for mut text in &mut node_stats_text_query {
+            text.sections[0].value = if game_state.show_node_stats {
+                "Stats: On".to_string()
+            } else {
+                "Stats: Off".to_string()
+            };
+        }
+    }
+}
+
+pub fn flow_field_button_system(
+    mut toggle_flow_field_event_writer: EventWriter<ToggleFlowFieldEvent>,
+    mut flow_field_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<FlowFieldButton>),
+    >,
+) {
+    for (interaction, mut color) in flow_field_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                toggle_flow_field_event_writer.send(ToggleFlowFieldEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn update_flow_field_text_system(
+    mut flow_field_changed_event_reader: EventReader<FlowFieldChangedEvent>,
+    mut flow_field_text_query: Query<&mut Text, With<FlowFieldText>>,
+    game_state: Res<GameState>,
+) {
+    for _ in flow_field_changed_event_reader.iter() {
+        for mut text in &mut flow_field_text_query {
+            text.sections[0].value = if game_state.show_flow_field {
+                "Flow: On".to_string()
+            } else {
+                "Flow: Off".to_string()
+            };
+        }
+    }
+}
+
+pub fn heat_map_button_system(
+    mut cycle_heat_map_event_writer: EventWriter<CycleHeatMapEvent>,
+    mut heat_map_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<HeatMapButton>),
+    >,
+) {
+    for (interaction, mut color) in heat_map_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                cycle_heat_map_event_writer.send(CycleHeatMapEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn update_heat_map_text_system(
+    mut heat_map_changed_event_reader: EventReader<HeatMapChangedEvent>,
+    mut heat_map_text_query: Query<&mut Text, With<HeatMapText>>,
+    game_state: Res<GameState>,
+) {
+    for _ in heat_map_changed_event_reader.iter() {
+        for mut text in &mut heat_map_text_query {
+            text.sections[0].value = format!("Heat: {}", game_state.heat_map.name());
+        }
+    }
+}
+
+pub fn smoothing_button_system(
+    mut cycle_smoothing_event_writer: EventWriter<CycleSmoothingEvent>,
+    mut smoothing_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<SmoothingButton>),
+    >,
+) {
+    for (interaction, mut color) in smoothing_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                cycle_smoothing_event_writer.send(CycleSmoothingEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn update_smoothing_text_system(
+    mut smoothing_changed_event_reader: EventReader<SmoothingChangedEvent>,
+    mut smoothing_text_query: Query<&mut Text, With<SmoothingText>>,
+    game_state: Res<GameState>,
+) {
+    for _ in smoothing_changed_event_reader.iter() {
+        for mut text in &mut smoothing_text_query {
+            text.sections[0].value = format!("Smooth: {}", game_state.smoothing.name());
+        }
+    }
+}
+
+// The ends of the scale follow the search, so this runs on every map update
+pub fn update_heat_map_legend_system(
+    mut map_updated_event_reader: EventReader<MapUpdatedEvent>,
+    mut heat_map_legend_query: Query<&mut Style, With<HeatMapLegend>>,
+    mut heat_map_legend_text_query: Query<(
+        &mut Text,
+        Option<&HeatMapLegendTitle>,
+        Option<&HeatMapLegendLowText>,
+        Option<&HeatMapLegendHighText>,
+    )>,
+    game_state: Res<GameState>,
+) {
+    if map_updated_event_reader.iter().last().is_none() {
+        return;
+    }
+    for mut style in &mut heat_map_legend_query {
+        style.display = if game_state.heat_map == HeatMapMode::Off {
+            Display::None
+        } else {
+            Flex
+        };
+    }
+
+    let nodes = &game_state.expanded_nodes;
+    let (title, low, high) = match game_state.heat_map {
+        HeatMapMode::Off => return,
+        HeatMapMode::ExpansionOrder => (
+            "Expansion order".to_string(),
+            "1".to_string(),
+            nodes.len().max(1).to_string(),
+        ),
+        HeatMapMode::CostToCome => {
+            let algorithm = game_state.pathfinding_algorithm;
+            let max_g = nodes.iter().map(|node| node.g).max().unwrap_or(0);
+            (
+                "Cost from start (g)".to_string(),
+                format_cost(0, algorithm),
+                format_cost(max_g, algorithm),
+            )
+        }
+    };
+    for (mut text, is_title, is_low, is_high) in &mut heat_map_legend_text_query {
+        if is_title.is_some() {
+            text.sections[0].value = title.clone();
+        } else if is_low.is_some() {
+            text.sections[0].value = low.clone();
+        } else if is_high.is_some() {
+            text.sections[0].value = high.clone();
+        }
+    }
+}
+
+pub fn corner_cutting_button_system(
+    mut cycle_corner_cutting_event_writer: EventWriter<CycleCornerCuttingEvent>,
+    mut corner_cutting_button_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<CornerCuttingButton>),
+    >,
+) {
+    for (interaction, mut color) in corner_cutting_button_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                cycle_corner_cutting_event_writer.send(CycleCornerCuttingEvent {});
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+pub fn update_corner_cutting_text_system(
+    mut corner_cutting_changed_event_reader: EventReader<CornerCuttingChangedEvent>,
+    mut corner_cutting_text_query: Query<&mut Text, With<CornerCuttingText>>,
+    map: Res<Map>,
+) {
+    for _ in corner_cutting_changed_event_reader.iter() {
+        for mut text in &mut corner_cutting_text_query {
+            text.sections[0].value = map.corner_cutting.name().to_string();
+        }
+    }
+}
+
 pub fn cycle_algorithm_left_button_system(
     mut cycle_algorithm_left_event_writer: EventWriter<CycleAlgorithmLeftEvent>,
     mut cycle_algorithm_left_button_query: Query<
@@ -1032,34 +2734,16 @@ pub fn cycle_algorithm_selection_system(
     game_state: Res<GameState>,
 ) {
     for _ in cycle_algorithm_left_event_reader.iter() {
-        let new_pathfinding_algorithm;
-        match game_state.pathfinding_algorithm {
-            PathfindingAlgorithm::BFS => new_pathfinding_algorithm = PathfindingAlgorithm::Jps,
-            PathfindingAlgorithm::Jps => new_pathfinding_algorithm = PathfindingAlgorithm::AStar,
-            PathfindingAlgorithm::AStar => {
-                new_pathfinding_algorithm = PathfindingAlgorithm::Dijkstra
-            }
-            PathfindingAlgorithm::Dijkstra => new_pathfinding_algorithm = PathfindingAlgorithm::BFS,
-        }
         pathfinding_algorithm_selection_changed_event_writer.send(
             PathfindingAlgorithmSelectionChangedEvent {
-                pathfinding_algorithm: new_pathfinding_algorithm,
+                pathfinding_algorithm: game_state.pathfinding_algorithm.previous(),
             },
         );
     }
     for _ in cycle_algorithm_right_event_reader.iter() {
-        let new_pathfinding_algorithm;
-        match game_state.pathfinding_algorithm {
-            PathfindingAlgorithm::BFS => new_pathfinding_algorithm = PathfindingAlgorithm::Dijkstra,
-            PathfindingAlgorithm::Dijkstra => {
-                new_pathfinding_algorithm = PathfindingAlgorithm::AStar
-            }
-            PathfindingAlgorithm::AStar => new_pathfinding_algorithm = PathfindingAlgorithm::Jps,
-            PathfindingAlgorithm::Jps => new_pathfinding_algorithm = PathfindingAlgorithm::BFS,
-        }
         pathfinding_algorithm_selection_changed_event_writer.send(
             PathfindingAlgorithmSelectionChangedEvent {
-                pathfinding_algorithm: new_pathfinding_algorithm,
+                pathfinding_algorithm: game_state.pathfinding_algorithm.next(),
             },
         );
     }
@@ -1072,18 +2756,7 @@ pub fn update_current_algorithm_text_system(
 ) {
     for _ in pathfinding_algorithm_changed_event_reader.iter() {
         for mut text in &mut current_algorithm_text_query {
-            match game_state.pathfinding_algorithm {
-                PathfindingAlgorithm::AStar => {
-                    text.sections[0].value = "AStar".to_string();
-                }
-                PathfindingAlgorithm::BFS => {
-                    text.sections[0].value = "BFS".to_string();
-                }
-                PathfindingAlgorithm::Dijkstra => {
-                    text.sections[0].value = "Dijkstra".to_string();
-                }
-                PathfindingAlgorithm::Jps => text.sections[0].value = "Jps".to_string(),
-            }
+            text.sections[0].value = game_state.pathfinding_algorithm.name().to_string();
         }
     }
 }
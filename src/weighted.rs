@@ -0,0 +1,506 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    BestFirstSearch, ExpandedNode, Expander, Map, Pathfinder, Position, SearchResult,
+    SearchSnapshot, SearchStatus, SearchSteps,
+};
+
+/// Heuristic weight of weighted A* and ARA* when none is picked.
+pub const DEFAULT_WEIGHT: f64 = 2.0;
+
+/// How much ARA* lowers its weight after each path it finds.
+pub const ARA_WEIGHT_STEP: f64 = 0.5;
+
+/// Search time ARA* may spend on improving its path, time between steps does not count.
+pub const ARA_TIME_BUDGET: Duration = Duration::from_millis(20);
+
+/// `h` times `weight`, in the fixed point units of the costs.
+fn weighted(h: i32, weight: f64) -> i32 {
+    (f64::from(h) * weight) as i32
+}
+
+pub struct WeightedAStar {
+    /// How much the heuristic is inflated.
+    pub weight: f64,
+}
+
+impl Default for WeightedAStar {
+    fn default() -> Self {
+        WeightedAStar {
+            weight: DEFAULT_WEIGHT,
+        }
+    }
+}
+
+impl Pathfinder for WeightedAStar {
+    fn name(&self) -> &'static str {
+        "Weighted A*"
+    }
+
+    fn weighted(&self) -> bool {
+        true
+    }
+
+    fn steps(&self, map: &Map, start: Position, goal: Position) -> Box<dyn SearchSteps> {
+        let expander = WeightedExpander {
+            weight: self.weight,
+        };
+        Box::new(BestFirstSearch::new(map, expander, start, goal))
+    }
+}
+
+/// Expands into neighbouring tiles like A*, with the heuristic inflated by `weight`.
+///
+/// Nodes are not opened again once expanded, which still keeps the path within `weight` times
+/// the optimal cost as the heuristic is consistent.
+pub struct WeightedExpander {
+    pub weight: f64,
+}
+
+impl Expander for WeightedExpander {
+    fn successors(
+        &self,
+        map: &Map,
+        node: Position,
+        _parent: Option<Position>,
+        _goal: Position,
+        generated: &mut Vec<Position>,
+    ) -> Vec<(Position, i32)> {
+        map.get_successors(&node)
+            .into_iter()
+            .map(|successor| {
+                generated.push(successor.position);
+                (successor.position, successor.cost)
+            })
+            .collect()
+    }
+
+    fn heuristic(&self, map: &Map, node: &Position, goal: &Position) -> i32 {
+        weighted(map.heuristic(node, goal), self.weight)
+    }
+
+    fn bound(&self) -> Option<f64> {
+        Some(self.weight.max(1.0))
+    }
+}
+
+pub struct AraStar {
+    /// Weight of the first round.
+    pub weight: f64,
+    /// How much the weight drops after each path, down to `1.0`.
+    pub weight_step: f64,
+    /// Search time allowed for improving the first path.
+    pub time_budget: Duration,
+}
+
+impl Default for AraStar {
+    fn default() -> Self {
+        AraStar {
+            weight: DEFAULT_WEIGHT,
+            weight_step: ARA_WEIGHT_STEP,
+            time_budget: ARA_TIME_BUDGET,
+        }
+    }
+}
+
+impl Pathfinder for AraStar {
+    fn name(&self) -> &'static str {
+        "ARA*"
+    }
+
+    fn weighted(&self) -> bool {
+        true
+    }
+
+    fn steps(&self, map: &Map, start: Position, goal: Position) -> Box<dyn SearchSteps> {
+        Box::new(AraSearch::new(
+            map,
+            start,
+            goal,
+            self.weight,
+            self.weight_step,
+            self.time_budget,
+        ))
+    }
+}
+
+/// Anytime Repairing A*: a weighted A* that, once it has a path, lowers the weight and searches
+/// again for a better one, reusing everything it found before.
+///
+/// Each round only expands nodes whose cost went down since they were last expanded. Between
+/// rounds the nodes that were improved after being expanded are opened again. The search stops
+/// with the best path so far once the weight is `1.0` or the time budget is spent, and reports how
+/// far from optimal that path can be.
+pub struct AraSearch {
+    goal: Position,
+    width: i32,
+    weight: f64,
+    weight_step: f64,
+    time_budget: Duration,
+    elapsed: Duration,
+    open: BinaryHeap<Reverse<(i32, i32, usize, Position)>>,
+    in_open: Vec<bool>,
+    /// Expanded in the current round.
+    closed: Vec<bool>,
+    /// Improved after being expanded in the current round, opened again in the next one.
+    inconsistent: Vec<Position>,
+    opened: usize,
+    g: Vec<Option<i32>>,
+    parents: Vec<Option<Position>>,
+    expanded: Vec<ExpandedNode>,
+    generated: Vec<Position>,
+    current: Option<ExpandedNode>,
+    path: Option<Vec<Position>>,
+    /// How much more than optimal the last path found may cost.
+    bound: Option<f64>,
+    status: SearchStatus,
+}
+
+impl AraSearch {
+    #[must_use]
+    pub fn new(
+        map: &Map,
+        start: Position,
+        goal: Position,
+        weight: f64,
+        weight_step: f64,
+        time_budget: Duration,
+    ) -> AraSearch {
+        let tiles = (map.width * map.height) as usize;
+        let mut search = AraSearch {
+            goal,
+            width: map.width,
+            weight: weight.max(1.0),
+            weight_step,
+            time_budget,
+            elapsed: Duration::ZERO,
+            open: BinaryHeap::new(),
+            in_open: vec![false; tiles],
+            closed: vec![false; tiles],
+            inconsistent: Vec::new(),
+            opened: 0,
+            g: vec![None; tiles],
+            parents: vec![None; tiles],
+            expanded: Vec::new(),
+            generated: Vec::new(),
+            current: None,
+            path: None,
+            bound: None,
+            status: SearchStatus::Searching,
+        };
+        if map.is_blocked(start.x(), start.y()) || map.is_blocked(goal.x(), goal.y()) {
+            search.status = SearchStatus::NotFound;
+        } else {
+            let index = search.index(start);
+            search.g[index] = Some(0);
+            search.open_node(map, start);
+        }
+        search
+    }
+
+    fn index(&self, node: Position) -> usize {
+        (node.y() * self.width + node.x()) as usize
+    }
+
+    fn open_node(&mut self, map: &Map, node: Position) {
+        let index = self.index(node);
+        let g = self.g[index].unwrap_or(0);
+        let h = weighted(map.heuristic(&node, &self.goal), self.weight);
+        self.in_open[index] = true;
+        self.open.push(Reverse((g + h, h, self.opened, node)));
+        self.opened += 1;
+    }
+
+    /// Drops entries of nodes that were expanded or improved since they were pushed.
+    fn skip_stale(&mut self) {
+        while let Some(Reverse((f, h, _, node))) = self.open.peek() {
+            let index = self.index(*node);
+            if self.in_open[index] && self.g[index].is_some_and(|g| g + h == *f) {
+                break;
+            }
+            self.open.pop();
+        }
+    }
+
+    /// Whether the round is over: no open node can lead to a path cheaper than the one to the
+    /// goal, as far as the weighted heuristic tells.
+    fn round_done(&mut self) -> bool {
+        self.skip_stale();
+        match (self.open.peek(), self.g[self.index(self.goal)]) {
+            (None, _) => true,
+            (Some(Reverse((f, _, _, _))), Some(goal_g)) => goal_g <= *f,
+            (Some(_), None) => false,
+        }
+    }
+
+    /// The goal's cost divided by the lowest `g + h` among the nodes left to look at, a bound on
+    /// how much the path can cost more than the optimal one.
+    fn bound(&self, map: &Map, goal_g: i32) -> f64 {
+        let lowest = self
+            .open
+            .iter()
+            .map(|Reverse((_, _, _, node))| *node)
+            .filter(|node| self.in_open[self.index(*node)])
+            .chain(self.inconsistent.iter().copied())
+            .filter_map(|node| {
+                let g = self.g[self.index(node)]?;
+                Some(g + map.heuristic(&node, &self.goal))
+            })
+            .min();
+        match lowest {
+            Some(lowest) if lowest > 0 => {
+                (f64::from(goal_g) / f64::from(lowest)).clamp(1.0, self.weight)
+            }
+            _ => 1.0,
+        }
+    }
+
+    fn back_trace(&self) -> Vec<Position> {
+        let mut path = vec![self.goal];
+        let mut node = self.goal;
+        while let Some(parent) = self.parents[self.index(node)] {
+            path.push(parent);
+            node = parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Keeps the path of the round that just ended and starts the next one with a lower weight,
+    /// or ends the search. Returns whether it goes on.
+    fn finish_round(&mut self, map: &Map) -> bool {
+        let Some(goal_g) = self.g[self.index(self.goal)] else {
+            self.status = SearchStatus::NotFound;
+            return false;
+        };
+        let bound = self.bound(map, goal_g);
+        self.path = Some(self.back_trace());
+        self.bound = Some(bound);
+        if bound <= 1.0 || self.weight <= 1.0 || self.elapsed >= self.time_budget {
+            self.status = SearchStatus::Found;
+            return false;
+        }
+
+        self.weight = (self.weight - self.weight_step).max(1.0);
+        // the open nodes and the inconsistent ones, keyed again with the new weight
+        let entries: Vec<Position> = self
+            .open
+            .drain()
+            .map(|Reverse((_, _, _, node))| node)
+            .collect();
+        let mut nodes: Vec<Position> = entries
+            .into_iter()
+            .filter(|node| self.in_open[self.index(*node)])
+            .collect();
+        nodes.append(&mut self.inconsistent);
+        self.in_open.iter_mut().for_each(|in_open| *in_open = false);
+        self.closed.iter_mut().for_each(|closed| *closed = false);
+        let mut seen = HashSet::new();
+        for node in nodes {
+            if seen.insert(node) {
+                self.open_node(map, node);
+            }
+        }
+        true
+    }
+}
+
+impl SearchSteps for AraSearch {
+    fn step(&mut self, map: &Map) -> SearchStatus {
+        if self.status.is_done() {
+            return self.status;
+        }
+        let now = Instant::now();
+        self.current = None;
+
+        while self.round_done() {
+            if !self.finish_round(map) {
+                self.elapsed += now.elapsed();
+                return self.status;
+            }
+        }
+
+        // `round_done` left an open node on top
+        if let Some(Reverse((_, h, _, node))) = self.open.pop() {
+            let index = self.index(node);
+            self.in_open[index] = false;
+            self.closed[index] = true;
+            let g = self.g[index].unwrap_or(0);
+            let expanded = ExpandedNode {
+                position: node,
+                g,
+                h,
+                parent: self.parents[index],
+                backward: false,
+            };
+            self.expanded.push(expanded);
+            self.current = Some(expanded);
+
+            for successor in map.get_successors(&node) {
+                let neighbour = successor.position;
+                self.generated.push(neighbour);
+                let neighbour_index = self.index(neighbour);
+                let ng = g + successor.cost;
+                if self.g[neighbour_index].is_some_and(|g| g <= ng) {
+                    continue;
+                }
+                self.g[neighbour_index] = Some(ng);
+                self.parents[neighbour_index] = Some(node);
+                if self.closed[neighbour_index] {
+                    self.inconsistent.push(neighbour);
+                } else {
+                    self.open_node(map, neighbour);
+                }
+            }
+        }
+        self.elapsed += now.elapsed();
+        self.status
+    }
+
+    fn status(&self) -> SearchStatus {
+        self.status
+    }
+
+    fn current(&self) -> Option<ExpandedNode> {
+        self.current
+    }
+
+    fn open(&self) -> Vec<Position> {
+        let mut seen = HashSet::new();
+        self.open
+            .iter()
+            .map(|Reverse((_, _, _, node))| *node)
+            .filter(|node| self.in_open[self.index(*node)] && seen.insert(*node))
+            .collect()
+    }
+
+    fn snapshot(&self) -> SearchSnapshot {
+        SearchSnapshot {
+            current: self.current.map(|node| node.position),
+            open: self.open(),
+            backward_open: Vec::new(),
+            closed: self.expanded.iter().map(|node| node.position).collect(),
+            expanded: self.expanded.clone(),
+            path: if self.status.is_done() {
+                self.path.clone()
+            } else {
+                None
+            },
+        }
+    }
+
+    fn into_result(self: Box<Self>, map: &Map) -> SearchResult {
+        let cost = self.path.as_ref().map(|path| map.path_cost(path));
+        SearchResult {
+            path: self.path,
+            cost,
+            bound: self.bound,
+            expanded: self.expanded.iter().map(|node| node.position).collect(),
+            expanded_nodes: self.expanded,
+            generated: self.generated,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{AraSearch, AraStar, WeightedAStar, ARA_WEIGHT_STEP, DEFAULT_WEIGHT};
+    use crate::{
+        random_pairs, solve, test_support::random_map, Pathfinder, PathfindingAlgorithm,
+        SearchSteps,
+    };
+
+    #[test]
+    fn costs_stay_within_the_bound() {
+        let pathfinders: [Box<dyn Pathfinder>; 3] = [
+            Box::new(WeightedAStar { weight: 1.5 }),
+            Box::new(WeightedAStar { weight: 3.0 }),
+            Box::new(AraStar::default()),
+        ];
+        for seed in 0..10 {
+            let map = random_map(24, 20, seed);
+            for (start, goal) in random_pairs(&map, 10, seed) {
+                let optimal = solve(&map, PathfindingAlgorithm::Dijkstra, start, goal)
+                    .cost
+                    .unwrap();
+                for pathfinder in &pathfinders {
+                    let result = pathfinder.find_path(&map, start, goal);
+                    let (cost, bound) = (result.cost.unwrap(), result.bound.unwrap());
+                    assert!(cost >= optimal);
+                    assert!(
+                        f64::from(cost) <= bound * f64::from(optimal),
+                        "{} from {start:?} to {goal:?}: {cost} over {bound} x {optimal}",
+                        pathfinder.name()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ara_bound_never_rises() {
+        let mut improved = false;
+        for seed in 0..10 {
+            let map = random_map(24, 20, seed);
+            for (start, goal) in random_pairs(&map, 10, seed) {
+                let mut search = AraSearch::new(
+                    &map,
+                    start,
+                    goal,
+                    DEFAULT_WEIGHT,
+                    ARA_WEIGHT_STEP,
+                    Duration::MAX,
+                );
+                let mut last: Option<(f64, i32)> = None;
+                loop {
+                    let done = search.step(&map).is_done();
+                    if let (Some(bound), Some(path)) = (search.bound, &search.path) {
+                        let cost = map.path_cost(path);
+                        if let Some((last_bound, last_cost)) = last {
+                            assert!(bound <= last_bound, "{start:?} to {goal:?}");
+                            assert!(cost <= last_cost, "{start:?} to {goal:?}");
+                            improved |= cost < last_cost;
+                        }
+                        last = Some((bound, cost));
+                    }
+                    if done {
+                        break;
+                    }
+                }
+            }
+        }
+        // the pairs are only worth checking if ARA* improved some of its first paths
+        assert!(improved);
+    }
+
+    #[test]
+    fn ara_without_a_time_budget_ends_optimal() {
+        let ara = AraStar {
+            time_budget: Duration::MAX,
+            ..AraStar::default()
+        };
+        for seed in 0..10 {
+            let map = random_map(24, 20, seed);
+            for (start, goal) in random_pairs(&map, 10, seed) {
+                let result = ara.find_path(&map, start, goal);
+                assert_eq!(result.bound, Some(1.0), "{start:?} to {goal:?}");
+                assert_eq!(
+                    result.cost,
+                    solve(&map, PathfindingAlgorithm::Dijkstra, start, goal).cost,
+                    "{start:?} to {goal:?}"
+                );
+            }
+        }
+    }
+}
+
+// References
+// 1. Pohl, "Heuristic Search Viewed as Path Finding in a Graph" (1970), for Weighted A*
+// 2. Likhachev, Gordon and Thrun, "ARA*: Anytime A* with Provable Bounds on Sub-Optimality" (2003)